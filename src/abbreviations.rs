@@ -0,0 +1,69 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+// languages of prose documents, abbreviations are expanded in them only
+pub const PROSE_LANGUAGES: [&str; 10] = [
+    "markdown",
+    "text",
+    "plaintext",
+    "git-commit",
+    "gitcommit",
+    "latex",
+    "typst",
+    "rst",
+    "org",
+    "asciidoc",
+];
+
+/// Abbreviation to expansion pairs, like `btw = "by the way"`
+#[derive(Default)]
+pub struct Abbreviations {
+    // lowercase abbreviation -> expansion
+    inner: HashMap<String, String>,
+}
+
+impl Abbreviations {
+    pub fn from_path(path: &std::path::Path) -> Result<Self> {
+        tracing::info!("Try load abbreviations from: {path:?}");
+        let content = std::fs::read_to_string(path)?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let inner = toml::from_str::<HashMap<String, String>>(content)?
+            .into_iter()
+            .map(|(abbreviation, expansion)| (abbreviation.to_lowercase(), expansion))
+            .collect();
+        Ok(Self { inner })
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Expansion of typed abbreviation, following its case
+    pub fn lookup(&self, typed: &str) -> Option<String> {
+        let expansion = self.inner.get(&typed.to_lowercase())?;
+        Some(adapt_case(typed, expansion))
+    }
+}
+
+/// Follow case of typed text: `BTW` -> `BY THE WAY`, `Btw` -> `By the way`
+pub fn adapt_case(typed: &str, expansion: &str) -> String {
+    let mut chars = typed.chars();
+    if !chars.next().is_some_and(char::is_uppercase) {
+        return expansion.to_string();
+    }
+    if chars.clone().next().is_some() && chars.all(|c| !c.is_lowercase()) {
+        return expansion.to_uppercase();
+    }
+    let mut expansion = expansion.chars();
+    expansion
+        .next()
+        .map(|first| first.to_uppercase().chain(expansion).collect())
+        .unwrap_or_default()
+}
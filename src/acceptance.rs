@@ -0,0 +1,94 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// label -> accepted count
+type Counts = BTreeMap<String, u64>;
+
+/// Accepted completions counters by workspace and language, persisted as JSON
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AcceptanceStats {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    // workspace -> language -> counts
+    #[serde(default)]
+    pub workspaces: BTreeMap<String, BTreeMap<String, Counts>>,
+}
+
+impl AcceptanceStats {
+    /// Load counters from file, missing file is empty stats
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut stats = if path.exists() {
+            serde_json::from_str::<Self>(&std::fs::read_to_string(path)?)?
+        } else {
+            Self::default()
+        };
+        stats.path = Some(path.to_path_buf());
+        Ok(stats)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, workspace: &str, language: &str, label: &str) {
+        *self
+            .workspaces
+            .entry(workspace.to_string())
+            .or_default()
+            .entry(language.to_string())
+            .or_default()
+            .entry(label.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn count(&self, workspace: &str, language: &str, label: &str) -> u64 {
+        self.workspaces
+            .get(workspace)
+            .and_then(|languages| languages.get(language))
+            .and_then(|counts| counts.get(label))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sort text placing accepted items first, most accepted at the top
+    pub fn sort_text(&self, workspace: &str, language: &str, label: &str) -> Option<String> {
+        let count = self.count(workspace, language, label);
+        (count > 0).then(|| {
+            format!(
+                "!{:06}{label}",
+                999_999u64.saturating_sub(count.min(999_999))
+            )
+        })
+    }
+
+    /// Counters as (workspace, language, label, count), most accepted first
+    pub fn top(&self) -> Vec<(&str, &str, &str, u64)> {
+        let mut top = self
+            .workspaces
+            .iter()
+            .flat_map(|(workspace, languages)| {
+                languages.iter().flat_map(move |(language, counts)| {
+                    counts.iter().map(move |(label, count)| {
+                        (
+                            workspace.as_str(),
+                            language.as_str(),
+                            label.as_str(),
+                            *count,
+                        )
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        top.sort_by(|a, b| b.3.cmp(&a.3).then((a.0, a.1, a.2).cmp(&(b.0, b.1, b.2))));
+        top
+    }
+}
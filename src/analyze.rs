@@ -0,0 +1,107 @@
+use crate::split_words;
+use anyhow::Result;
+use std::collections::HashMap;
+
+// skip generated and vendored content
+const SKIP_DIRS: [&str; 4] = ["target", "node_modules", "vendor", "dist"];
+
+// larger files are most likely generated
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct WordStats {
+    pub count: usize,
+    pub files: usize,
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+    pub files: usize,
+    /// Words sorted by count desc
+    pub words: Vec<(String, WordStats)>,
+}
+
+impl Analysis {
+    pub fn top(&self, n: usize) -> impl Iterator<Item = &(String, WordStats)> {
+        self.words.iter().take(n)
+    }
+
+    /// Words found in at least half of the files
+    pub fn stop_words(&self, n: usize) -> impl Iterator<Item = &(String, WordStats)> {
+        let min_files = (self.files / 2).max(2);
+        self.words
+            .iter()
+            .filter(move |(_, stats)| stats.files >= min_files)
+            .take(n)
+    }
+
+    /// Frequent words useless for completion: too short to save typing or hash-like
+    pub fn suggested_exclude(&self, n: usize) -> impl Iterator<Item = &String> {
+        self.words
+            .iter()
+            .filter(|(word, stats)| {
+                let len = word.chars().count();
+                let digits = word.chars().filter(char::is_ascii_digit).count();
+                (len <= 3 && stats.count > 1) || (len >= 8 && digits * 3 >= len)
+            })
+            .map(|(word, _)| word)
+            .take(n)
+    }
+}
+
+fn collect_files(path: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if !SKIP_DIRS.contains(&name.as_ref()) {
+                collect_files(&path, files);
+            }
+        } else if entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_SIZE) {
+            files.push(path);
+        }
+    }
+}
+
+pub fn analyze(path: &std::path::Path) -> Result<Analysis> {
+    let mut files = Vec::new();
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    } else {
+        collect_files(path, &mut files);
+    }
+
+    let mut words: HashMap<String, WordStats> = HashMap::new();
+    let mut analyzed = 0;
+    for file in files {
+        // skip binary files
+        let Ok(content) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        analyzed += 1;
+
+        let mut seen = std::collections::HashSet::new();
+        for word in split_words(&content).filter(|w| !w.chars().all(|c| c.is_ascii_digit())) {
+            let stats = words.entry(word.to_string()).or_default();
+            stats.count += 1;
+            if seen.insert(word) {
+                stats.files += 1;
+            }
+        }
+    }
+
+    let mut words = words.into_iter().collect::<Vec<_>>();
+    words.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(Analysis {
+        files: analyzed,
+        words,
+    })
+}
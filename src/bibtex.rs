@@ -0,0 +1,424 @@
+use ropey::Rope;
+use std::collections::HashMap;
+
+// limit of workspace .bib files to read for entry keys
+pub const MAX_BIB_FILES: usize = 100;
+
+// nested `crossref`/`xdata` parents deeper than it are treated as cycle
+const MAX_INHERIT_DEPTH: usize = 4;
+
+pub const BIBTEX_LANGUAGES: [&str; 2] = ["bibtex", "bib"];
+
+// BibTeX and BibLaTeX entry types
+pub const ENTRY_TYPES: [(&str, &str); 18] = [
+    ("article", "An article from a journal or magazine"),
+    ("book", "A book with an explicit publisher"),
+    (
+        "booklet",
+        "A work that is printed and bound, but without a named publisher",
+    ),
+    ("conference", "The same as inproceedings"),
+    (
+        "inbook",
+        "A part of a book, e.g. a chapter, section or range of pages",
+    ),
+    ("incollection", "A part of a book having its own title"),
+    ("inproceedings", "An article in a conference proceedings"),
+    ("manual", "Technical documentation"),
+    ("mastersthesis", "A Master's thesis"),
+    ("misc", "For use when nothing else fits"),
+    ("online", "An online resource"),
+    ("phdthesis", "A Ph.D. thesis"),
+    ("proceedings", "The proceedings of a conference"),
+    ("report", "A technical report"),
+    ("software", "Computer software"),
+    (
+        "techreport",
+        "A report published by a school or other institution",
+    ),
+    ("thesis", "A thesis written for an educational institution"),
+    (
+        "unpublished",
+        "A document having an author and title, but not formally published",
+    ),
+];
+
+pub const FIELDS: [&str; 30] = [
+    "address",
+    "annote",
+    "author",
+    "booktitle",
+    "chapter",
+    "crossref",
+    "date",
+    "doi",
+    "edition",
+    "editor",
+    "eprint",
+    "howpublished",
+    "institution",
+    "isbn",
+    "issn",
+    "journal",
+    "keywords",
+    "month",
+    "note",
+    "number",
+    "organization",
+    "pages",
+    "publisher",
+    "school",
+    "series",
+    "title",
+    "type",
+    "url",
+    "volume",
+    "year",
+];
+
+// predefined `@string` abbreviations
+pub const MONTHS: [(&str, &str); 12] = [
+    ("jan", "January"),
+    ("feb", "February"),
+    ("mar", "March"),
+    ("apr", "April"),
+    ("may", "May"),
+    ("jun", "June"),
+    ("jul", "July"),
+    ("aug", "August"),
+    ("sep", "September"),
+    ("oct", "October"),
+    ("nov", "November"),
+    ("dec", "December"),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum BibContext<'a> {
+    EntryType(&'a str),
+    Field(&'a str),
+    Month(&'a str),
+}
+
+/// Bibliography entry, fields are resolved from `@string` abbreviations and inherited
+/// from `crossref`/`xdata` parents
+#[derive(Debug, Clone, PartialEq)]
+pub struct BibEntry {
+    pub entry_type: String,
+    pub key: String,
+    // line of `@type{key,`
+    pub line: usize,
+    pub fields: HashMap<String, String>,
+}
+
+impl BibEntry {
+    /// Short description like `The TeXbook. Knuth, 1984`, when entry has title
+    pub fn summary(&self) -> Option<String> {
+        let field = |name: &str| self.fields.get(name).filter(|v| !v.is_empty());
+        let title = field("title")?;
+        let container = field("journal")
+            .or_else(|| field("journaltitle"))
+            .or_else(|| field("booktitle"))
+            .map(|c| format!(". In: {c}"))
+            .unwrap_or_default();
+        let year = field("year")
+            .map(String::as_str)
+            .or_else(|| field("date").and_then(|d| d.get(..4)));
+        let by = match (field("author").or_else(|| field("editor")), year) {
+            (Some(author), Some(year)) => format!(". {author}, {year}"),
+            (Some(author), None) => format!(". {author}"),
+            (None, Some(year)) => format!(". {year}"),
+            (None, None) => String::new(),
+        };
+        Some(format!("{title}{container}{by}"))
+    }
+}
+
+/// Reader of entries, tracks line of current position
+struct Reader {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += 1;
+        if ch == '\n' {
+            self.line += 1;
+        }
+        Some(ch)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        let mut ident = String::new();
+        while let Some(ch) = self
+            .peek()
+            .filter(|ch| !ch.is_whitespace() && !"{}()=,#\"@".contains(*ch))
+        {
+            ident.push(ch);
+            self.bump();
+        }
+        ident
+    }
+
+    /// Content up to closing brace (opening one is consumed), nested braces are kept
+    fn braced(&mut self) -> String {
+        let mut depth = 0;
+        let mut value = String::new();
+        while let Some(ch) = self.bump() {
+            match ch {
+                '{' => depth += 1,
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            value.push(ch);
+        }
+        value
+    }
+
+    /// Content up to closing quote (opening one is consumed), quotes in braces are kept
+    fn quoted(&mut self) -> String {
+        let mut depth = 0;
+        let mut value = String::new();
+        while let Some(ch) = self.bump() {
+            match ch {
+                '"' if depth == 0 => break,
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            value.push(ch);
+        }
+        value
+    }
+
+    /// Field value, parts concatenated by `#`, abbreviations are expanded
+    fn value(&mut self, strings: &HashMap<String, String>) -> String {
+        let mut value = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => {
+                    self.bump();
+                    value.push_str(&self.braced());
+                }
+                Some('"') => {
+                    self.bump();
+                    value.push_str(&self.quoted());
+                }
+                Some(_) => {
+                    let name = self.ident();
+                    let lowercase = name.to_lowercase();
+                    match strings.get(&lowercase).map(String::as_str).or_else(|| {
+                        MONTHS
+                            .iter()
+                            .find(|(month, _)| *month == lowercase)
+                            .map(|(_, month)| *month)
+                    }) {
+                        Some(expanded) => value.push_str(expanded),
+                        None => value.push_str(&name),
+                    }
+                }
+                None => break,
+            }
+            self.skip_whitespace();
+            if self.peek() != Some('#') {
+                break;
+            }
+            self.bump();
+        }
+        value.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// `name = value` pairs up to entry end, which is consumed
+    fn fields(&mut self, close: char, strings: &HashMap<String, String>) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some(ch) if ch == close => {
+                    self.bump();
+                    break;
+                }
+                Some(',') => {
+                    self.bump();
+                    continue;
+                }
+                Some(_) => {}
+            }
+            let name = self.ident().to_lowercase();
+            self.skip_whitespace();
+            if name.is_empty() || self.peek() != Some('=') {
+                // broken field, skip char to move on
+                self.bump();
+                continue;
+            }
+            self.bump();
+            fields.push((name, self.value(strings)));
+        }
+        fields
+    }
+}
+
+/// Entries of bibliography with resolved `@string` abbreviations (defined before use,
+/// like BibTeX does) and `crossref`/`xdata` inheritance
+pub fn entries(text: &str) -> Vec<BibEntry> {
+    let mut reader = Reader {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 0,
+    };
+    let mut strings = HashMap::new();
+    let mut entries = Vec::new();
+
+    while let Some(ch) = reader.bump() {
+        if ch != '@' {
+            continue;
+        }
+        let line = reader.line;
+        let entry_type = reader.ident().to_lowercase();
+        reader.skip_whitespace();
+        let close = match reader.bump() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => continue,
+        };
+        match entry_type.as_str() {
+            "comment" | "preamble" => {
+                reader.braced();
+            }
+            "string" => {
+                for (name, value) in reader.fields(close, &strings) {
+                    strings.insert(name, value);
+                }
+            }
+            _ => {
+                reader.skip_whitespace();
+                let key = reader.ident();
+                if key.is_empty() {
+                    continue;
+                }
+                let fields = reader.fields(close, &strings).into_iter().collect();
+                entries.push(BibEntry {
+                    entry_type,
+                    key,
+                    line,
+                    fields,
+                });
+            }
+        }
+    }
+
+    let by_key = entries
+        .iter()
+        .map(|e| (e.key.to_lowercase(), e))
+        .collect::<HashMap<_, _>>();
+    let resolved = entries
+        .iter()
+        .map(|e| inherited(e, &by_key, 0))
+        .collect::<Vec<_>>();
+    entries
+        .into_iter()
+        .zip(resolved)
+        .map(|(entry, fields)| BibEntry { fields, ..entry })
+        .collect()
+}
+
+/// Fields of entry with missing ones taken from `xdata` and `crossref` parents,
+/// parent title becomes `booktitle` of `in*` entries (like `inproceedings` of `proceedings`)
+fn inherited(
+    entry: &BibEntry,
+    by_key: &HashMap<String, &BibEntry>,
+    depth: usize,
+) -> HashMap<String, String> {
+    let mut fields = entry.fields.clone();
+    if depth >= MAX_INHERIT_DEPTH {
+        return fields;
+    }
+    let parents = entry
+        .fields
+        .get("xdata")
+        .into_iter()
+        .flat_map(|keys| keys.split(','))
+        .chain(entry.fields.get("crossref").map(String::as_str))
+        .map(|key| key.trim().to_lowercase())
+        .collect::<Vec<_>>();
+    for parent in parents.iter().filter_map(|key| by_key.get(key)) {
+        for (name, value) in inherited(parent, by_key, depth + 1) {
+            let name = match name.as_str() {
+                "crossref" | "xdata" | "ids" => continue,
+                "title" if entry.entry_type.starts_with("in") => "booktitle".to_string(),
+                _ => name,
+            };
+            fields.entry(name).or_insert(value);
+        }
+    }
+    fields
+}
+
+/// Detect what is typed at the end of line prefix
+pub fn context<'a>(text: &Rope, line: usize, line_prefix: &'a str) -> Option<BibContext<'a>> {
+    let trimmed = line_prefix.trim_start();
+
+    if let Some(query) = trimmed.strip_prefix('@') {
+        return query
+            .chars()
+            .all(char::is_alphanumeric)
+            .then_some(BibContext::EntryType(query));
+    }
+
+    if let Some((field, value)) = trimmed.split_once('=') {
+        if !field.trim().eq_ignore_ascii_case("month") {
+            return None;
+        }
+        let query = value.trim_start().trim_start_matches(['{', '"']);
+        return query
+            .chars()
+            .all(char::is_alphanumeric)
+            .then_some(BibContext::Month(query));
+    }
+
+    if !trimmed.chars().all(char::is_alphanumeric) || !is_inside_entry(text, line, line_prefix) {
+        return None;
+    }
+    Some(BibContext::Field(trimmed))
+}
+
+// count braces from entry start up to cursor
+fn is_inside_entry(text: &Rope, line: usize, line_prefix: &str) -> bool {
+    let mut depth = 0i32;
+    for ch in line_prefix.chars().rev() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    for idx in (0..line).rev() {
+        let prev = text.line(idx).to_string();
+        for ch in prev.chars() {
+            match ch {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if prev.trim_start().starts_with('@') {
+            return depth > 0;
+        }
+    }
+    false
+}
@@ -0,0 +1,81 @@
+// styles in order of completion items
+pub const STYLES: [&str; 5] = [
+    "camelCase",
+    "PascalCase",
+    "snake_case",
+    "kebab-case",
+    "SCREAMING_SNAKE_CASE",
+];
+
+/// Identifier char, `-` joins kebab-case words
+pub fn char_is_identifier(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '-'
+}
+
+/// Split identifier into lowercase words by `_`, `-` and case changes, `parseHTTPRequest` is `parse http request`
+pub fn split_words(identifier: &str) -> Vec<String> {
+    let chars = identifier.chars().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, ch) in chars.iter().enumerate() {
+        if *ch == '_' || *ch == '-' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let boundary = ch.is_uppercase()
+            && prev.is_some_and(|prev| {
+                prev.is_lowercase()
+                    || prev.is_numeric()
+                    || (prev.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+            });
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(ch.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Identifier in style, None for unknown style
+pub fn convert(words: &[String], style: &str) -> Option<String> {
+    Some(match style {
+        "camelCase" => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+            .collect(),
+        "PascalCase" => words.iter().map(|w| capitalize(w)).collect(),
+        "snake_case" => words.join("_"),
+        "kebab-case" => words.join("-"),
+        "SCREAMING_SNAKE_CASE" => words.join("_").to_uppercase(),
+        _ => return None,
+    })
+}
+
+/// Identifier variants as (style, value), without the identifier itself, empty for single word
+pub fn variants(identifier: &str) -> Vec<(&'static str, String)> {
+    let words = split_words(identifier);
+    if words.len() < 2 {
+        return Vec::new();
+    }
+    STYLES
+        .iter()
+        .filter_map(|style| Some((*style, convert(&words, style)?)))
+        .filter(|(_, value)| value != identifier)
+        .collect()
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+// shell script language ids
+pub const SHELL_LANGUAGES: [&str; 5] = ["bash", "zsh", "fish", "sh", "shellscript"];
+
+// commands allowed to run with `--help` by default, read-only tools only
+pub const DEFAULT_COMMANDS: [&str; 8] = ["awk", "cut", "du", "find", "grep", "ls", "sed", "sort"];
+
+// help output hardly changes, cache it for long
+pub const HELP_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Command of the pipeline segment before cursor and typed flag prefix, like `ls` and `--al` for `cd x && ls --al`
+pub fn command_and_flag(before_cursor: &str) -> Option<(&str, &str)> {
+    let flag = before_cursor
+        .rsplit(char::is_whitespace)
+        .next()
+        .filter(|flag| flag.starts_with('-'))?;
+    let segment = before_cursor[..before_cursor.len() - flag.len()]
+        .rsplit(['|', ';', '&', '(', '`'])
+        .next()?;
+    let command = segment
+        .split_whitespace()
+        // skip `sudo`, `env` and `VAR=value` prefixes
+        .find(|word| {
+            !matches!(*word, "sudo" | "env" | "command" | "exec") && !word.contains('=')
+        })?;
+    Some((command, flag))
+}
+
+/// Allowed command name, found by `PATH` only: commands with path (like `./ls`)
+/// or shell metacharacters are never run
+pub fn is_allowed(command: &str, allowed: &[String]) -> bool {
+    !command.starts_with(['-', '.'])
+        && command
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && allowed.iter().any(|allowed| allowed == command)
+}
+
+/// Flags with descriptions from `--help` output lines like `  -a, --all    do not ignore entries`
+pub fn parse_flags(help: &str) -> Vec<(String, String)> {
+    let mut flags: Vec<(String, String)> = Vec::new();
+    for line in help.lines() {
+        let line = line.trim_start();
+        if !line.starts_with('-') {
+            continue;
+        }
+        // flags and description are separated by at least two spaces or tab
+        let (spec, description) = line
+            .find("  ")
+            .or_else(|| line.find('\t'))
+            .map(|i| (&line[..i], line[i..].trim()))
+            .unwrap_or((line, ""));
+        for word in spec.split([',', ' ', '|']) {
+            let flag = word
+                .split(['=', '[', '<'])
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches(['.', ':']);
+            let name = flag.trim_start_matches('-');
+            let valid = !name.is_empty()
+                && (1..=2).contains(&(flag.len() - name.len()))
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            if valid && !flags.iter().any(|(f, _)| f == flag) {
+                flags.push((flag.to_string(), description.to_string()));
+            }
+        }
+    }
+    flags
+}
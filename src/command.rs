@@ -0,0 +1,192 @@
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// how long command output stays valid by default
+pub const COMMAND_CACHE_TTL: Duration = Duration::from_secs(60);
+// how long command could run by default
+pub const COMMAND_TIMEOUT: Duration = Duration::from_millis(1000);
+// configured timeouts are capped by
+pub const MAX_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+// command output beyond is dropped
+pub const MAX_OUTPUT_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandSource {
+    pub trigger: String,
+    pub command: String,
+    #[serde(default)]
+    pub languages: Vec<String>,
+    // milliseconds
+    pub timeout: Option<u64>,
+    // seconds
+    pub ttl: Option<u64>,
+}
+
+impl CommandSource {
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+            .map(Duration::from_millis)
+            .unwrap_or(COMMAND_TIMEOUT)
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+            .map(Duration::from_secs)
+            .unwrap_or(COMMAND_CACHE_TTL)
+    }
+}
+
+type CacheEntry = (Instant, String);
+
+/// Output of user-defined shell commands per working directory, commands are run in background
+#[derive(Default)]
+pub struct CommandCache {
+    entries: RefCell<HashMap<(PathBuf, String), CacheEntry>>,
+    // commands being run in background
+    running: RefCell<HashSet<(PathBuf, String)>>,
+}
+
+impl CommandCache {
+    /// Cached output, outdated one while command is run again or None if never run,
+    /// and whether command should be run now, it's marked as running then
+    pub fn get(&self, dir: &Path, command: &str, ttl: Duration) -> (Option<String>, bool) {
+        let span = tracing::debug_span!("command_cache", command, hit = false).entered();
+        let key = (dir.to_path_buf(), command.to_string());
+        let entries = self.entries.borrow();
+        let entry = entries.get(&key);
+        let is_expired = entry.is_none_or(|(created, _)| created.elapsed() >= ttl);
+        if !is_expired {
+            span.record("hit", true);
+        }
+        let output = entry.map(|(_, output)| output.clone());
+        (output, is_expired && self.running.borrow_mut().insert(key))
+    }
+
+    pub fn loaded(&self, dir: PathBuf, command: String, output: String) {
+        let key = (dir, command);
+        self.running.borrow_mut().remove(&key);
+        self.entries
+            .borrow_mut()
+            .insert(key, (Instant::now(), output));
+    }
+}
+
+/// Program name of shell command, its first word without path
+pub fn program(command: &str) -> &str {
+    let first = command.split_whitespace().next().unwrap_or_default();
+    first.rsplit('/').next().unwrap_or(first)
+}
+
+/// Command is allowed when allowlist is not set or contains its program
+pub fn is_allowed(command: &str, allowlist: Option<&[String]>) -> bool {
+    allowlist.is_none_or(|allowlist| allowlist.iter().any(|p| p == program(command)))
+}
+
+/// Run command by `sh -c` and kill it on timeout (capped by `MAX_COMMAND_TIMEOUT`),
+/// output is truncated to `MAX_OUTPUT_BYTES`
+pub fn run(dir: &Path, command: &str, timeout: Duration) -> anyhow::Result<String> {
+    let mut shell = std::process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    output(shell, dir, command, false, timeout)
+}
+
+/// Run program (found by `PATH`) directly, without shell, stderr is appended to output if asked
+pub fn run_program(
+    dir: &Path,
+    program: &str,
+    args: &[&str],
+    with_stderr: bool,
+    timeout: Duration,
+) -> anyhow::Result<String> {
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+    output(
+        command,
+        dir,
+        &format!("{program} {}", args.join(" ")),
+        with_stderr,
+        timeout,
+    )
+}
+
+fn output(
+    mut command: std::process::Command,
+    dir: &Path,
+    label: &str,
+    with_stderr: bool,
+    timeout: Duration,
+) -> anyhow::Result<String> {
+    let timeout = timeout.min(MAX_COMMAND_TIMEOUT);
+    tracing::debug!("Run command: {label} at {dir:?}");
+    let mut child = command
+        .current_dir(dir)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(if with_stderr {
+            std::process::Stdio::piped()
+        } else {
+            std::process::Stdio::null()
+        })
+        .spawn()?;
+
+    // drain pipes in background to not block child on full pipe,
+    // pipe is closed after limit, so verbose command is stopped
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut output = Vec::new();
+            match pipe {
+                Some(pipe) => pipe
+                    .take(MAX_OUTPUT_BYTES)
+                    .read_to_end(&mut output)
+                    .map(|len| {
+                        (
+                            String::from_utf8_lossy(&output).into_owned(),
+                            len as u64 >= MAX_OUTPUT_BYTES,
+                        )
+                    }),
+                None => Ok((String::new(), false)),
+            }
+        })
+    };
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("Failed to get stdout of '{label}'"))?;
+    let stdout = drain(Some(Box::new(stdout)));
+    let stderr = drain(
+        child
+            .stderr
+            .take()
+            .map(|stderr| Box::new(stderr) as Box<dyn Read + Send>),
+    );
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("Command '{label}' timed out after {timeout:?}");
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    };
+
+    let read_error = || anyhow::anyhow!("Failed to read output of '{label}'");
+    let (mut output, truncated) = stdout.join().map_err(|_| read_error())??;
+    let (errors, _) = stderr.join().map_err(|_| read_error())??;
+    output.push_str(&errors);
+
+    // truncated command is failed on closed pipe
+    if truncated {
+        tracing::warn!("Output of '{label}' is truncated to {MAX_OUTPUT_BYTES} bytes");
+    } else if !status.success() {
+        anyhow::bail!("Command '{label}' failed with {status}");
+    }
+    Ok(output)
+}
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+// comment tags offered by default, `${USER}` is substituted from user variables
+pub const DEFAULT_TAGS: [&str; 7] = [
+    "TODO",
+    "FIXME",
+    "HACK",
+    "NOTE",
+    "XXX",
+    "TODO(${USER}):",
+    "NOTE(${USER}):",
+];
+
+// line and block comment leaders of common languages
+const LEADERS: [&str; 16] = [
+    "//", "///", "//!", "#", "##", "--", ";", ";;", "/*", "/**", "*", "<!--", "%", "{-", "(*", "\"",
+];
+
+/// Text before typed word ends with comment leader, like `    // ` or `x = 1  # `
+pub fn after_comment_leader(line_prefix: &str) -> bool {
+    line_prefix
+        .trim_end()
+        .rsplit(char::is_whitespace)
+        .next()
+        .is_some_and(|token| LEADERS.contains(&token))
+}
+
+/// Tag with substituted user variables (`${USER}` for `user`), None when some variable is not set
+pub fn expand(tag: &str, variables: &HashMap<String, String>) -> Option<String> {
+    let mut tag = tag.to_string();
+    for (name, value) in variables {
+        tag = tag.replace(&format!("${{{}}}", name.to_uppercase()), value);
+    }
+    (!tag.contains("${")).then_some(tag)
+}
@@ -0,0 +1,29 @@
+use notify::{RecursiveMode, Watcher};
+use std::path::PathBuf;
+
+/// Watch snippets and unicode input paths on disk, `on_change` is called (from watcher thread)
+/// on any created, modified or removed file, paths not existing yet are skipped
+pub fn watch(
+    paths: &[PathBuf],
+    on_change: impl Fn() + Send + 'static,
+) -> notify::Result<notify::RecommendedWatcher> {
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event)
+                if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() =>
+            {
+                tracing::debug!("Config changed: {:?}", event.paths);
+                on_change();
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Error on watch config: {e}"),
+        })?;
+    for path in paths {
+        if !path.exists() {
+            tracing::debug!("Skip watch of not existed {path:?}");
+            continue;
+        }
+        watcher.watch(path, RecursiveMode::Recursive)?;
+    }
+    Ok(watcher)
+}
@@ -0,0 +1,134 @@
+use ropey::Rope;
+
+// sources useful in prose only, inactive inside fenced code blocks
+pub const PROSE_SOURCES: [&str; 5] = [
+    "abbreviations",
+    "synonyms",
+    "mentions",
+    "markdown_links",
+    "frontmatter_tags",
+];
+
+// short fence info strings used instead of language ids
+const LANGUAGE_ALIASES: [(&str, &str); 16] = [
+    ("c++", "cpp"),
+    ("cs", "c-sharp"),
+    ("csharp", "c-sharp"),
+    ("js", "javascript"),
+    ("kt", "kotlin"),
+    ("md", "markdown"),
+    ("py", "python"),
+    ("python3", "python"),
+    ("rb", "ruby"),
+    ("rs", "rust"),
+    ("sh", "bash"),
+    ("shell", "bash"),
+    ("ts", "typescript"),
+    ("tex", "latex"),
+    ("yml", "yaml"),
+    ("zsh", "bash"),
+];
+
+/// Language id of fence info, like `python` for `py`
+fn language_id(info: &str) -> String {
+    let info = info.to_lowercase();
+    LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == info)
+        .map_or(info, |(_, language_id)| language_id.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Marker {
+    // fence char and its count, like ('`', 3)
+    fence: (char, usize),
+    // language of opening fence, like `python` for ```` ```python ```` or ```` ```py ````
+    info: String,
+}
+
+/// Parse fence line like ```` ```rust ```` or `~~~`, indented by up to 3 spaces
+fn parse_marker(line: &str) -> Option<Marker> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let ch = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let count = trimmed.chars().take_while(|c| *c == ch).count();
+    if count < 3 {
+        return None;
+    }
+    let info = trimmed[count..].trim();
+    // backtick fence info can't contain backticks, it's inline code then
+    if ch == '`' && info.contains('`') {
+        return None;
+    }
+    Some(Marker {
+        fence: (ch, count),
+        info: language_id(
+            info.split(|c: char| c.is_whitespace() || c == '{' || c == ',')
+                .next()
+                .unwrap_or_default(),
+        ),
+    })
+}
+
+/// Fence lines of markdown document, updated incrementally on edits
+#[derive(Debug, Default)]
+pub struct Fences {
+    // sorted by line
+    markers: Vec<(usize, Marker)>,
+}
+
+impl Fences {
+    pub fn new(text: &Rope) -> Self {
+        let mut fences = Self::default();
+        fences.scan(text, 0, text.len_lines().saturating_sub(1));
+        fences
+    }
+
+    /// Lines `start..=old_end` were replaced by lines `start..=new_end`
+    pub fn edit(&mut self, text: &Rope, start: usize, old_end: usize, new_end: usize) {
+        self.markers
+            .retain(|(line, _)| *line < start || *line > old_end);
+        for (line, _) in self.markers.iter_mut().filter(|(line, _)| *line > old_end) {
+            *line = *line - old_end + new_end;
+        }
+        self.scan(text, start, new_end);
+    }
+
+    fn scan(&mut self, text: &Rope, start: usize, end: usize) {
+        let found = (start..=end)
+            .filter_map(|i| {
+                let line = text.get_line(i)?;
+                // fence lines are short, skip long lines without allocation
+                if line.len_chars() > 256
+                    || !matches!(line.chars().find(|c| *c != ' '), Some('`' | '~'))
+                {
+                    return None;
+                }
+                parse_marker(&line.to_string()).map(|marker| (i, marker))
+            })
+            .collect::<Vec<_>>();
+        let at = self.markers.partition_point(|(line, _)| *line < start);
+        self.markers.splice(at..at, found);
+    }
+
+    /// Language of fenced code block containing line (empty when not set), None in prose
+    pub fn language_at(&self, line: usize) -> Option<&str> {
+        let mut open: Option<&Marker> = None;
+        for (_, marker) in self.markers.iter().take_while(|(i, _)| *i < line) {
+            match open {
+                None => open = Some(marker),
+                Some(opening)
+                    if marker.info.is_empty()
+                        && marker.fence.0 == opening.fence.0
+                        && marker.fence.1 >= opening.fence.1 =>
+                {
+                    open = None
+                }
+                _ => (),
+            }
+        }
+        open.map(|m| m.info.as_str())
+    }
+}
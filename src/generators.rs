@@ -0,0 +1,94 @@
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// generator names, `rand<N>` is random alphanumeric string of N chars
+pub const GENERATORS: [&str; 4] = ["uuid4", "ulid", "rand16", "rand32"];
+
+// limit of random string length
+const MAX_RANDOM_LEN: usize = 256;
+
+const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Random value from randomly keyed SipHash of time and counter
+fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    if let Ok(elapsed) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+    hasher.finish()
+}
+
+/// Random UUID version 4, like `c4a7a1e2-3b1f-4d6e-9a0b-1f2e3d4c5b6a`
+pub fn uuid4() -> String {
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&random_u64().to_be_bytes());
+    bytes[8..].copy_from_slice(&random_u64().to_be_bytes());
+    // version 4 and RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// ULID: 48 bits of unix time millis and 80 random bits, Crockford base32 encoded
+pub fn ulid() -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let random = ((random_u64() as u128) << 16) ^ (random_u64() as u128 & 0xffff);
+    let value = ((millis & 0xffff_ffff_ffff) << 80) | (random & ((1 << 80) - 1));
+    (0..26)
+        .rev()
+        .map(|i| CROCKFORD[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// Random alphanumeric string
+pub fn random_string(len: usize) -> String {
+    (0..len.min(MAX_RANDOM_LEN))
+        .map(|_| ALPHANUMERIC[(random_u64() % ALPHANUMERIC.len() as u64) as usize] as char)
+        .collect()
+}
+
+/// Freshly generated values as (generator name, value) for typed prefix, like `uu` or `rand8`
+pub fn generate(prefix: &str) -> Vec<(String, String)> {
+    let prefix = prefix.to_lowercase();
+    if prefix.len() < 2 {
+        return Vec::new();
+    }
+    let mut names = GENERATORS
+        .iter()
+        .filter(|name| name.starts_with(&prefix))
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    if let Some(len) = prefix
+        .strip_prefix("rand")
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        if !names.contains(&prefix) && len > 0 && len <= MAX_RANDOM_LEN {
+            names.push(prefix.clone());
+        }
+    }
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let value = match name.as_str() {
+                "uuid4" => uuid4(),
+                "ulid" => ulid(),
+                _ => random_string(name.strip_prefix("rand")?.parse().ok()?),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
@@ -0,0 +1,175 @@
+use crate::command;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// how long git output stays valid
+const GIT_CACHE_TTL: Duration = Duration::from_secs(30);
+// how long git command could run, it's run in background
+const GIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// languages of documents edited during git operations
+pub const GIT_LANGUAGES: [&str; 5] = [
+    "git-commit",
+    "gitcommit",
+    "git-rebase",
+    "git-config",
+    "gitconfig",
+];
+
+// conventional commit types with descriptions
+pub const CONVENTIONAL_TYPES: [(&str, &str); 11] = [
+    ("feat", "A new feature"),
+    ("fix", "A bug fix"),
+    ("docs", "Documentation only changes"),
+    (
+        "style",
+        "Changes that do not affect the meaning of the code",
+    ),
+    (
+        "refactor",
+        "A code change that neither fixes a bug nor adds a feature",
+    ),
+    ("perf", "A code change that improves performance"),
+    ("test", "Adding missing tests or correcting existing tests"),
+    (
+        "build",
+        "Changes that affect the build system or external dependencies",
+    ),
+    ("ci", "Changes to CI configuration files and scripts"),
+    ("chore", "Other changes that don't modify src or test files"),
+    ("revert", "Reverts a previous commit"),
+];
+
+// commit message trailers which take `Name <email>` value
+pub const AUTHOR_TRAILERS: [&str; 6] = [
+    "Co-authored-by",
+    "Signed-off-by",
+    "Reviewed-by",
+    "Acked-by",
+    "Tested-by",
+    "Reported-by",
+];
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum GitQuery {
+    Refs,
+    Subjects,
+    Authors,
+}
+
+impl GitQuery {
+    fn args(&self) -> &'static [&'static str] {
+        match self {
+            GitQuery::Refs => &[
+                "for-each-ref",
+                "--format=%(refname)",
+                "refs/heads",
+                "refs/remotes",
+                "refs/tags",
+            ],
+            GitQuery::Subjects => &["log", "-n", "500", "--format=%s"],
+            GitQuery::Authors => &["log", "-n", "1000", "--format=%aN <%aE>"],
+        }
+    }
+}
+
+/// Run git query at repository directory, blocking, so it's run on worker pool
+pub fn load(dir: &Path, query: GitQuery) -> anyhow::Result<Vec<String>> {
+    let _span = tracing::debug_span!("git_load", ?query).entered();
+    Ok(
+        command::run_program(dir, "git", query.args(), false, GIT_TIMEOUT)?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+type CacheEntry = (Instant, Vec<String>);
+
+/// Output lines of git commands per repository directory
+#[derive(Default)]
+pub struct GitCache {
+    entries: RefCell<HashMap<(PathBuf, GitQuery), CacheEntry>>,
+    // queries being loaded in background
+    loading: RefCell<HashSet<(PathBuf, GitQuery)>>,
+}
+
+impl GitCache {
+    /// Cached lines, outdated ones while reloaded or None if never loaded,
+    /// and whether query should be loaded now, it's marked as loading then
+    pub fn get(&self, dir: &Path, query: GitQuery) -> (Option<Vec<String>>, bool) {
+        let span = tracing::debug_span!("git_cache", ?query, hit = false).entered();
+        let key = (dir.to_path_buf(), query);
+        let entries = self.entries.borrow();
+        let entry = entries.get(&key);
+        let is_expired = entry.is_none_or(|(created, _)| created.elapsed() >= GIT_CACHE_TTL);
+        if !is_expired {
+            span.record("hit", true);
+        }
+        let lines = entry.map(|(_, lines)| lines.clone());
+        (lines, is_expired && self.loading.borrow_mut().insert(key))
+    }
+
+    pub fn loaded(&self, dir: PathBuf, query: GitQuery, lines: Vec<String>) {
+        let key = (dir, query);
+        self.loading.borrow_mut().remove(&key);
+        self.entries
+            .borrow_mut()
+            .insert(key, (Instant::now(), lines));
+    }
+}
+
+/// Split full ref name to short name and its kind
+pub fn parse_ref(refname: &str) -> Option<(&str, &'static str)> {
+    if let Some(name) = refname.strip_prefix("refs/heads/") {
+        return Some((name, "branch"));
+    }
+    if let Some(name) = refname.strip_prefix("refs/remotes/") {
+        if name.ends_with("/HEAD") {
+            return None;
+        }
+        return Some((name, "remote branch"));
+    }
+    refname.strip_prefix("refs/tags/").map(|name| (name, "tag"))
+}
+
+#[inline]
+pub fn char_is_ref(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '/' | '-' | '_' | '.')
+}
+
+/// Scopes of conventional commit subjects, most used first
+pub fn conventional_scopes(subjects: &[String]) -> Vec<String> {
+    let mut counter: HashMap<&str, usize> = HashMap::new();
+    for subject in subjects {
+        let Some((header, _)) = subject.split_once(':') else {
+            continue;
+        };
+        let Some((_, scope)) = header.trim_end_matches('!').split_once('(') else {
+            continue;
+        };
+        let Some(scope) = scope.strip_suffix(')') else {
+            continue;
+        };
+        if !scope.is_empty() {
+            *counter.entry(scope).or_default() += 1;
+        }
+    }
+    let mut scopes = counter.into_iter().collect::<Vec<_>>();
+    scopes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    scopes.into_iter().map(|(s, _)| s.to_string()).collect()
+}
+
+/// Unique authors, most active first
+pub fn authors(lines: &[String]) -> Vec<&str> {
+    let mut counter: HashMap<&str, usize> = HashMap::new();
+    for line in lines {
+        *counter.entry(line.as_str()).or_default() += 1;
+    }
+    let mut authors = counter.into_iter().collect::<Vec<_>>();
+    authors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    authors.into_iter().map(|(a, _)| a).collect()
+}
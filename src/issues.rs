@@ -0,0 +1,61 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IssueSource {
+    pub command: String,
+    #[serde(default = "default_trigger")]
+    pub trigger: String,
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+fn default_trigger() -> String {
+    "#".to_string()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    pub id: String,
+    pub title: String,
+}
+
+fn json_field(value: &serde_json::Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| value.get(key))
+        .find_map(|v| match v {
+            serde_json::Value::String(s) => Some(s.to_owned()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+}
+
+/// Parse JSON array of objects (like `gh issue list --json number,title`)
+/// or lines of `<id> <title>`
+pub fn parse_issues(output: &str) -> Vec<Issue> {
+    if let Ok(serde_json::Value::Array(values)) = serde_json::from_str(output) {
+        return values
+            .iter()
+            .filter_map(|value| {
+                Some(Issue {
+                    id: json_field(value, &["number", "iid", "id", "key"])?,
+                    title: json_field(value, &["title", "summary"]).unwrap_or_default(),
+                })
+            })
+            .collect();
+    }
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (id, title) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            if id.is_empty() {
+                return None;
+            }
+            Some(Issue {
+                id: id.trim_start_matches('#').to_string(),
+                title: title.trim().to_string(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,94 @@
+use ropey::Rope;
+
+pub const LATEX_LANGUAGES: [&str; 2] = ["latex", "tex"];
+pub const TYPST_LANGUAGES: [&str; 1] = ["typst"];
+
+// commands which take label as argument
+const LATEX_REF_COMMANDS: [&str; 7] = [
+    "\\ref{",
+    "\\eqref{",
+    "\\pageref{",
+    "\\autoref{",
+    "\\cref{",
+    "\\Cref{",
+    "\\nameref{",
+];
+
+pub type ParseLabels = fn(&Rope) -> Vec<String>;
+
+/// Labels parser for document language
+pub fn parser(language_id: &str) -> Option<ParseLabels> {
+    if LATEX_LANGUAGES.contains(&language_id) {
+        Some(latex_labels)
+    } else if TYPST_LANGUAGES.contains(&language_id) {
+        Some(typst_labels)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn char_is_label(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '-' | '_' | ':' | '.')
+}
+
+/// Labels defined by `\label{...}`
+pub fn latex_labels(text: &Rope) -> Vec<String> {
+    let mut result = Vec::new();
+    for line in text.lines() {
+        let line = line.to_string();
+        let mut rest = line.as_str();
+        while let Some(idx) = rest.find("\\label{") {
+            rest = &rest[idx + "\\label{".len()..];
+            if let Some((label, _)) = rest.split_once('}') {
+                if !label.is_empty() {
+                    result.push(label.to_string());
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Labels defined by `<label>`
+pub fn typst_labels(text: &Rope) -> Vec<String> {
+    let mut result = Vec::new();
+    for line in text.lines() {
+        let line = line.to_string();
+        let mut rest = line.as_str();
+        while let Some(idx) = rest.find('<') {
+            rest = &rest[idx + 1..];
+            let Some((label, _)) = rest.split_once('>') else {
+                break;
+            };
+            if label.starts_with(char::is_alphabetic) && label.chars().all(char_is_label) {
+                result.push(label.to_string());
+            }
+        }
+    }
+    result
+}
+
+/// Partial label at the end of line prefix in reference context
+pub fn latex_label_query(line_prefix: &str) -> Option<&str> {
+    let (idx, command) = LATEX_REF_COMMANDS
+        .iter()
+        .filter_map(|command| line_prefix.rfind(command).map(|idx| (idx, command)))
+        .max_by_key(|(idx, _)| *idx)?;
+    let query = &line_prefix[idx + command.len()..];
+    // multiple labels like \cref{a,b}
+    let query = query.rsplit(',').next().unwrap_or(query);
+    query.chars().all(char_is_label).then_some(query)
+}
+
+pub fn typst_label_query(line_prefix: &str) -> Option<&str> {
+    let idx = line_prefix.rfind('@')?;
+    let query = &line_prefix[idx + 1..];
+    // skip emails like user@host
+    let is_word_before = line_prefix[..idx]
+        .chars()
+        .last()
+        .map(char::is_alphanumeric)
+        .unwrap_or(false);
+    (!is_word_before && query.chars().all(char_is_label)).then_some(query)
+}
@@ -3,44 +3,231 @@ use anyhow::Result;
 use ropey::Rope;
 use serde::Deserialize;
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::io::prelude::*;
 use tokio::sync::{mpsc, oneshot};
 use tower_lsp::lsp_types::*;
+use unicode_segmentation::UnicodeSegmentation;
 
+pub mod abbreviations;
+pub mod acceptance;
+pub mod analyze;
+pub mod bibtex;
+pub mod case_style;
+pub mod cli_flags;
+pub mod colors;
+pub mod command;
+pub mod comment_tags;
+pub mod config_watch;
+pub mod fences;
+pub mod generators;
+pub mod git;
+pub mod gitmoji;
+pub mod issues;
+pub mod labels;
+pub mod links;
+pub mod logging;
+pub mod markdown;
+pub mod mentions;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+pub mod position;
+#[cfg(feature = "profiling")]
+pub mod profile;
+pub mod selfcheck;
 pub mod server;
+pub mod shebang;
 pub mod snippets;
+pub mod stats;
+pub mod thesaurus;
+pub mod tmux;
+pub mod unicode_input;
+pub mod warm;
+pub mod word_index;
+pub mod workspace_index;
 
-use snippets::Snippet;
+use snippets::{Snippet, WordEntry};
 
+// word list file name at workspace root and config dir
+pub const WORDS_FILENAME: &str = ".scls-words";
+/// Settings of workspace folder, override client settings for its documents
+pub const FOLDER_SETTINGS_FILENAME: &str = ".scls.toml";
+/// Command execution policy settings, ignored in workspace folder settings
+pub const POLICY_SETTINGS: [&str; 2] = ["require_workspace_trust", "command_allowlist"];
+
+#[derive(Clone)]
 pub struct StartOptions {
     pub home_dir: String,
     pub external_snippets_config_path: std::path::PathBuf,
-    pub snippets_path: std::path::PathBuf,
+    // snippets dirs (or files) searched in order, later ones override earlier on (prefix, scope)
+    pub snippets_paths: Vec<std::path::PathBuf>,
     pub unicode_input_path: std::path::PathBuf,
+    pub words_path: std::path::PathBuf,
+    // accepted snippets counters
+    pub snippets_usage_path: std::path::PathBuf,
+    // accepted completions counters of all sources
+    pub acceptance_path: std::path::PathBuf,
+    // words of last session documents, preloaded on startup
+    pub warm_cache_path: std::path::PathBuf,
+}
+
+impl StartOptions {
+    /// Snippets dir new snippets are written to, the last (overriding) one of search paths
+    pub fn user_snippets_path(&self) -> Option<&std::path::PathBuf> {
+        self.snippets_paths.last()
+    }
+}
+
+/// Sources loaded in background on startup
+#[derive(Debug, Default)]
+pub struct LoadedSources {
+    pub snippets: Vec<Snippet>,
+    pub unicode_input: HashMap<String, String>,
+    pub unicode_input_info: HashMap<String, snippets::config::UnicodeInputInfo>,
+    pub words: Vec<WordEntry>,
+    // config problems found on load, reported to client
+    pub problems: Vec<String>,
 }
 
 #[derive(Deserialize)]
 pub struct BackendSettings {
     pub max_completion_items: usize,
     pub max_path_chars: usize,
+    // limits of opened documents kept in memory, least recently used are evicted
+    pub max_resident_documents: Option<usize>,
+    pub max_resident_bytes: Option<usize>,
+    // source latency target, items limit of slower sources is tuned down (and restored when fast)
+    pub adaptive_latency_ms: Option<u64>,
     pub snippets_first: bool,
+    // only exactly matched snippet is offered when typed word equals its prefix
+    pub snippets_exclusive_on_exact: bool,
+    // record accepted completions (per workspace and language) on disk to rank them first
+    pub acceptance_stats: bool,
+    // with several workspace folders, documents see words, tags and word lists of their own folder only
+    pub isolate_workspace_folders: bool,
+    // line (with file name) where word is found is shown by completion item resolve
+    pub word_context: bool,
+    // snippets and unicode input files are watched on disk by server itself, for clients not watching them
+    pub watch_config: bool,
     // feature flags
     pub feature_words: bool,
+    // words of workspace files not opened yet, indexed in background
+    pub feature_workspace_words: bool,
+    // gitignore-like globs of workspace files never indexed, in addition to ignore files
+    pub index_exclude: Vec<String>,
+    // words never offered by words completion
+    pub words_exclude: Vec<String>,
+    // regexes of tokens never taken as words, like numbers or hashes
+    pub words_exclude_patterns: Vec<String>,
+    // compiled words exclusion, applied when words are indexed
+    #[serde(skip)]
+    pub words_filter: word_index::WordsFilter,
     pub feature_snippets: bool,
     pub feature_unicode_input: bool,
+    // language id (or `*` for any) -> enabled unicode input categories, all when not set
+    pub unicode_input_categories: HashMap<String, Vec<String>>,
     pub feature_paths: bool,
+    pub feature_datetime: bool,
+    pub feature_generators: bool,
+    pub feature_colors: bool,
+    pub feature_git_refs: bool,
+    pub feature_git_commit: bool,
+    pub feature_gitmoji: bool,
+    pub gitmoji_format: gitmoji::GitmojiFormat,
+    pub feature_thesaurus: bool,
+    pub feature_case_styles: bool,
+    pub feature_tmux: bool,
+    pub feature_markdown_links: bool,
+    pub thesaurus_path: Option<String>,
+    pub feature_abbreviations: bool,
+    pub abbreviations_path: Option<String>,
+    pub feature_frontmatter_tags: bool,
+    pub notes_path: Option<String>,
+    pub feature_labels: bool,
+    pub feature_bibtex: bool,
+    // strftime-like formats offered by date/time items
+    pub datetime_formats: Vec<String>,
+    // user variables substituted into snippets bodies as `${NAME}`
+    pub variables: HashMap<String, String>,
+    // source name -> regex on line text before cursor, source is active on match only
+    pub source_line_filters: HashMap<String, String>,
+    // source name -> chars accepting its item and then typed, like `/` after folder of paths
+    pub commit_characters: HashMap<String, Vec<String>>,
+    pub issue_source: Option<issues::IssueSource>,
+    pub command_sources: Vec<command::CommandSource>,
+    pub feature_mentions: bool,
+    pub mention_source: mentions::MentionSource,
+    pub feature_cli_flags: bool,
+    pub feature_comment_tags: bool,
+    // tags offered after comment leader, `${NAME}` is substituted from user variables
+    pub comment_tags: Vec<String>,
+    // commands allowed to run with `--help` for shell scripts flags
+    pub cli_flags_commands: Vec<String>,
+    // commands of issue, command and `--help` sources run only in workspaces trusted by user
+    pub require_workspace_trust: bool,
+    // programs allowed to run by issue, command and `--help` sources, any when not set
+    pub command_allowlist: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
 pub struct PartialBackendSettings {
     pub max_completion_items: Option<usize>,
     pub max_path_chars: Option<usize>,
+    #[serde(alias = "max_open_documents")]
+    pub max_resident_documents: Option<usize>,
+    #[serde(alias = "max_total_bytes")]
+    pub max_resident_bytes: Option<usize>,
+    pub adaptive_latency_ms: Option<u64>,
     pub snippets_first: Option<bool>,
+    pub snippets_exclusive_on_exact: Option<bool>,
+    pub acceptance_stats: Option<bool>,
+    pub isolate_workspace_folders: Option<bool>,
+    pub word_context: Option<bool>,
+    pub watch_config: Option<bool>,
     pub feature_words: Option<bool>,
+    pub feature_workspace_words: Option<bool>,
+    pub index_exclude: Option<Vec<String>>,
+    pub words_exclude: Option<Vec<String>>,
+    pub words_exclude_patterns: Option<Vec<String>>,
     pub feature_snippets: Option<bool>,
     pub feature_unicode_input: Option<bool>,
+    pub unicode_input_categories: Option<HashMap<String, Vec<String>>>,
     pub feature_paths: Option<bool>,
+    pub feature_datetime: Option<bool>,
+    pub feature_generators: Option<bool>,
+    pub feature_colors: Option<bool>,
+    pub feature_git_refs: Option<bool>,
+    pub feature_git_commit: Option<bool>,
+    pub feature_gitmoji: Option<bool>,
+    pub gitmoji_format: Option<gitmoji::GitmojiFormat>,
+    pub feature_thesaurus: Option<bool>,
+    pub feature_case_styles: Option<bool>,
+    pub feature_tmux: Option<bool>,
+    pub feature_markdown_links: Option<bool>,
+    pub thesaurus_path: Option<String>,
+    pub feature_abbreviations: Option<bool>,
+    pub abbreviations_path: Option<String>,
+    pub feature_frontmatter_tags: Option<bool>,
+    pub notes_path: Option<String>,
+    pub feature_labels: Option<bool>,
+    pub feature_bibtex: Option<bool>,
+    pub datetime_formats: Option<Vec<String>>,
+    pub variables: Option<HashMap<String, String>>,
+    pub source_line_filters: Option<HashMap<String, String>>,
+    pub commit_characters: Option<HashMap<String, Vec<String>>>,
+    pub issue_source: Option<issues::IssueSource>,
+    pub command_sources: Option<Vec<command::CommandSource>>,
+    pub feature_mentions: Option<bool>,
+    pub mention_source: Option<mentions::MentionSource>,
+    pub feature_cli_flags: Option<bool>,
+    pub feature_comment_tags: Option<bool>,
+    pub comment_tags: Option<Vec<String>>,
+    pub cli_flags_commands: Option<Vec<String>>,
+    pub require_workspace_trust: Option<bool>,
+    pub command_allowlist: Option<Vec<String>>,
 }
 
 impl Default for BackendSettings {
@@ -48,36 +235,560 @@ impl Default for BackendSettings {
         BackendSettings {
             max_completion_items: 20,
             max_path_chars: 256,
+            max_resident_documents: None,
+            max_resident_bytes: None,
+            adaptive_latency_ms: None,
             snippets_first: false,
+            snippets_exclusive_on_exact: false,
+            acceptance_stats: true,
+            isolate_workspace_folders: true,
+            word_context: false,
+            watch_config: false,
             feature_words: true,
+            feature_workspace_words: false,
+            index_exclude: Vec::new(),
+            words_exclude: Vec::new(),
+            words_exclude_patterns: Vec::new(),
+            words_filter: word_index::WordsFilter::default(),
             feature_snippets: true,
             feature_unicode_input: true,
+            unicode_input_categories: HashMap::new(),
             feature_paths: true,
+            feature_datetime: true,
+            feature_generators: false,
+            feature_colors: true,
+            feature_git_refs: true,
+            feature_git_commit: true,
+            feature_gitmoji: true,
+            gitmoji_format: gitmoji::GitmojiFormat::default(),
+            feature_thesaurus: false,
+            feature_case_styles: false,
+            feature_tmux: false,
+            feature_markdown_links: true,
+            thesaurus_path: None,
+            feature_abbreviations: false,
+            abbreviations_path: None,
+            feature_frontmatter_tags: true,
+            notes_path: None,
+            feature_labels: true,
+            feature_bibtex: true,
+            datetime_formats: vec![
+                "%Y-%m-%d".to_string(),
+                "%Y-%m-%d %H:%M".to_string(),
+                "%Y%m%dT%H%M".to_string(),
+                "%Y-%m-%dT%H:%M:%S%:z".to_string(),
+                "%s".to_string(),
+            ],
+            variables: HashMap::new(),
+            source_line_filters: HashMap::new(),
+            commit_characters: HashMap::new(),
+            issue_source: None,
+            command_sources: Vec::new(),
+            feature_mentions: false,
+            mention_source: mentions::MentionSource::default(),
+            feature_cli_flags: false,
+            feature_comment_tags: true,
+            comment_tags: comment_tags::DEFAULT_TAGS
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            cli_flags_commands: cli_flags::DEFAULT_COMMANDS
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            require_workspace_trust: true,
+            command_allowlist: None,
         }
     }
 }
 
 impl BackendSettings {
+    /// Names of enabled completion sources
+    pub fn enabled_features(&self) -> Vec<&'static str> {
+        self.features()
+            .into_iter()
+            .filter_map(|(name, enabled)| enabled.then_some(name))
+            .collect()
+    }
+
+    /// Feature names (without `feature_` prefix) with its flags
+    pub fn features(&self) -> [(&'static str, bool); 22] {
+        [
+            ("words", self.feature_words),
+            ("workspace_words", self.feature_workspace_words),
+            ("snippets", self.feature_snippets),
+            ("unicode_input", self.feature_unicode_input),
+            ("paths", self.feature_paths),
+            ("datetime", self.feature_datetime),
+            ("generators", self.feature_generators),
+            ("colors", self.feature_colors),
+            ("git_refs", self.feature_git_refs),
+            ("git_commit", self.feature_git_commit),
+            ("gitmoji", self.feature_gitmoji),
+            ("thesaurus", self.feature_thesaurus),
+            ("case_styles", self.feature_case_styles),
+            ("tmux", self.feature_tmux),
+            ("abbreviations", self.feature_abbreviations),
+            ("markdown_links", self.feature_markdown_links),
+            ("frontmatter_tags", self.feature_frontmatter_tags),
+            ("labels", self.feature_labels),
+            ("bibtex", self.feature_bibtex),
+            ("mentions", self.feature_mentions),
+            ("cli_flags", self.feature_cli_flags),
+            ("comment_tags", self.feature_comment_tags),
+        ]
+    }
+
     pub fn apply_partial_settings(&self, settings: PartialBackendSettings) -> Self {
-        Self {
+        let mut applied = Self {
             max_completion_items: settings
                 .max_completion_items
                 .unwrap_or(self.max_completion_items),
             max_path_chars: settings.max_path_chars.unwrap_or(self.max_path_chars),
+            max_resident_documents: settings
+                .max_resident_documents
+                .or(self.max_resident_documents),
+            max_resident_bytes: settings.max_resident_bytes.or(self.max_resident_bytes),
+            adaptive_latency_ms: settings.adaptive_latency_ms.or(self.adaptive_latency_ms),
             snippets_first: settings.snippets_first.unwrap_or(self.snippets_first),
+            snippets_exclusive_on_exact: settings
+                .snippets_exclusive_on_exact
+                .unwrap_or(self.snippets_exclusive_on_exact),
+            acceptance_stats: settings.acceptance_stats.unwrap_or(self.acceptance_stats),
+            isolate_workspace_folders: settings
+                .isolate_workspace_folders
+                .unwrap_or(self.isolate_workspace_folders),
+            word_context: settings.word_context.unwrap_or(self.word_context),
+            watch_config: settings.watch_config.unwrap_or(self.watch_config),
             feature_words: settings.feature_words.unwrap_or(self.feature_words),
+            feature_workspace_words: settings
+                .feature_workspace_words
+                .unwrap_or(self.feature_workspace_words),
+            index_exclude: settings
+                .index_exclude
+                .unwrap_or_else(|| self.index_exclude.clone()),
+            words_exclude: settings
+                .words_exclude
+                .unwrap_or_else(|| self.words_exclude.clone()),
+            words_exclude_patterns: settings
+                .words_exclude_patterns
+                .unwrap_or_else(|| self.words_exclude_patterns.clone()),
             feature_snippets: settings.feature_snippets.unwrap_or(self.feature_snippets),
             feature_unicode_input: settings
                 .feature_unicode_input
                 .unwrap_or(self.feature_unicode_input),
-            feature_paths: settings.feature_words.unwrap_or(self.feature_paths),
+            unicode_input_categories: settings
+                .unicode_input_categories
+                .unwrap_or_else(|| self.unicode_input_categories.clone()),
+            feature_paths: settings.feature_paths.unwrap_or(self.feature_paths),
+            feature_datetime: settings.feature_datetime.unwrap_or(self.feature_datetime),
+            feature_generators: settings
+                .feature_generators
+                .unwrap_or(self.feature_generators),
+            feature_colors: settings.feature_colors.unwrap_or(self.feature_colors),
+            feature_git_refs: settings.feature_git_refs.unwrap_or(self.feature_git_refs),
+            feature_git_commit: settings
+                .feature_git_commit
+                .unwrap_or(self.feature_git_commit),
+            feature_gitmoji: settings.feature_gitmoji.unwrap_or(self.feature_gitmoji),
+            gitmoji_format: settings.gitmoji_format.unwrap_or(self.gitmoji_format),
+            feature_thesaurus: settings.feature_thesaurus.unwrap_or(self.feature_thesaurus),
+            feature_case_styles: settings
+                .feature_case_styles
+                .unwrap_or(self.feature_case_styles),
+            feature_tmux: settings.feature_tmux.unwrap_or(self.feature_tmux),
+            thesaurus_path: settings
+                .thesaurus_path
+                .or_else(|| self.thesaurus_path.clone()),
+            feature_abbreviations: settings
+                .feature_abbreviations
+                .unwrap_or(self.feature_abbreviations),
+            abbreviations_path: settings
+                .abbreviations_path
+                .or_else(|| self.abbreviations_path.clone()),
+            feature_markdown_links: settings
+                .feature_markdown_links
+                .unwrap_or(self.feature_markdown_links),
+            feature_frontmatter_tags: settings
+                .feature_frontmatter_tags
+                .unwrap_or(self.feature_frontmatter_tags),
+            notes_path: settings.notes_path.or_else(|| self.notes_path.clone()),
+            feature_labels: settings.feature_labels.unwrap_or(self.feature_labels),
+            feature_bibtex: settings.feature_bibtex.unwrap_or(self.feature_bibtex),
+            datetime_formats: settings
+                .datetime_formats
+                .unwrap_or_else(|| self.datetime_formats.clone()),
+            variables: settings.variables.unwrap_or_else(|| self.variables.clone()),
+            source_line_filters: settings
+                .source_line_filters
+                .unwrap_or_else(|| self.source_line_filters.clone()),
+            commit_characters: settings
+                .commit_characters
+                .unwrap_or_else(|| self.commit_characters.clone()),
+            issue_source: settings.issue_source.or_else(|| self.issue_source.clone()),
+            command_sources: settings
+                .command_sources
+                .unwrap_or_else(|| self.command_sources.clone()),
+            feature_mentions: settings.feature_mentions.unwrap_or(self.feature_mentions),
+            mention_source: settings
+                .mention_source
+                .unwrap_or_else(|| self.mention_source.clone()),
+            feature_cli_flags: settings.feature_cli_flags.unwrap_or(self.feature_cli_flags),
+            feature_comment_tags: settings
+                .feature_comment_tags
+                .unwrap_or(self.feature_comment_tags),
+            comment_tags: settings
+                .comment_tags
+                .unwrap_or_else(|| self.comment_tags.clone()),
+            cli_flags_commands: settings
+                .cli_flags_commands
+                .unwrap_or_else(|| self.cli_flags_commands.clone()),
+            require_workspace_trust: settings
+                .require_workspace_trust
+                .unwrap_or(self.require_workspace_trust),
+            command_allowlist: settings
+                .command_allowlist
+                .or_else(|| self.command_allowlist.clone()),
+            words_filter: self.words_filter.clone(),
+        };
+        // patterns are compiled on change only
+        if applied.words_exclude != self.words_exclude
+            || applied.words_exclude_patterns != self.words_exclude_patterns
+        {
+            applied.words_filter = word_index::WordsFilter::new(
+                &applied.words_exclude,
+                &applied.words_exclude_patterns,
+            );
         }
+        applied
+    }
+}
+
+// how long aggregated frontmatter tags stay valid
+const TAGS_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+
+// aggregated frontmatter tags with its creation time
+// with workspace roots tags of saved notes were aggregated from
+type CachedTags = (
+    std::time::Instant,
+    Vec<std::path::PathBuf>,
+    HashMap<String, usize>,
+);
+
+// mention files are checked for changes (by modification time) after
+const MENTIONS_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+// mention source people file and document dir (when CODEOWNERS is used)
+type MentionsKey = (Option<std::path::PathBuf>, Option<std::path::PathBuf>);
+
+// people of mention files with its last check time, None when files are changed
+type CachedMentions = (
+    Option<std::time::Instant>,
+    mentions::MentionFiles,
+    Vec<mentions::Person>,
+);
+
+/// Sender of completion items streamed as partial result
+pub type PartialResultSender = mpsc::UnboundedSender<Vec<CompletionItem>>;
+
+// .bib file with its entries keys and lines
+type BibKeys = (std::path::PathBuf, Vec<bibtex::BibEntry>);
+
+// compiled search automata kept for recently typed prefixes
+const AUTOMATA_CACHE_SIZE: usize = 64;
+
+/// Command attached to snippet items, reports accepted snippet prefix
+pub const SNIPPET_ACCEPTED_COMMAND: &str = "scls.snippetAccepted";
+
+/// Command attached to other items, reports accepted item label with its workspace and language
+pub const COMPLETION_ACCEPTED_COMMAND: &str = "scls.completionAccepted";
+
+// requests waiting for backend (besides superseding look-ahead), completions beyond it are answered as incomplete
+pub const REQUEST_QUEUE_CAPACITY: usize = 64;
+
+// key of word item data, document uri to locate word context line
+const WORD_CONTEXT_DATA: &str = "scls.wordContext";
+// limit of word context line shown by resolve
+const MAX_WORD_CONTEXT_CHARS: usize = 120;
+
+// requests taken out of channel to look for superseding ones, rest stay in channel for backpressure
+const SUPERSEDED_LOOKAHEAD: usize = 16;
+
+// queue depth to warn about backend falling behind
+const QUEUE_DEPTH_WARNING: usize = REQUEST_QUEUE_CAPACITY / 2;
+
+// results of repeated completion requests kept, most recent at the end
+const COMPLETION_CACHE_SIZE: usize = 8;
+// cached results are not served after, time based sources would be outdated
+const COMPLETION_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Completion request position in document version, with trigger character
+#[derive(Debug, PartialEq)]
+struct CompletionCacheKey {
+    uri: Url,
+    version: i32,
+    line: u32,
+    character: u32,
+    trigger: Option<String>,
+}
+
+/// Completion sources names, used by per-source settings
+pub const SOURCES: [&str; 25] = [
+    "file_templates",
+    "abbreviations",
+    "snippets",
+    "words",
+    "tmux",
+    "synonyms",
+    "case_styles",
+    "datetime",
+    "generators",
+    "comment_tags",
+    "colors",
+    "git_refs",
+    "git_commit",
+    "git_trailers",
+    "gitmoji",
+    "issues",
+    "command_sources",
+    "mentions",
+    "cli_flags",
+    "markdown_links",
+    "frontmatter_tags",
+    "labels",
+    "bibtex",
+    "paths",
+    "unicode_input",
+];
+
+/// Source shown by label details of its items, for sources not evident from item itself
+const SOURCE_LABELS: [(&str, &str); 6] = [
+    ("file_templates", "snippet"),
+    ("snippets", "snippet"),
+    ("paths", "path"),
+    ("unicode_input", "σ unicode"),
+    ("labels", "label"),
+    ("bibtex", "bibtex"),
+];
+
+/// Describe items by its source in label details, detail set by source is kept
+fn set_source_label(source: &str, items: &mut [CompletionItem]) {
+    let Some((_, label)) = SOURCE_LABELS.iter().find(|(name, _)| *name == source) else {
+        return;
+    };
+    for item in items {
+        item.label_details
+            .get_or_insert_with(CompletionItemLabelDetails::default)
+            .description = Some(label.to_string());
     }
 }
 
+// limit of workspace symbols returned
+const MAX_WORKSPACE_SYMBOLS: usize = 200;
+
+// words which trigger date/time items, typed in full
+const DATETIME_TRIGGERS: [&str; 3] = ["date", "now", "ts"];
+
 #[inline]
 pub fn char_is_word(ch: char) -> bool {
-    ch.is_alphanumeric() || ch == '_'
+    // combining marks and joiners extend previous char (grapheme), e.g. decomposed accents
+    ch.is_alphanumeric()
+        || ch == '_'
+        || ch == '\u{200D}'
+        || unicode_normalization::char::is_combining_mark(ch)
+}
+
+/// Grapheme is a word one by its base char, marks and joiners never start a word
+#[inline]
+fn grapheme_is_word(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+/// Words of text split on grapheme boundaries, e.g. emoji sequences never join a word
+pub fn split_words(text: &str) -> impl Iterator<Item = &str> {
+    let mut graphemes = text.grapheme_indices(true).peekable();
+    std::iter::from_fn(move || {
+        let (start, first) = graphemes.find(|(_, g)| grapheme_is_word(g))?;
+        let mut end = start + first.len();
+        while let Some((idx, grapheme)) = graphemes.next_if(|(_, g)| grapheme_is_word(g)) {
+            end = idx + grapheme.len();
+        }
+        Some(&text[start..end])
+    })
+}
+
+/// Byte length of word graphemes at start of text
+fn word_head_len(text: &str) -> usize {
+    text.graphemes(true)
+        .take_while(|g| grapheme_is_word(g))
+        .map(str::len)
+        .sum()
+}
+
+/// Byte length of word graphemes at end of text
+fn word_tail_len(text: &str) -> usize {
+    text.graphemes(true)
+        .rev()
+        .take_while(|g| grapheme_is_word(g))
+        .map(str::len)
+        .sum()
+}
+
+/// Requests waiting in backend queue
+pub fn queue_depth(tx: &mpsc::Sender<BackendRequest>) -> usize {
+    tx.max_capacity() - tx.capacity()
+}
+
+/// Match text by glob pattern with `*` (any chars) and `?` (single char)
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    // position in pattern and text to retry from on last `*`
+    let (mut p, mut t, mut star) = (0, 0, None);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Filesystem path of file uri, percent-decoded (with drive prefix on Windows)
+pub fn uri_to_path(uri: &Url) -> Option<std::path::PathBuf> {
+    if uri.scheme() != "file" {
+        return None;
+    }
+    uri.to_file_path().ok()
+}
+
+/// Matched workspace symbol as (score, name, kind, container, file, line),
+/// line is None for not yet located snippet
+type SymbolMatch = (
+    usize,
+    String,
+    SymbolKind,
+    String,
+    std::path::PathBuf,
+    Option<usize>,
+);
+
+/// Keys of .bib files in workspace roots
+fn workspace_bib_keys(roots: &[std::path::PathBuf]) -> Vec<BibKeys> {
+    let mut bib_files = Vec::new();
+    for root in roots {
+        markdown::collect_files(root, "bib", bibtex::MAX_BIB_FILES, &mut bib_files);
+    }
+    bib_files
+        .into_iter()
+        .filter_map(|path| {
+            let keys = bibtex::entries(&std::fs::read_to_string(&path).ok()?);
+            Some((path, keys))
+        })
+        .collect()
+}
+
+/// Match bibliography keys and locate matched symbols in its files, run on worker pool.
+/// Workspace .bib files are parsed when not cached, backend cache is updated then
+fn locate_symbols(
+    query: &str,
+    mut matched: Vec<SymbolMatch>,
+    cached: Option<Vec<BibKeys>>,
+    roots: &[std::path::PathBuf],
+    opened: Vec<BibKeys>,
+    backend_tx: &mpsc::Sender<BackendRequest>,
+) -> Vec<SymbolInformation> {
+    let mut bib_keys = cached.unwrap_or_else(|| {
+        let bib_keys = workspace_bib_keys(roots);
+        if backend_tx
+            .blocking_send(BackendRequest::LoadedBibKeys(bib_keys.clone()))
+            .is_err()
+        {
+            tracing::error!("Error on send bibliography keys");
+        }
+        bib_keys
+    });
+    for (path, keys) in opened {
+        bib_keys.retain(|(p, _)| *p != path);
+        bib_keys.push((path, keys));
+    }
+    for (path, entries) in bib_keys {
+        for entry in entries {
+            if let Some(score) = snippets::search::fuzzy_score(query, &entry.key) {
+                // resolved metadata, like the bibliography renders it
+                let container = entry
+                    .summary()
+                    .unwrap_or_else(|| "bibliography".to_string());
+                matched.push((
+                    score,
+                    entry.key,
+                    SymbolKind::KEY,
+                    container,
+                    path.clone(),
+                    Some(entry.line),
+                ));
+            }
+        }
+    }
+
+    matched.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    matched.truncate(MAX_WORKSPACE_SYMBOLS);
+
+    // locate snippets by its quoted prefix, reading each file once
+    let mut files: HashMap<std::path::PathBuf, Option<String>> = HashMap::new();
+    matched
+        .into_iter()
+        .filter_map(|(_, name, kind, container, path, line)| {
+            let line = line.unwrap_or_else(|| {
+                files
+                    .entry(path.clone())
+                    .or_insert_with(|| std::fs::read_to_string(&path).ok())
+                    .as_deref()
+                    .and_then(|text| {
+                        let quoted = [format!("\"{name}\""), format!("'{name}'")];
+                        text.lines()
+                            .position(|l| quoted.iter().any(|q| l.contains(q.as_str())))
+                    })
+                    .unwrap_or_default()
+            });
+            let position = Position {
+                line: line as u32,
+                character: 0,
+            };
+            #[allow(deprecated)]
+            Some(SymbolInformation {
+                name,
+                kind,
+                tags: None,
+                deprecated: None,
+                location: Location {
+                    uri: Url::from_file_path(&path).ok()?,
+                    range: Range {
+                        start: position,
+                        end: position,
+                    },
+                },
+                container_name: Some(container),
+            })
+        })
+        .collect()
 }
 
 pub struct RopeReader<'a> {
@@ -103,14 +814,82 @@ impl<'a> std::io::Read for RopeReader<'a> {
 
 #[derive(Debug)]
 pub enum BackendRequest {
+    Initialize(Box<InitializeParams>),
     NewDoc(DidOpenTextDocumentParams),
     ChangeDoc(DidChangeTextDocumentParams),
     ChangeConfiguration(DidChangeConfigurationParams),
+    // document content read from disk in background after save
+    LoadedDoc((Url, Rope)),
+    // evicted document content read from disk in background (with its restore number), None on error
+    RestoredDoc((Url, u64, Option<Rope>)),
+    // words of other tmux panes captured in background
+    TmuxWords(Vec<String>),
     SaveDoc(DidSaveTextDocumentParams),
+    // cached files changed on disk, reported by client
+    ChangeWatchedFiles(DidChangeWatchedFilesParams),
+    // metrics in Prometheus text format
+    Metrics(oneshot::Sender<String>),
+    // problems of current settings
+    SelfCheck(oneshot::Sender<Vec<String>>),
+    // enabled features with count of requests taken out of channel ahead, answered to health-check
+    Ping(oneshot::Sender<(Vec<&'static str>, usize)>),
+    // snippet completion accepted by user, by its prefix
+    SnippetAccepted(String),
+    // completion accepted by user, as (workspace, language, label)
+    CompletionAccepted((String, String, String)),
+    // mention files read in background, people are None when files are not changed
+    LoadedMentions(
+        (
+            MentionsKey,
+            mentions::MentionFiles,
+            Option<Vec<mentions::Person>>,
+        ),
+    ),
+    // output of command at working directory run in background
+    RanCommand((std::path::PathBuf, String, String)),
+    // output lines of git query at repository directory run in background
+    LoadedGit((std::path::PathBuf, git::GitQuery, Vec<String>)),
+    // tags of notes in workspace roots aggregated in background
+    LoadedTags((Vec<std::path::PathBuf>, HashMap<String, usize>)),
+    // words of workspace roots files indexed in background
+    IndexedWorkspace(Vec<(std::path::PathBuf, word_index::WordIndex)>),
+    // keys of workspace .bib files parsed in background
+    LoadedBibKeys(Vec<(std::path::PathBuf, Vec<bibtex::BibEntry>)>),
+    // user answer to run commands in workspace
+    WorkspaceTrust((String, bool)),
+    // snippets, unicode input and words loaded in background on startup
+    LoadedSources(Box<LoadedSources>),
+    // snippets or unicode input files are changed, sources are reloaded in background
+    ReloadSnippets,
+    // flip feature flag by name, answered with its new state or error on unknown feature
+    ToggleFeature((oneshot::Sender<Result<bool>>, String)),
+    // completion stats summary
+    Stats(oneshot::Sender<String>),
+    // client asked to shutdown, answered when state is persisted
+    Shutdown(oneshot::Sender<()>),
+    // unicode input (name, body) entries matched by name fragment
+    UnicodeByName((oneshot::Sender<Vec<(String, String)>>, String)),
+    // unicode input replacements of the word under cursor
+    CodeAction((oneshot::Sender<Vec<CodeActionOrCommand>>, CodeActionParams)),
+    // snippet prefixes and bibliography keys matched by query
+    WorkspaceSymbol((oneshot::Sender<Vec<SymbolInformation>>, String)),
+    // completion item with details filled in
+    ResolveCompletion((oneshot::Sender<CompletionItem>, Box<CompletionItem>)),
+    // paths and urls in document
+    DocumentLink((oneshot::Sender<Vec<DocumentLink>>, Url)),
+    // occurrences of the word under cursor
+    DocumentHighlight(
+        (
+            oneshot::Sender<Vec<DocumentHighlight>>,
+            TextDocumentPositionParams,
+        ),
+    ),
+    // with sender of partial results, when client asked for them
     CompletionRequest(
         (
             oneshot::Sender<anyhow::Result<BackendResponse>>,
             CompletionParams,
+            Option<PartialResultSender>,
         ),
     ),
 }
@@ -124,16 +903,203 @@ pub struct Document {
     uri: Url,
     text: Rope,
     language_id: String,
+    // last synchronized version from client
+    version: i32,
+    // documents clock value on last use, for LRU eviction
+    last_used: u64,
+    // changed since open or save, its text exists in client only and is never evicted
+    dirty: bool,
+    // fenced code blocks of markdown document
+    fences: fences::Fences,
+    // words of document, completion doesn't scan whole text
+    index: word_index::WordIndex,
+    // frontmatter tags of markdown document, parsed once per change
+    tags: Vec<String>,
+    // LaTeX or Typst labels defined in document, parsed once per change
+    labels: Vec<String>,
+}
+
+/// Document evicted from memory, only its word index is kept
+pub struct EvictedDocument {
+    language_id: String,
+    version: i32,
+    index: word_index::WordIndex,
+}
+
+impl Document {
+    fn new(
+        uri: Url,
+        text: Rope,
+        language_id: String,
+        version: i32,
+        words_filter: word_index::WordsFilter,
+    ) -> Self {
+        let mut doc = Document {
+            uri,
+            text: Rope::new(),
+            language_id,
+            version,
+            last_used: 0,
+            dirty: false,
+            fences: fences::Fences::default(),
+            index: word_index::WordIndex::with_filter(words_filter),
+            tags: Vec::new(),
+            labels: Vec::new(),
+        };
+        doc.set_text(text);
+        doc
+    }
+
+    /// Replace whole content, words and fences are scanned again
+    fn set_text(&mut self, text: Rope) {
+        self.text = text;
+        self.index = word_index::WordIndex::new(&self.text, self.index.filter().clone());
+        if self.language_id == "markdown" {
+            self.fences = fences::Fences::new(&self.text);
+        }
+        self.parse_refs();
+    }
+
+    /// Parse tags and labels of changed text, requests only merge them
+    fn parse_refs(&mut self) {
+        if self.language_id == "markdown" {
+            self.tags = markdown::frontmatter_tags(&markdown::frontmatter_head(&self.text));
+        }
+        if let Some(parse) = labels::parser(&self.language_id) {
+            self.labels = parse(&self.text);
+        }
+    }
+
+    /// Language of markdown fenced code block at line, None in prose
+    fn code_block(&self, line: u32) -> Option<&str> {
+        self.fences.language_at(line as usize)
+    }
+
+    /// Directory of the document file
+    fn dir(&self) -> Option<std::path::PathBuf> {
+        uri_to_path(&self.uri).and_then(|p| p.parent().map(std::path::Path::to_path_buf))
+    }
+
+    /// Char range of the word around position (with char column)
+    fn word_at(&self, position: Position) -> Option<(usize, usize)> {
+        let line_start = self.text.try_line_to_char(position.line as usize).ok()?;
+        let line = Cow::<str>::from(self.text.line(position.line as usize));
+        let column = position.character as usize;
+        let cursor = match line.char_indices().nth(column) {
+            Some((idx, _)) => idx,
+            None if column == line.chars().count() => line.len(),
+            None => return None,
+        };
+        let start = column
+            - line[cursor - word_tail_len(&line[..cursor])..cursor]
+                .chars()
+                .count();
+        let end = column
+            + line[cursor..cursor + word_head_len(&line[cursor..])]
+                .chars()
+                .count();
+        (start != end).then_some((line_start + start, line_start + end))
+    }
+
+    /// Language id used to filter snippets and sources, inferred from shebang for generic documents
+    fn scope(&self) -> &str {
+        if !shebang::GENERIC_LANGUAGES.contains(&self.language_id.as_str()) {
+            return &self.language_id;
+        }
+        self.text
+            .get_line(0)
+            .filter(|line| line.len_chars() > 2 && line.char(0) == '#' && line.char(1) == '!')
+            .and_then(|line| shebang::language_id(&line.to_string()))
+            .unwrap_or(&self.language_id)
+    }
 }
 
 pub struct BackendState {
     home_dir: String,
     settings: BackendSettings,
     docs: HashMap<Url, Document>,
+    evicted_docs: HashMap<Url, EvictedDocument>,
+    // workspaces allowed (or not) to run commands, None while user is asked
+    workspace_trust: RefCell<HashMap<String, Option<bool>>>,
+    // sender of (workspace, command) to ask user for trust, commands are trusted without it
+    trust_requests: Option<mpsc::UnboundedSender<(String, String)>>,
+    // words of documents are saved here on shutdown
+    warm_cache_path: Option<std::path::PathBuf>,
+    docs_clock: u64,
     snippets: Vec<Snippet>,
+    snippet_index: snippets::index::SnippetIndex,
+    // compiled snippets `when` and source line filters regexes, None for invalid
+    regexes: RefCell<HashMap<String, Option<regex::Regex>>>,
+    snippets_usage: snippets::usage::SnippetUsage,
+    acceptance: acceptance::AcceptanceStats,
+    // (workspace, language) of current completion request, when acceptance is recorded
+    acceptance_scope: RefCell<Option<(String, String)>>,
     unicode_input: HashMap<String, String>,
+    // aliases and descriptions of unicode input entries by prefix
+    unicode_input_info: HashMap<String, snippets::config::UnicodeInputInfo>,
     max_unicude_input_prefix: usize,
-    rx: mpsc::UnboundedReceiver<BackendRequest>,
+    words: Vec<WordEntry>,
+    // words of workspace roots word list files
+    workspace_words: Vec<(std::path::PathBuf, Vec<WordEntry>)>,
+    // words of other tmux panes with last capture time
+    tmux_words: Vec<String>,
+    tmux_captured: std::cell::Cell<Option<std::time::Instant>>,
+    // items limits of sources exceeded latency target
+    source_limits: RefCell<HashMap<&'static str, usize>>,
+    // items limit of currently collected source
+    source_limit: std::cell::Cell<Option<usize>>,
+    // results of recent completion requests, dropped on any state change
+    completion_cache: Vec<(CompletionCacheKey, std::time::Instant, Vec<CompletionItem>)>,
+    workspace_roots: Vec<std::path::PathBuf>,
+    // partial settings of workspace folders
+    folder_settings: Vec<(std::path::PathBuf, toml::Table)>,
+    // settings of workspace folders applied over client settings, resolved on any of them change
+    resolved_folder_settings: Vec<(std::path::PathBuf, BackendSettings)>,
+    tags: RefCell<Option<CachedTags>>,
+    // tags are aggregated in background
+    tags_loading: std::cell::Cell<bool>,
+    // current completion is inside markdown fenced code block, prose sources are inactive
+    in_code_block: std::cell::Cell<bool>,
+    // some source of current completion waits for background output, client asks again
+    completion_incomplete: std::cell::Cell<bool>,
+    // sender of partial results of current completion request with its document
+    partial_results: RefCell<Option<(PartialResultSender, Url)>>,
+    // words of workspace roots files, None until indexed
+    workspace_index: Option<Vec<(std::path::PathBuf, word_index::WordIndex)>>,
+    workspace_indexing: bool,
+    // reloads snippets, unicode input and words, set by server
+    sources_loader: Option<server::SourcesLoader>,
+    // snippets dirs, external snippets config and unicode input dir
+    sources_paths: Vec<std::path::PathBuf>,
+    // watcher of sources paths, kept while `watch_config` is on
+    config_watcher: Option<notify::RecommendedWatcher>,
+    // sources are reloaded in background, changed again while reloading
+    sources_reloading: bool,
+    sources_reload_pending: bool,
+    // entries keys of workspace .bib files
+    bib_keys: RefCell<Option<Vec<BibKeys>>>,
+    // recently used first at the end
+    automata: RefCell<Vec<(String, AhoCorasick)>>,
+    stats: RefCell<stats::CompletionStats>,
+    thesaurus: Option<(String, thesaurus::Thesaurus)>,
+    abbreviations: Option<(String, abbreviations::Abbreviations)>,
+    git: git::GitCache,
+    mentions: RefCell<HashMap<MentionsKey, CachedMentions>>,
+    mentions_loading: RefCell<HashSet<MentionsKey>>,
+    commands: command::CommandCache,
+    // saved documents waiting for content read from disk
+    pending_reads: HashSet<Url>,
+    // documents out of sync with client, waiting for content read from disk
+    stale_docs: HashSet<Url>,
+    // evicted documents read from disk, with restore number and changes applied when read
+    restoring_docs: HashMap<Url, (u64, Vec<DidChangeTextDocumentParams>)>,
+    position_encoding: PositionEncodingKind,
+    // completion capabilities of client, received on initialize
+    completion_capabilities: Option<CompletionClientCapabilities>,
+    tx: mpsc::Sender<BackendRequest>,
+    rx: mpsc::Receiver<BackendRequest>,
+    // requests taken out of channel ahead of time, while coalescing changes and completions
+    queued: std::collections::VecDeque<BackendRequest>,
 }
 
 impl BackendState {
@@ -141,15 +1107,26 @@ impl BackendState {
         home_dir: String,
         snippets: Vec<Snippet>,
         unicode_input: HashMap<String, String>,
-    ) -> (mpsc::UnboundedSender<BackendRequest>, Self) {
-        let (request_tx, request_rx) = mpsc::unbounded_channel::<BackendRequest>();
+        words: Vec<WordEntry>,
+    ) -> (mpsc::Sender<BackendRequest>, Self) {
+        let (request_tx, request_rx) = mpsc::channel::<BackendRequest>(REQUEST_QUEUE_CAPACITY);
 
         (
-            request_tx,
+            request_tx.clone(),
             BackendState {
                 home_dir,
                 settings: BackendSettings::default(),
                 docs: HashMap::new(),
+                evicted_docs: HashMap::new(),
+                workspace_trust: RefCell::new(HashMap::new()),
+                trust_requests: None,
+                warm_cache_path: None,
+                docs_clock: 0,
+                snippet_index: snippets::index::SnippetIndex::new(&snippets),
+                regexes: RefCell::new(HashMap::new()),
+                snippets_usage: snippets::usage::SnippetUsage::default(),
+                acceptance: acceptance::AcceptanceStats::default(),
+                acceptance_scope: RefCell::new(None),
                 snippets,
                 max_unicude_input_prefix: unicode_input
                     .keys()
@@ -157,116 +1134,819 @@ impl BackendState {
                     .max()
                     .unwrap_or_default(),
                 unicode_input,
+                unicode_input_info: HashMap::new(),
+                words,
+                workspace_words: Vec::new(),
+                tmux_words: Vec::new(),
+                tmux_captured: std::cell::Cell::new(None),
+                source_limits: RefCell::new(HashMap::new()),
+                source_limit: std::cell::Cell::new(None),
+                completion_cache: Vec::new(),
+                workspace_roots: Vec::new(),
+                folder_settings: Vec::new(),
+                resolved_folder_settings: Vec::new(),
+                workspace_index: None,
+                workspace_indexing: false,
+                sources_loader: None,
+                sources_paths: Vec::new(),
+                config_watcher: None,
+                sources_reloading: false,
+                sources_reload_pending: false,
+                bib_keys: RefCell::new(None),
+                tags_loading: std::cell::Cell::new(false),
+                in_code_block: std::cell::Cell::new(false),
+                completion_incomplete: std::cell::Cell::new(false),
+                partial_results: RefCell::new(None),
+                tags: RefCell::new(None),
+                automata: RefCell::new(Vec::new()),
+                stats: RefCell::new(stats::CompletionStats::default()),
+                thesaurus: None,
+                abbreviations: None,
+                git: git::GitCache::default(),
+                mentions: RefCell::new(HashMap::new()),
+                mentions_loading: RefCell::new(HashSet::new()),
+                commands: command::CommandCache::default(),
+                pending_reads: HashSet::new(),
+                stale_docs: HashSet::new(),
+                restoring_docs: HashMap::new(),
+                position_encoding: PositionEncodingKind::UTF16,
+                completion_capabilities: None,
+                tx: request_tx,
                 rx: request_rx,
+                queued: std::collections::VecDeque::new(),
             },
         )
     }
 
-    fn save_doc(&mut self, params: DidSaveTextDocumentParams) -> Result<()> {
-        let Some(doc) = self.docs.get_mut(&params.text_document.uri) else {
-            anyhow::bail!("Document {} not found", params.text_document.uri)
+    /// Replace snippets, unicode input and words, like on background load
+    pub fn set_sources(
+        &mut self,
+        snippets: Vec<Snippet>,
+        unicode_input: HashMap<String, String>,
+        words: Vec<WordEntry>,
+    ) {
+        self.snippet_index = snippets::index::SnippetIndex::new(&snippets);
+        self.snippets = snippets;
+        self.max_unicude_input_prefix = unicode_input
+            .keys()
+            .map(|s| s.len())
+            .max()
+            .unwrap_or_default();
+        self.unicode_input = unicode_input;
+        self.words = words;
+    }
+
+    /// Aliases and descriptions of unicode input entries, matched besides prefixes
+    pub fn set_unicode_input_info(
+        &mut self,
+        info: HashMap<String, snippets::config::UnicodeInputInfo>,
+    ) {
+        self.unicode_input_info = info;
+    }
+
+    /// Load accepted snippets counters used to rank snippets
+    pub fn load_snippets_usage(&mut self, path: &std::path::Path) {
+        match snippets::usage::SnippetUsage::load(path) {
+            Ok(usage) => self.snippets_usage = usage,
+            Err(e) => tracing::error!("On read snippets usage from {path:?}: {e}"),
+        }
+    }
+
+    /// Load accepted completions counters used to rank items of any source
+    pub fn load_acceptance(&mut self, path: &std::path::Path) {
+        match acceptance::AcceptanceStats::load(path) {
+            Ok(acceptance) => self.acceptance = acceptance,
+            Err(e) => tracing::error!("On read acceptance stats from {path:?}: {e}"),
+        }
+    }
+
+    /// Receiver of (workspace, command) requests to ask user for trust
+    pub fn trust_requests(&mut self) -> mpsc::UnboundedReceiver<(String, String)> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.trust_requests = Some(tx);
+        rx
+    }
+
+    /// Warm cache file, it's loaded on initialize for workspace roots
+    pub fn set_warm_cache_path(&mut self, path: &std::path::Path) {
+        self.warm_cache_path = Some(path.to_path_buf());
+    }
+
+    /// Preload words of last session documents unchanged on disk, as evicted ones
+    fn load_warm_cache(&mut self) {
+        let Some(path) = &self.warm_cache_path else {
+            return;
         };
-        doc.text = if let Some(text) = &params.text {
-            Rope::from_str(text)
-        } else {
-            // Sync read content from file
-            let file = std::fs::File::open(params.text_document.uri.path())?;
-            Rope::from_reader(file)?
+        let docs = match warm::WarmCache::load(path, &self.workspace_roots) {
+            Ok(docs) => docs,
+            Err(e) => {
+                tracing::error!("On read warm cache from {path:?}: {e}");
+                return;
+            }
         };
-        Ok(())
+        tracing::debug!("Preload {} documents from warm cache", docs.len());
+        for doc in docs {
+            let Ok(uri) = Url::from_file_path(&doc.path) else {
+                continue;
+            };
+            if !self.docs.contains_key(&uri) {
+                let words_filter = self.words_filter(&uri);
+                self.evicted_docs.insert(
+                    uri,
+                    EvictedDocument {
+                        language_id: doc.language_id,
+                        version: 0,
+                        index: word_index::WordIndex::from_words(doc.words, words_filter),
+                    },
+                );
+            }
+        }
     }
 
-    fn change_doc(&mut self, params: DidChangeTextDocumentParams) -> Result<()> {
-        if let Some(doc) = self.docs.get_mut(&params.text_document.uri) {
-            for change in params.content_changes {
-                let Some(range) = change.range else { continue };
-                let start_idx = doc
-                    .text
-                    .try_line_to_char(range.start.line as usize)
-                    .map(|idx| idx + range.start.character as usize);
-                let end_idx = doc
-                    .text
-                    .try_line_to_char(range.end.line as usize)
-                    .map(|idx| idx + range.end.character as usize)
-                    .and_then(|c| {
-                        if c > doc.text.len_chars() {
-                            Err(ropey::Error::CharIndexOutOfBounds(c, doc.text.len_chars()))
-                        } else {
-                            Ok(c)
-                        }
-                    });
+    /// Persist words of resident and evicted documents, most recently used first
+    fn save_warm_cache(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.warm_cache_path else {
+            return Ok(());
+        };
+        let mut resident = self.docs.values().collect::<Vec<_>>();
+        resident.sort_by_key(|d| std::cmp::Reverse(d.last_used));
+        let docs = resident
+            .into_iter()
+            .map(|d| (&d.uri, d.language_id.clone(), self.indexed_words(&d.index)))
+            .chain(
+                self.evicted_docs
+                    .iter()
+                    .map(|(uri, d)| (uri, d.language_id.clone(), self.indexed_words(&d.index))),
+            )
+            .filter_map(|(uri, language_id, words)| {
+                let path = uri_to_path(uri)?;
+                Some(warm::WarmDocument {
+                    mtime: warm::mtime(&path)?,
+                    path,
+                    language_id,
+                    words,
+                })
+            })
+            .take(warm::MAX_WARM_DOCS)
+            .collect();
+        warm::WarmCache {
+            roots: self.workspace_roots.clone(),
+            docs,
+        }
+        .save(path)
+    }
+
+    fn initialize(&mut self, params: Box<InitializeParams>) {
+        let params = *params;
+        self.position_encoding = position::negotiate(&params);
+        self.completion_capabilities = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.completion.clone());
+        #[allow(deprecated)]
+        let roots = params
+            .workspace_folders
+            .map(|folders| folders.into_iter().map(|f| f.uri).collect::<Vec<_>>())
+            .or_else(|| params.root_uri.map(|uri| vec![uri]))
+            .unwrap_or_default();
+
+        self.workspace_roots = roots
+            .into_iter()
+            .filter_map(|root| uri_to_path(&root))
+            .collect();
+
+        self.load_warm_cache();
+        self.load_workspace_words();
+        self.load_folder_settings();
+        self.index_workspace();
+    }
+
+    /// Sources loader with paths of its files, used to reload sources on their changes
+    pub fn set_sources_loader(
+        &mut self,
+        loader: server::SourcesLoader,
+        paths: Vec<std::path::PathBuf>,
+    ) {
+        self.sources_loader = Some(loader);
+        self.sources_paths = paths;
+    }
+
+    /// Reload sources in background, changes made while reloading are picked up by one more reload
+    fn reload_sources(&mut self) {
+        let Some(loader) = self.sources_loader.clone() else {
+            tracing::warn!("Sources have no loader, skip reload");
+            return;
+        };
+        if self.sources_reloading {
+            self.sources_reload_pending = true;
+            return;
+        }
+        self.sources_reloading = true;
+        self.in_background(move || {
+            let sources = loader();
+            for problem in &sources.problems {
+                tracing::warn!("Self-check: {problem}");
+            }
+            tracing::info!(
+                "Reloaded {} snippets, {} unicode inputs, {} words",
+                sources.snippets.len(),
+                sources.unicode_input.len(),
+                sources.words.len()
+            );
+            Some(BackendRequest::LoadedSources(Box::new(sources)))
+        });
+    }
+
+    /// Start (or stop) watching sources paths on disk by `watch_config` setting
+    fn watch_config(&mut self) {
+        if !self.settings.watch_config {
+            self.config_watcher = None;
+            return;
+        }
+        if self.config_watcher.is_some() || self.sources_loader.is_none() {
+            return;
+        }
+        let tx = self.tx.clone();
+        match config_watch::watch(&self.sources_paths, move || {
+            // burst of changes is coalesced by reload in progress
+            let _ = tx.try_send(BackendRequest::ReloadSnippets);
+        }) {
+            Ok(watcher) => self.config_watcher = Some(watcher),
+            Err(e) => tracing::error!("Error on watch config: {e}"),
+        }
+    }
+
+    /// Flip feature flag like by configuration change, returns its new state
+    fn toggle_feature(&mut self, feature: &str) -> Result<bool> {
+        let Some((_, enabled)) = self
+            .settings
+            .features()
+            .into_iter()
+            .find(|(name, _)| *name == feature)
+        else {
+            anyhow::bail!("Unknown feature: {feature}")
+        };
+        let mut settings = serde_json::Map::new();
+        settings.insert(format!("feature_{feature}"), (!enabled).into());
+        self.change_configuration(DidChangeConfigurationParams {
+            settings: settings.into(),
+        })?;
+        tracing::info!("Feature {feature} is toggled to {}", !enabled);
+        Ok(!enabled)
+    }
+
+    /// Index words of workspace roots files in background, once
+    fn index_workspace(&mut self) {
+        if !self.settings.feature_workspace_words
+            || self.workspace_roots.is_empty()
+            || self.workspace_index.is_some()
+            || self.workspace_indexing
+        {
+            return;
+        }
+        self.workspace_indexing = true;
+        let roots = self.workspace_roots.clone();
+        let exclude = self.settings.index_exclude.clone();
+        let words_filter = self.settings.words_filter.clone();
+        self.in_background(move || {
+            Some(BackendRequest::IndexedWorkspace(
+                workspace_index::index_roots(&roots, &exclude, &words_filter),
+            ))
+        });
+    }
+
+    /// Read settings files of workspace folders
+    fn load_folder_settings(&mut self) {
+        self.folder_settings = self
+            .workspace_roots
+            .iter()
+            .filter_map(|root| {
+                let path = root.join(FOLDER_SETTINGS_FILENAME);
+                let content = std::fs::read_to_string(&path).ok()?;
+                match content.parse::<toml::Table>() {
+                    Ok(table) => Some((root.clone(), table)),
+                    Err(e) => {
+                        tracing::error!("On read folder settings {path:?}: {e}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        self.resolve_folder_settings();
+    }
 
-                match (start_idx, end_idx) {
-                    (Ok(start_idx), Err(_)) => {
-                        doc.text.try_remove(start_idx..)?;
-                        doc.text.try_insert(start_idx, &change.text)?;
+    /// Client settings overridden by settings of each workspace folder
+    fn resolve_folder_settings(&mut self) {
+        self.resolved_folder_settings = self
+            .folder_settings
+            .iter()
+            .filter_map(|(root, table)| {
+                let mut table = table.clone();
+                for key in POLICY_SETTINGS {
+                    if table.remove(key).is_some() {
+                        tracing::warn!("Setting '{key}' of folder {root:?} is ignored");
                     }
-                    (Ok(start_idx), Ok(end_idx)) => {
-                        doc.text.try_remove(start_idx..end_idx)?;
-                        doc.text.try_insert(start_idx, &change.text)?;
+                }
+                match table.try_into::<PartialBackendSettings>() {
+                    Ok(partial) => {
+                        Some((root.clone(), self.settings.apply_partial_settings(partial)))
                     }
-                    (Err(_), _) => {
-                        *doc = Document {
-                            uri: doc.uri.clone(),
-                            text: Rope::from(change.text),
-                            language_id: doc.language_id.clone(),
-                        }
+                    Err(e) => {
+                        tracing::error!("Invalid settings of folder {root:?}: {e}");
+                        None
                     }
                 }
+            })
+            .collect();
+        self.refilter_words();
+    }
+
+    /// People of mention files, files are read in background on first use and when changed,
+    /// completion is answered as incomplete until they are read
+    fn mention_people(&self, key: MentionsKey) -> Option<Vec<mentions::Person>> {
+        let cached = self
+            .mentions
+            .borrow()
+            .get(&key)
+            .map(|(checked, files, people)| (*checked, files.clone(), people.clone()));
+        let is_expired = cached.as_ref().is_none_or(|(checked, _, _)| {
+            checked.is_none_or(|checked| checked.elapsed() > MENTIONS_CHECK_INTERVAL)
+        });
+        if is_expired && self.mentions_loading.borrow_mut().insert(key.clone()) {
+            let previous = cached.as_ref().map(|(_, files, _)| files.clone());
+            self.in_background(move || {
+                let files = mentions::MentionFiles::new(key.0.as_deref(), key.1.as_deref());
+                // not changed files are not parsed again
+                let people = (previous.as_ref() != Some(&files)).then(|| files.load());
+                Some(BackendRequest::LoadedMentions((key, files, people)))
+            });
+        }
+        match cached {
+            Some((_, _, people)) => Some(people),
+            None => {
+                self.completion_incomplete.set(true);
+                None
             }
         }
+    }
 
-        Ok(())
+    /// Changed mention file is checked again on next completion
+    fn expire_mentions(&self, path: &std::path::Path) {
+        for (checked, files, _) in self.mentions.borrow_mut().values_mut() {
+            if files.contains(path) {
+                *checked = None;
+            }
+        }
     }
 
-    fn change_configuration(&mut self, params: DidChangeConfigurationParams) -> Result<()> {
-        self.settings = self
-            .settings
-            .apply_partial_settings(serde_json::from_value(params.settings)?);
-        Ok(())
+    /// Words exclusion of document, by settings of its workspace folder
+    fn words_filter(&self, uri: &Url) -> word_index::WordsFilter {
+        self.folder_settings(uri)
+            .and_then(|idx| self.resolved_folder_settings.get(idx))
+            .map(|(_, settings)| &settings.words_filter)
+            .unwrap_or(&self.settings.words_filter)
+            .clone()
     }
 
-    fn get_prefix(&self, params: &CompletionParams) -> Result<(Option<&str>, &Document)> {
-        let Some(doc) = self
+    /// Index words again when their exclusion is changed, evicted documents (and workspace
+    /// files) drop newly excluded words only, as their text is not kept
+    fn refilter_words(&mut self) {
+        let filters = self
             .docs
-            .get(&params.text_document_position.text_document.uri)
-        else {
-            anyhow::bail!(
-                "Document {} not found",
-                params.text_document_position.text_document.uri
-            )
-        };
+            .keys()
+            .chain(self.evicted_docs.keys())
+            .map(|uri| (uri.clone(), self.words_filter(uri)))
+            .collect::<Vec<_>>();
+        for (uri, words_filter) in filters {
+            if let Some(doc) = self.docs.get_mut(&uri) {
+                if *doc.index.filter() != words_filter {
+                    doc.index = word_index::WordIndex::new(&doc.text, words_filter);
+                }
+            } else if let Some(doc) = self.evicted_docs.get_mut(&uri) {
+                if *doc.index.filter() != words_filter {
+                    doc.index.refilter(words_filter);
+                }
+            }
+        }
+        for (_, index) in self.workspace_index.iter_mut().flatten() {
+            if *index.filter() != self.settings.words_filter {
+                index.refilter(self.settings.words_filter.clone());
+            }
+        }
+    }
 
-        // word prefix
-        let cursor = doc
-            .text
-            .try_line_to_char(params.text_document_position.position.line as usize)?
-            + params.text_document_position.position.character as usize;
-        let mut iter = doc
-            .text
-            .get_chars_at(cursor)
-            .ok_or_else(|| anyhow::anyhow!("bounds error"))?;
-        iter.reverse();
-        let offset = iter.take_while(|ch| char_is_word(*ch)).count();
-        let start_offset = cursor.saturating_sub(offset);
+    /// Settings of document workspace folder (the nearest one), by index
+    fn folder_settings(&self, uri: &Url) -> Option<usize> {
+        let path = uri_to_path(uri)?;
+        self.resolved_folder_settings
+            .iter()
+            .enumerate()
+            .filter(|(_, (root, _))| path.starts_with(root))
+            .max_by_key(|(_, (root, _))| root.components().count())
+            .map(|(idx, _)| idx)
+    }
 
-        if cursor == start_offset {
-            return Ok((None, doc));
+    /// Swap client settings with settings of workspace folder for one request,
+    /// swapped back by the same call
+    fn swap_settings(&mut self, folder: Option<usize>) {
+        if let Some((_, settings)) =
+            folder.and_then(|idx| self.resolved_folder_settings.get_mut(idx))
+        {
+            std::mem::swap(&mut self.settings, settings);
         }
+    }
 
-        let len_chars = doc.text.len_chars();
-        if start_offset > len_chars || cursor > len_chars {
-            anyhow::bail!("bounds error")
+    /// Read word list files of workspace roots
+    fn load_workspace_words(&mut self) {
+        self.workspace_words.clear();
+        for root in &self.workspace_roots {
+            let path = root.join(WORDS_FILENAME);
+            if !path.exists() {
+                continue;
+            }
+            match snippets::config::load_words_from_file(&path) {
+                Ok(words) => self.workspace_words.push((root.clone(), words)),
+                Err(e) => tracing::error!("On read words from {path:?}: {e}"),
+            }
         }
+    }
 
-        let prefix = doc.text.slice(start_offset..cursor).as_str();
-        Ok((prefix, doc))
+    /// Invalidate caches of changed files
+    fn change_watched_files(&mut self, params: DidChangeWatchedFilesParams) {
+        for change in params.changes {
+            let Some(path) = uri_to_path(&change.uri) else {
+                continue;
+            };
+            tracing::debug!("Watched file changed: {path:?} {:?}", change.typ);
+            self.expire_mentions(&path);
+            if path.file_name().is_some_and(|f| f == WORDS_FILENAME) {
+                self.load_workspace_words();
+            } else if path
+                .file_name()
+                .is_some_and(|f| f == FOLDER_SETTINGS_FILENAME)
+            {
+                self.load_folder_settings();
+            } else if path.extension().is_some_and(|e| e == "bib") {
+                self.bib_keys.replace(None);
+            }
+        }
     }
 
-    fn get_prefix_as_chars(
-        &self,
-        params: &CompletionParams,
-        max_chars: usize,
+    fn save_doc(&mut self, params: DidSaveTextDocumentParams) -> Result<()> {
+        if self.restoring_docs.contains_key(&params.text_document.uri) {
+            // saved file has changes made while restoring, read it again
+            self.restore_doc(params.text_document.uri);
+            return Ok(());
+        }
+        let Some(doc) = self.docs.get_mut(&params.text_document.uri) else {
+            anyhow::bail!("Document {} not found", params.text_document.uri)
+        };
+        doc.dirty = false;
+        if let Some(text) = &params.text {
+            doc.set_text(Rope::from_str(text));
+            return Ok(());
+        }
+
+        let uri = params.text_document.uri;
+        if self.read_doc(uri.clone()) {
+            self.pending_reads.insert(uri);
+        }
+        Ok(())
+    }
+
+    /// Read document content from file in background, don't stall other requests
+    fn read_doc(&self, uri: Url) -> bool {
+        // untitled and other path-less documents live in client only
+        let Some(path) = uri_to_path(&uri) else {
+            tracing::warn!("Document {uri} has no file to read content from");
+            return false;
+        };
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            match tokio::fs::read_to_string(path).await {
+                Ok(content) => {
+                    let _ = tx
+                        .send(BackendRequest::LoadedDoc((uri, Rope::from(content))))
+                        .await;
+                }
+                Err(e) => tracing::error!("Error on read doc {uri}: {e}"),
+            }
+        });
+        true
+    }
+
+    /// Capture other tmux panes in background, at most once per interval
+    fn capture_tmux(&self) {
+        if self
+            .tmux_captured
+            .get()
+            .is_some_and(|captured| captured.elapsed() < tmux::CAPTURE_INTERVAL)
+        {
+            return;
+        }
+        self.tmux_captured.set(Some(std::time::Instant::now()));
+        self.in_background(|| match tmux::capture_panes(command::COMMAND_TIMEOUT) {
+            Ok(text) => Some(BackendRequest::TmuxWords(tmux::words(&text))),
+            Err(e) => {
+                tracing::debug!("On capture tmux panes: {e}");
+                None
+            }
+        });
+    }
+
+    /// Run blocking work on worker pool, its resulting request is handled by backend when ready
+    fn in_background(&self, f: impl FnOnce() -> Option<BackendRequest> + Send + 'static) {
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Some(request) = f() {
+                if tx.blocking_send(request).is_err() {
+                    tracing::error!("Error on send background result");
+                }
+            }
+        });
+    }
+
+    /// Output lines of git query at repository directory, run in background when not cached
+    /// or expired, completion is answered as incomplete until it is loaded
+    fn git_lines(&self, dir: &std::path::Path, query: git::GitQuery) -> Option<Vec<String>> {
+        let (lines, load) = self.git.get(dir, query);
+        if load {
+            let dir = dir.to_path_buf();
+            self.in_background(move || {
+                let lines = git::load(&dir, query).unwrap_or_else(|e| {
+                    tracing::warn!("On run git {query:?} at {dir:?}: {e}");
+                    Vec::new()
+                });
+                Some(BackendRequest::LoadedGit((dir, query, lines)))
+            });
+        }
+        if lines.is_none() {
+            self.completion_incomplete.set(true);
+        }
+        lines
+    }
+
+    /// Output of command (by its cache key) at working directory, run in background by `run`
+    /// when not cached or expired, completion is answered as incomplete until it is done
+    fn command_output(
+        &self,
+        dir: &std::path::Path,
+        command: &str,
+        ttl: std::time::Duration,
+        run: impl FnOnce(&std::path::Path) -> Result<String> + Send + 'static,
+    ) -> Option<String> {
+        let (output, load) = self.commands.get(dir, command, ttl);
+        if load {
+            let dir = dir.to_path_buf();
+            let command = command.to_string();
+            self.in_background(move || {
+                let output = run(&dir).unwrap_or_else(|e| {
+                    tracing::warn!("On run command at {dir:?}: {e}");
+                    String::new()
+                });
+                Some(BackendRequest::RanCommand((dir, command, output)))
+            });
+        }
+        if output.is_none() {
+            self.completion_incomplete.set(true);
+        }
+        output
+    }
+
+    /// Document is out of sync with client, skip changes until it is re-read from disk
+    fn resync_doc(&mut self, uri: Url, reason: &str) {
+        tracing::warn!("Document {uri} is out of sync ({reason}), re-read from disk");
+        // path-less documents stay stale until next full content change
+        if self.stale_docs.insert(uri.clone()) {
+            self.read_doc(uri);
+        }
+    }
+
+    fn change_doc(&mut self, params: DidChangeTextDocumentParams) -> Result<()> {
+        if let Some((_, changes)) = self.restoring_docs.get_mut(&params.text_document.uri) {
+            changes.push(params);
+            return Ok(());
+        }
+        let uri = params.text_document.uri;
+        let version = params.text_document.version;
+        let Some(doc) = self.docs.get_mut(&uri) else {
+            anyhow::bail!("Document {uri} not found")
+        };
+        doc.dirty = true;
+
+        if version <= doc.version {
+            let reason = format!("version {version} after {}", doc.version);
+            self.resync_doc(uri, &reason);
+            return Ok(());
+        }
+        doc.version = version;
+
+        if self.stale_docs.contains(&uri) {
+            // full content brings document back in sync
+            if !params.content_changes.iter().any(|c| c.range.is_none()) {
+                return Ok(());
+            }
+            self.stale_docs.remove(&uri);
+        }
+
+        let result = Self::apply_changes(doc, params.content_changes, &self.position_encoding);
+        if let Err(e) = result {
+            self.resync_doc(uri, &e.to_string());
+        }
+
+        Ok(())
+    }
+
+    fn apply_changes(
+        doc: &mut Document,
+        changes: Vec<TextDocumentContentChangeEvent>,
+        encoding: &PositionEncodingKind,
+    ) -> Result<()> {
+        // changes before the last full content one are overwritten by it
+        let skip = changes
+            .iter()
+            .rposition(|change| change.range.is_none())
+            .unwrap_or_default();
+        let mut edited = false;
+        for change in changes.into_iter().skip(skip) {
+            // full document content
+            let Some(mut range) = change.range else {
+                doc.set_text(Rope::from(change.text));
+                continue;
+            };
+            edited = true;
+            let old_end_line = range.end.line as usize;
+            for position in [&mut range.start, &mut range.end] {
+                if let Some(line) = doc.text.get_line(position.line as usize) {
+                    position.character =
+                        position::to_char_column(line, position.character, encoding);
+                }
+            }
+            let start_idx = doc
+                .text
+                .try_line_to_char(range.start.line as usize)
+                .map(|idx| idx + range.start.character as usize);
+            let end_idx = doc
+                .text
+                .try_line_to_char(range.end.line as usize)
+                .map(|idx| idx + range.end.character as usize)
+                .and_then(|c| {
+                    if c > doc.text.len_chars() {
+                        Err(ropey::Error::CharIndexOutOfBounds(c, doc.text.len_chars()))
+                    } else {
+                        Ok(c)
+                    }
+                });
+
+            let (start_idx, end_idx) = match (start_idx, end_idx) {
+                (Ok(start_idx), Err(_)) => (start_idx, doc.text.len_chars()),
+                (Ok(start_idx), Ok(end_idx)) => (start_idx, end_idx),
+                (Err(e), _) => {
+                    anyhow::bail!("Failed to apply change at {:?}: {e}", range.start)
+                }
+            };
+            // words of edited lines are counted again, document is re-read on error
+            let start_line = doc.text.try_char_to_line(start_idx)?;
+            let end_line = doc.text.try_char_to_line(end_idx.max(start_idx))?;
+            doc.index.remove_lines(&doc.text, start_line, end_line);
+            doc.text.try_remove(start_idx..end_idx)?;
+            doc.text.try_insert(start_idx, &change.text)?;
+            let end_line = doc
+                .text
+                .char_to_line(start_idx + change.text.chars().count());
+            doc.index.add_lines(&doc.text, start_line, end_line);
+            if doc.language_id == "markdown" {
+                let start_line = range.start.line as usize;
+                let new_end_line = start_line + change.text.matches('\n').count();
+                doc.fences
+                    .edit(&doc.text, start_line, old_end_line, new_end_line);
+            }
+        }
+        if edited {
+            doc.parse_refs();
+        }
+
+        Ok(())
+    }
+
+    fn change_configuration(&mut self, params: DidChangeConfigurationParams) -> Result<()> {
+        self.settings = self
+            .settings
+            .apply_partial_settings(serde_json::from_value(params.settings)?);
+        self.resolve_folder_settings();
+
+        // (re)load thesaurus on path change
+        if let (true, Some(path)) = (
+            self.settings.feature_thesaurus,
+            &self.settings.thesaurus_path,
+        ) {
+            if self.thesaurus.as_ref().map(|(p, _)| p) != Some(path) {
+                let expanded = if path.starts_with("~/") {
+                    path.replacen('~', &self.home_dir, 1)
+                } else {
+                    path.to_string()
+                };
+                // other settings are applied anyway
+                match thesaurus::Thesaurus::from_path(std::path::Path::new(&expanded)) {
+                    Ok(thesaurus) => self.thesaurus = Some((path.to_string(), thesaurus)),
+                    Err(e) => tracing::error!("On read thesaurus from {expanded}: {e}"),
+                }
+            }
+        }
+
+        // (re)load abbreviations on path change
+        if let (true, Some(path)) = (
+            self.settings.feature_abbreviations,
+            &self.settings.abbreviations_path,
+        ) {
+            if self.abbreviations.as_ref().map(|(p, _)| p) != Some(path) {
+                let expanded = if path.starts_with("~/") {
+                    path.replacen('~', &self.home_dir, 1)
+                } else {
+                    path.to_string()
+                };
+                match abbreviations::Abbreviations::from_path(std::path::Path::new(&expanded)) {
+                    Ok(abbreviations) => {
+                        tracing::info!("Loaded {} abbreviations", abbreviations.len());
+                        self.abbreviations = Some((path.to_string(), abbreviations));
+                    }
+                    Err(e) => tracing::error!("On read abbreviations from {expanded}: {e}"),
+                }
+            }
+        }
+
+        self.index_workspace();
+        self.watch_config();
+
+        Ok(())
+    }
+
+    fn get_prefix(&self, params: &CompletionParams) -> Result<(Option<&str>, &Document)> {
+        let Some(doc) = self
+            .docs
+            .get(&params.text_document_position.text_document.uri)
+        else {
+            anyhow::bail!(
+                "Document {} not found",
+                params.text_document_position.text_document.uri
+            )
+        };
+
+        // word prefix
+        let line_start = doc
+            .text
+            .try_line_to_char(params.text_document_position.position.line as usize)?;
+        let cursor = line_start + params.text_document_position.position.character as usize;
+        let before = Cow::<str>::from(
+            doc.text
+                .get_slice(line_start..cursor)
+                .ok_or_else(|| anyhow::anyhow!("bounds error"))?,
+        );
+        let offset = before[before.len() - word_tail_len(&before)..]
+            .chars()
+            .count();
+        let start_offset = cursor - offset;
+
+        if cursor == start_offset {
+            return Ok((None, doc));
+        }
+
+        let len_chars = doc.text.len_chars();
+        if start_offset > len_chars || cursor > len_chars {
+            anyhow::bail!("bounds error")
+        }
+
+        let prefix = doc.text.slice(start_offset..cursor).as_str();
+        Ok((prefix, doc))
+    }
+
+    fn get_line_prefix(&self, params: &CompletionParams) -> Result<(String, &Document)> {
+        let Some(doc) = self
+            .docs
+            .get(&params.text_document_position.text_document.uri)
+        else {
+            anyhow::bail!(
+                "Document {} not found",
+                params.text_document_position.text_document.uri
+            )
+        };
+
+        let line_start = doc
+            .text
+            .try_line_to_char(params.text_document_position.position.line as usize)?;
+        let cursor = line_start + params.text_document_position.position.character as usize;
+        if cursor > doc.text.len_chars() {
+            anyhow::bail!("bounds error")
+        }
+
+        Ok((doc.text.slice(line_start..cursor).to_string(), doc))
+    }
+
+    fn get_prefix_as_chars(
+        &self,
+        params: &CompletionParams,
+        max_chars: usize,
     ) -> Result<(Option<&str>, &Document)> {
         let Some(doc) = self
             .docs
@@ -308,7 +1988,18 @@ impl BackendState {
         Ok((prefix, doc))
     }
 
-    fn search(
+    /// Words of document index starting with prefix
+    fn search(&self, prefix: &str, doc: &Document, to_take: usize) -> HashSet<String> {
+        doc.index
+            .starting_with(prefix)
+            .filter(|word| *word != prefix)
+            .take(to_take)
+            .map(String::from)
+            .collect()
+    }
+
+    /// Text starting with prefix (which may contain non-word chars) up to word end, scans whole text
+    fn scan(
         &self,
         ac: &AhoCorasick,
         prefix: &str,
@@ -324,23 +2015,22 @@ impl BackendState {
             let mat = mat?;
             let mat_end = doc.text.byte_to_char(mat.end());
 
-            let word_end = doc
+            let line_end = doc
                 .text
-                .chars()
-                .skip(mat_end)
-                .take_while(|ch| char_is_word(*ch))
-                .count();
-
-            let word_end = doc.text.char_to_byte(mat_end + word_end);
+                .line_to_char(doc.text.char_to_line(mat_end) + 1)
+                .min(doc.text.len_chars());
+            let rest = Cow::<str>::from(doc.text.slice(mat_end..line_end));
+            let word_end = mat.end() + word_head_len(&rest);
 
             if word_end > len_bytes {
                 continue;
             }
 
-            let item = doc.text.byte_slice(mat.start()..word_end);
-            if item != prefix {
-                result.insert(item.to_string());
-                if result.len() >= self.settings.max_completion_items {
+            let item = doc.text.byte_slice(mat.start()..word_end).to_string();
+            // scanned text is not indexed, so it's filtered here
+            if item != prefix && !doc.index.filter().is_excluded(&item) {
+                result.insert(item);
+                if result.len() >= self.max_items() {
                     return Ok(result);
                 }
             }
@@ -349,36 +2039,317 @@ impl BackendState {
         Ok(result)
     }
 
-    fn completion(&self, prefix: &str, current_doc: &Document) -> Result<HashSet<String>> {
-        // prepare search pattern
+    /// Occurrences of the whole word under cursor in document
+    fn document_highlights(
+        &self,
+        params: &TextDocumentPositionParams,
+    ) -> Result<Vec<DocumentHighlight>> {
+        let Some(doc) = self.docs.get(&params.text_document.uri) else {
+            anyhow::bail!("Document {} not found", params.text_document.uri)
+        };
+        let Some((start, end)) = doc.word_at(params.position) else {
+            return Ok(Vec::new());
+        };
+        let word = doc.text.slice(start..end).to_string();
+
+        let is_boundary = |idx: usize| doc.text.get_char(idx).is_none_or(|ch| !char_is_word(ch));
+        let mut result = Vec::new();
+        for mat in self
+            .automaton(&word)?
+            .try_stream_find_iter(RopeReader::new(&doc.text))?
+        {
+            let mat = mat?;
+            let start = doc.text.byte_to_char(mat.start());
+            let end = doc.text.byte_to_char(mat.end());
+            // automaton is case insensitive, match whole word exactly
+            if (start > 0 && !is_boundary(start - 1))
+                || !is_boundary(end)
+                || doc.text.slice(start..end) != word.as_str()
+            {
+                continue;
+            }
+            result.push(DocumentHighlight {
+                range: Range {
+                    start: position::from_char_index(&doc.text, start, &self.position_encoding),
+                    end: position::from_char_index(&doc.text, end, &self.position_encoding),
+                },
+                kind: Some(DocumentHighlightKind::TEXT),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Unicode input entries by name fragment: exact match, by prefix and by substring
+    fn unicode_by_name(&self, fragment: &str) -> Vec<(String, String)> {
+        let mut matched = self
+            .unicode_input
+            .iter()
+            .filter_map(|(name, body)| {
+                let info = self.unicode_input_info.get(name);
+                let rank = unicode_input::match_rank(fragment, name, info)?;
+                Some((rank, name, body))
+            })
+            .collect::<Vec<_>>();
+        matched.sort();
+        matched
+            .into_iter()
+            .take(self.max_items())
+            .map(|(_, name, body)| (name.to_string(), body.to_string()))
+            .collect()
+    }
+
+    /// Replace the word under cursor by unicode input entries matched by its name
+    fn code_actions(&self, params: &CodeActionParams) -> Vec<CodeActionOrCommand> {
+        if !self.settings.feature_unicode_input {
+            return Vec::new();
+        }
+        let uri = &params.text_document.uri;
+        let Some(doc) = self.docs.get(uri) else {
+            return Vec::new();
+        };
+        let Some((start, end)) = doc.word_at(params.range.start) else {
+            return Vec::new();
+        };
+        let word = doc.text.slice(start..end).to_string();
+        let range = Range {
+            start: position::from_char_index(&doc.text, start, &self.position_encoding),
+            end: position::from_char_index(&doc.text, end, &self.position_encoding),
+        };
+        self.unicode_by_name(&word)
+            .into_iter()
+            .map(|(name, body)| {
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Insert {body} ({name})"),
+                    kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from_iter([(
+                            uri.clone(),
+                            vec![TextEdit {
+                                range,
+                                new_text: body,
+                            }],
+                        )])),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    /// Snippet prefixes and bibliography keys (of opened and workspace .bib files) with its location,
+    /// files are read on worker pool and symbols are sent from there
+    fn workspace_symbols(&self, query: String, tx: oneshot::Sender<Vec<SymbolInformation>>) {
+        let mut matched: Vec<SymbolMatch> = Vec::new();
+        for snippet in &self.snippets {
+            let (Some(score), Some(file)) = (
+                snippets::search::fuzzy_score(&query, &snippet.prefix),
+                &snippet.file,
+            ) else {
+                continue;
+            };
+            let container = match &snippet.scope {
+                Some(scope) => format!("snippet ({})", scope.join(", ")),
+                None => "snippet".to_string(),
+            };
+            matched.push((
+                score,
+                snippet.prefix.clone(),
+                SymbolKind::FUNCTION,
+                container,
+                file.clone(),
+                None,
+            ));
+        }
+
+        let cached = self.bib_keys.borrow().clone();
+        let roots = self.workspace_roots.clone();
+        // opened documents may be not saved yet
+        let opened = self
+            .docs
+            .values()
+            .filter_map(|doc| {
+                let path = uri_to_path(&doc.uri)?;
+                (bibtex::BIBTEX_LANGUAGES.contains(&doc.language_id.as_str())
+                    || path.extension().is_some_and(|e| e == "bib"))
+                .then(|| (path, bibtex::entries(&doc.text.to_string())))
+            })
+            .collect::<Vec<_>>();
+        let backend_tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let symbols = locate_symbols(&query, matched, cached, &roots, opened, &backend_tx);
+            if tx.send(symbols).is_err() {
+                tracing::error!("Error on send workspace symbols");
+            }
+        });
+    }
+
+    /// Existing paths (relative to document dir or working dir) and urls in document
+    fn document_links(&self, uri: &Url) -> Result<Vec<DocumentLink>> {
+        let Some(doc) = self.docs.get(uri) else {
+            anyhow::bail!("Document {uri} not found")
+        };
+        let doc_dir = doc.dir();
+        let mut result = Vec::new();
+        for (line_idx, line) in doc.text.lines().enumerate() {
+            let line_text = Cow::from(line);
+            for (start, end, text, kind) in
+                links::find_links(&line_text, self.settings.max_path_chars)
+            {
+                let target = match kind {
+                    links::LinkKind::Url => Url::parse(text).ok(),
+                    links::LinkKind::Path => {
+                        let path = if let Some(rest) = text.strip_prefix("~/") {
+                            std::path::Path::new(&self.home_dir).join(rest)
+                        } else {
+                            std::path::PathBuf::from(text)
+                        };
+                        let path = match &doc_dir {
+                            Some(dir) if path.is_relative() && dir.join(&path).exists() => {
+                                dir.join(&path)
+                            }
+                            _ => path,
+                        };
+                        std::path::absolute(&path)
+                            .ok()
+                            .filter(|path| path.exists())
+                            .and_then(|path| Url::from_file_path(path).ok())
+                    }
+                };
+                let Some(target) = target else {
+                    continue;
+                };
+                let position = |column: usize| Position {
+                    line: line_idx as u32,
+                    character: position::from_char_column(
+                        line,
+                        column as u32,
+                        &self.position_encoding,
+                    ),
+                };
+                result.push(DocumentLink {
+                    range: Range {
+                        start: position(start),
+                        end: position(end),
+                    },
+                    target: Some(target),
+                    tooltip: None,
+                    data: None,
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// Search automaton for prefix, reused from LRU cache while user types
+    fn automaton(&self, prefix: &str) -> Result<AhoCorasick> {
+        // automaton is ascii case insensitive
+        let key = prefix.to_ascii_lowercase();
+        let span = tracing::debug_span!("automata_cache", hit = false).entered();
+        let mut cache = self.automata.borrow_mut();
+        if let Some(i) = cache.iter().position(|(k, _)| *k == key) {
+            let entry = cache.remove(i);
+            let ac = entry.1.clone();
+            cache.push(entry);
+            self.stats.borrow_mut().automata_hits += 1;
+            span.record("hit", true);
+            return Ok(ac);
+        }
+
         let ac = AhoCorasick::builder()
             .ascii_case_insensitive(true)
             .build([&prefix])
             .map_err(|e| anyhow::anyhow!("error {e}"))?;
+        self.stats.borrow_mut().automata_misses += 1;
+        if cache.len() >= AUTOMATA_CACHE_SIZE {
+            cache.remove(0);
+        }
+        cache.push((key, ac.clone()));
+        Ok(ac)
+    }
+
+    /// Hex colors like `#ff0000` of documents, `#` is not a word char so documents are scanned
+    fn used_colors(&self, prefix: &str, current_doc: &Document) -> Result<HashSet<String>> {
+        let ac = self.automaton(prefix)?;
+        let mut result = self.scan(&ac, prefix, current_doc, self.max_items())?;
+        let root = self.isolated_root(current_doc);
+        for doc in self.docs.values().filter(|doc| {
+            doc.uri != current_doc.uri
+                && root.is_none_or(|root| {
+                    uri_to_path(&doc.uri).is_some_and(|path| path.starts_with(root))
+                })
+        }) {
+            if result.len() >= self.max_items() {
+                break;
+            }
+            result.extend(self.scan(&ac, prefix, doc, self.max_items() - result.len())?);
+        }
+        Ok(result)
+    }
 
+    fn completion(&self, prefix: &str, current_doc: &Document) -> Result<HashSet<String>> {
         // search in current doc at first
-        let mut result =
-            self.search(&ac, prefix, current_doc, self.settings.max_completion_items)?;
-        if result.len() >= self.settings.max_completion_items {
+        let mut result = self.search(prefix, current_doc, self.max_items());
+        if result.len() >= self.max_items() {
             return Ok(result);
         }
 
-        for doc in self.docs.values().filter(|doc| doc.uri != current_doc.uri) {
-            result.extend(self.search(
-                &ac,
-                prefix,
-                doc,
-                self.settings.max_completion_items - result.len(),
-            )?);
-            if result.len() >= self.settings.max_completion_items {
+        let root = self.isolated_root(current_doc);
+        let in_root = |uri: &Url| {
+            root.is_none_or(|root| uri_to_path(uri).is_some_and(|path| path.starts_with(root)))
+        };
+
+        for doc in self
+            .docs
+            .values()
+            .filter(|doc| doc.uri != current_doc.uri && in_root(&doc.uri))
+        {
+            result.extend(self.search(prefix, doc, self.max_items() - result.len()));
+            if result.len() >= self.max_items() {
+                return Ok(result);
+            }
+        }
+
+        // evicted documents contribute by its word index only
+        for word in self
+            .evicted_docs
+            .iter()
+            .filter(|(uri, _)| in_root(uri))
+            .flat_map(|(_, d)| d.index.starting_with(prefix))
+            .filter(|word| *word != prefix)
+        {
+            result.insert(word.to_string());
+            if result.len() >= self.max_items() {
                 return Ok(result);
             }
         }
 
+        // words of workspace files on disk are the least priority
+        if self.settings.feature_workspace_words {
+            for (_, index) in self
+                .workspace_index
+                .iter()
+                .flatten()
+                .filter(|(r, _)| root.is_none_or(|root| r == root))
+            {
+                for word in index.starting_with(prefix).filter(|word| *word != prefix) {
+                    result.insert(word.to_string());
+                    if result.len() >= self.max_items() {
+                        return Ok(result);
+                    }
+                }
+            }
+        }
+
         Ok(result)
     }
 
     fn words(&self, prefix: &str, doc: &Document) -> impl Iterator<Item = CompletionItem> {
+        // context line is located on resolve, current document first
+        let data = self
+            .settings
+            .word_context
+            .then(|| serde_json::json!({ WORD_CONTEXT_DATA: doc.uri }));
         match self.completion(prefix, doc) {
             Ok(words) => words.into_iter(),
             Err(e) => {
@@ -386,53 +2357,433 @@ impl BackendState {
                 HashSet::new().into_iter()
             }
         }
-        .map(|word| CompletionItem {
+        .map(move |word| CompletionItem {
             label: word,
             kind: Some(CompletionItemKind::TEXT),
+            data: data.clone(),
             ..Default::default()
         })
     }
 
-    fn snippets<'a>(
-        &'a self,
-        prefix: &'a str,
-        doc: &'a Document,
-    ) -> impl Iterator<Item = CompletionItem> + 'a {
-        self.snippets
-            .iter()
-            .filter(move |s| {
-                s.prefix.starts_with(prefix)
-                    && if let Some(scope) = &s.scope {
-                        scope.is_empty() | scope.contains(&doc.language_id)
-                    } else {
-                        true
-                    }
-            })
-            .map(move |s| CompletionItem {
-                label: s.prefix.to_owned(),
-                kind: Some(CompletionItemKind::SNIPPET),
-                detail: Some(if let Some(description) = &s.description {
-                    format!("{description}\n{}", s.body)
-                } else {
-                    s.body.to_string()
-                }),
-                insert_text: Some(s.body.to_string()),
-                insert_text_format: Some(InsertTextFormat::SNIPPET),
-                ..Default::default()
-            })
-            .take(self.settings.max_completion_items)
+    /// Client renders markdown documentation of completion items
+    fn markdown_documentation(&self) -> bool {
+        self.completion_capabilities
+            .as_ref()
+            .and_then(|c| c.completion_item.as_ref())
+            .and_then(|c| c.documentation_format.as_ref())
+            .is_some_and(|formats| formats.contains(&MarkupKind::Markdown))
     }
 
-    fn unicode_input(
-        &self,
-        word_prefix: &str,
-        params: &CompletionParams,
-    ) -> impl Iterator<Item = CompletionItem> {
-        let Ok((chars, _doc)) = self.get_prefix_as_chars(params, self.max_unicude_input_prefix)
-        else {
-            tracing::error!("Failed to get prefix as sequence of chars");
-            return Vec::new().into_iter();
-        };
+    /// Client expands snippet syntax of inserted text
+    fn snippet_support(&self) -> bool {
+        self.completion_capabilities
+            .as_ref()
+            .and_then(|c| c.completion_item.as_ref())
+            .and_then(|c| c.snippet_support)
+            .unwrap_or(false)
+    }
+
+    /// Snippet body inserted by client, placeholders are reduced to text without snippet support
+    fn snippet_text(&self, body: &str) -> String {
+        if self.snippet_support() {
+            body.to_string()
+        } else {
+            snippets::plain::to_plain_text(body)
+        }
+    }
+
+    fn snippet_format(&self) -> InsertTextFormat {
+        if self.snippet_support() {
+            InsertTextFormat::SNIPPET
+        } else {
+            InsertTextFormat::PLAIN_TEXT
+        }
+    }
+
+    /// Code shown by item documentation, fenced with its language when client renders markdown
+    fn code_documentation(&self, code: &str, language: &str) -> Documentation {
+        if !self.markdown_documentation() {
+            return Documentation::String(code.to_string());
+        }
+        let fence = if code.contains("```") { "````" } else { "```" };
+        Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("{fence}{language}\n{code}\n{fence}"),
+        })
+    }
+
+    /// Fill detail and documentation of word item by line where the word is found
+    fn resolve_completion(&self, mut item: CompletionItem) -> CompletionItem {
+        let Some(uri) = item
+            .data
+            .as_ref()
+            .and_then(|data| data.get(WORD_CONTEXT_DATA))
+            .and_then(|uri| uri.as_str())
+            .and_then(|uri| Url::parse(uri).ok())
+        else {
+            return item;
+        };
+        // lines are scanned only in documents with the word, current document first
+        let docs = self
+            .docs
+            .get(&uri)
+            .into_iter()
+            .chain(self.docs.values().filter(|doc| doc.uri != uri))
+            .filter(|doc| doc.index.contains(&item.label));
+        for doc in docs {
+            let found = doc.text.lines().enumerate().find_map(|(idx, line)| {
+                let line = line.to_string();
+                let is_word = line.match_indices(&item.label).any(|(start, word)| {
+                    let end = start + word.len();
+                    !line[..start].chars().next_back().is_some_and(char_is_word)
+                        && !line[end..].chars().next().is_some_and(char_is_word)
+                });
+                is_word.then_some((idx, line))
+            });
+            let Some((idx, line)) = found else {
+                continue;
+            };
+            let file_name = uri_to_path(&doc.uri)
+                .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_else(|| doc.uri.to_string());
+            item.detail = Some(format!("{file_name}:{}", idx + 1));
+            let line = line
+                .trim()
+                .chars()
+                .take(MAX_WORD_CONTEXT_CHARS)
+                .collect::<String>();
+            item.documentation = Some(self.code_documentation(&line, doc.scope()));
+            break;
+        }
+        item
+    }
+
+    fn tmux_words<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = CompletionItem> + 'a {
+        let lowercase_prefix = prefix.to_lowercase();
+        self.tmux_words
+            .iter()
+            .filter(move |w| *w != prefix && w.to_lowercase().starts_with(&lowercase_prefix))
+            .map(|w| CompletionItem {
+                label: w.to_owned(),
+                kind: Some(CompletionItemKind::TEXT),
+                detail: Some("tmux".to_string()),
+                ..Default::default()
+            })
+            .take(self.max_items())
+    }
+
+    fn word_list<'a>(
+        &'a self,
+        prefix: &'a str,
+        doc: &Document,
+    ) -> impl Iterator<Item = CompletionItem> + 'a {
+        let lowercase_prefix = prefix.to_lowercase();
+        let root = self.isolated_root(doc).cloned();
+        self.words
+            .iter()
+            .chain(
+                self.workspace_words
+                    .iter()
+                    .filter(move |(r, _)| root.as_ref().is_none_or(|root| r == root))
+                    .flat_map(|(_, words)| words),
+            )
+            .filter(move |w| {
+                w.word != prefix && w.word.to_lowercase().starts_with(&lowercase_prefix)
+            })
+            .map(|w| CompletionItem {
+                label: w.word.to_owned(),
+                kind: Some(CompletionItemKind::TEXT),
+                detail: w.description.to_owned(),
+                ..Default::default()
+            })
+            .take(self.max_items())
+    }
+
+    /// Expansion of typed abbreviation in prose documents
+    fn abbreviations(
+        &self,
+        word: &str,
+        params: &CompletionParams,
+        doc: &Document,
+    ) -> Option<CompletionItem> {
+        let (_, abbreviations) = self.abbreviations.as_ref()?;
+        if !abbreviations::PROSE_LANGUAGES.contains(&doc.scope()) {
+            return None;
+        }
+        let expansion = abbreviations.lookup(word)?;
+
+        let line = params.text_document_position.position.line;
+        let character = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: character - word.chars().count() as u32,
+            },
+            end: Position { line, character },
+        };
+        Some(CompletionItem {
+            label: expansion.clone(),
+            filter_text: Some(word.to_string()),
+            // ranked before other items
+            sort_text: Some("!".to_string()),
+            preselect: Some(true),
+            kind: Some(CompletionItemKind::TEXT),
+            detail: Some(format!("Abbreviation {word}")),
+            text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                replace: range,
+                insert: range,
+                new_text: expansion,
+            })),
+            ..Default::default()
+        })
+    }
+
+    fn synonyms(&self, word: &str, params: &CompletionParams) -> Vec<CompletionItem> {
+        let Some((_, thesaurus)) = &self.thesaurus else {
+            return Vec::new();
+        };
+
+        let line = params.text_document_position.position.line;
+        let start = params.text_document_position.position.character - word.chars().count() as u32;
+        let replace_end = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: replace_end,
+            },
+        };
+
+        let mut seen = HashSet::new();
+        thesaurus
+            .lookup(word)
+            .flat_map(|sense| {
+                let documentation = sense.join(", ");
+                sense
+                    .iter()
+                    .map(move |synonym| (synonym, documentation.clone()))
+            })
+            .filter(|(synonym, _)| !synonym.eq_ignore_ascii_case(word) && seen.insert(*synonym))
+            .map(|(synonym, documentation)| CompletionItem {
+                label: synonym.to_string(),
+                // keep synonyms visible on client side filtering by current word
+                filter_text: Some(word.to_string()),
+                kind: Some(CompletionItemKind::TEXT),
+                documentation: Some(Documentation::String(documentation)),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: synonym.to_string(),
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    /// camelCase/snake_case/kebab-case/... variants of identifier ending at cursor
+    fn case_styles(
+        &self,
+        before_cursor: &str,
+        params: &CompletionParams,
+        doc: &Document,
+    ) -> Vec<CompletionItem> {
+        let position = params.text_document_position.position;
+        let at_end = doc
+            .text
+            .try_line_to_char(position.line as usize)
+            .ok()
+            .and_then(|line_start| doc.text.get_char(line_start + position.character as usize))
+            .is_none_or(|ch| !case_style::char_is_identifier(ch));
+        if !at_end {
+            return Vec::new();
+        }
+
+        let identifier = before_cursor
+            .chars()
+            .rev()
+            .take_while(|ch| case_style::char_is_identifier(*ch))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .skip_while(|ch| *ch == '-')
+            .collect::<String>();
+        let range = Range {
+            start: Position {
+                line: position.line,
+                character: position.character - identifier.chars().count() as u32,
+            },
+            end: position,
+        };
+
+        case_style::variants(&identifier)
+            .into_iter()
+            .map(|(style, value)| CompletionItem {
+                label: value.clone(),
+                filter_text: Some(identifier.clone()),
+                kind: Some(CompletionItemKind::TEXT),
+                detail: Some(style.to_string()),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: value,
+                })),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Distinct words of document index persisted in warm cache, excluded ones are never indexed
+    fn indexed_words(&self, index: &word_index::WordIndex) -> Vec<String> {
+        index.words().map(String::from).collect()
+    }
+
+    /// Match text by regex pattern, compiled regexes are cached, None for invalid pattern
+    fn regex_is_match(&self, pattern: &str, text: &str) -> Option<bool> {
+        let mut cache = self.regexes.borrow_mut();
+        let regex = cache.entry(pattern.to_string()).or_insert_with(|| {
+            regex::Regex::new(pattern)
+                .map_err(|e| tracing::error!("Invalid regex '{pattern}': {e}"))
+                .ok()
+        });
+        regex.as_ref().map(|r| r.is_match(text))
+    }
+
+    /// Snippet `when` regex matches line text before typed word
+    fn snippet_condition(&self, snippet: &Snippet, line_prefix: &str) -> bool {
+        let Some(when) = &snippet.when else {
+            return true;
+        };
+        self.regex_is_match(when, line_prefix).unwrap_or(false)
+    }
+
+    /// Substitute user variables (`${AUTHOR}` for `author`) into snippet body
+    fn expand_variables<'a>(&self, body: &'a str) -> Cow<'a, str> {
+        if self.settings.variables.is_empty() || !body.contains("${") {
+            return Cow::Borrowed(body);
+        }
+        let mut body = body.to_string();
+        for (name, value) in &self.settings.variables {
+            // value is inserted as text, escape snippet syntax
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('$', "\\$")
+                .replace('}', "\\}");
+            body = body.replace(&format!("${{{}}}", name.to_uppercase()), &value);
+        }
+        Cow::Owned(body)
+    }
+
+    /// Whole file snippets for empty document (or document with typed word only)
+    fn file_templates(&self, prefix: Option<&str>, doc: &Document) -> Vec<CompletionItem> {
+        let prefix = prefix.unwrap_or_default();
+        if doc.text.len_chars() > 256 || doc.text.to_string().trim() != prefix {
+            return Vec::new();
+        }
+        let file_name = doc
+            .uri
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or_default()
+            .to_string();
+        self.snippets
+            .iter()
+            .filter(|s| s.file_template && s.prefix.starts_with(prefix))
+            .filter(|s| {
+                s.scope
+                    .as_ref()
+                    .is_none_or(|scope| scope.is_empty() || scope.iter().any(|s| s == doc.scope()))
+            })
+            .filter(|s| {
+                s.file_glob
+                    .as_ref()
+                    .is_none_or(|glob| glob_match(glob, &file_name))
+            })
+            .map(|s| {
+                let body = self.expand_variables(&s.body);
+                CompletionItem {
+                    label: s.prefix.to_owned(),
+                    kind: Some(CompletionItemKind::FILE),
+                    detail: Some(
+                        s.description
+                            .clone()
+                            .unwrap_or_else(|| "File template".to_string()),
+                    ),
+                    documentation: Some(self.code_documentation(&body, doc.scope())),
+                    insert_text: Some(self.snippet_text(&body)),
+                    insert_text_format: Some(self.snippet_format()),
+                    command: Some(Command {
+                        title: "Snippet accepted".to_string(),
+                        command: SNIPPET_ACCEPTED_COMMAND.to_string(),
+                        arguments: Some(vec![s.prefix.clone().into()]),
+                    }),
+                    ..Default::default()
+                }
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn snippets<'a>(
+        &'a self,
+        prefix: &'a str,
+        line_prefix: &'a str,
+        scope: &'a str,
+    ) -> impl Iterator<Item = CompletionItem> + 'a {
+        self.snippet_index
+            .lookup(prefix, scope)
+            .into_iter()
+            .map(|i| &self.snippets[i])
+            .filter(move |s| self.snippet_condition(s, line_prefix))
+            .map(move |s| (s, self.expand_variables(&s.body)))
+            .map(move |(s, body)| CompletionItem {
+                label: s.prefix.to_owned(),
+                kind: Some(CompletionItemKind::SNIPPET),
+                detail: s.description.clone(),
+                documentation: Some(self.code_documentation(&body, scope)),
+                insert_text: Some(self.snippet_text(&body)),
+                insert_text_format: Some(self.snippet_format()),
+                // first body line follows label
+                label_details: body
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .map(|line| CompletionItemLabelDetails {
+                        detail: Some(format!(" {line}")),
+                        description: None,
+                    }),
+                sort_text: self.snippets_usage.sort_text(&s.prefix),
+                // typed word is exactly snippet prefix, expand it predictably
+                preselect: (s.prefix == prefix).then_some(true),
+                // client reports accepted snippet back to count its usage
+                command: Some(Command {
+                    title: "Snippet accepted".to_string(),
+                    command: SNIPPET_ACCEPTED_COMMAND.to_string(),
+                    arguments: Some(vec![s.prefix.clone().into()]),
+                }),
+                ..Default::default()
+            })
+            .take(self.max_items())
+    }
+
+    fn unicode_input(
+        &self,
+        word_prefix: &str,
+        params: &CompletionParams,
+    ) -> impl Iterator<Item = CompletionItem> {
+        let Ok((chars, doc)) = self.get_prefix_as_chars(params, self.max_unicude_input_prefix)
+        else {
+            tracing::error!("Failed to get prefix as sequence of chars");
+            return Vec::new().into_iter();
+        };
+        let categories = self
+            .settings
+            .unicode_input_categories
+            .get(doc.scope())
+            .or_else(|| self.settings.unicode_input_categories.get("*"));
 
         let Some(chars) = chars else {
             return Vec::new().into_iter();
@@ -457,9 +2808,16 @@ impl BackendState {
                     if !prefix.starts_with(char_prefix) {
                         return None;
                     }
+                    if categories.is_some_and(|categories| {
+                        !categories
+                            .iter()
+                            .any(|c| c == unicode_input::category(body))
+                    }) {
+                        return None;
+                    }
                     let line = params.text_document_position.position.line;
-                    let start =
-                        params.text_document_position.position.character - char_prefix.len() as u32;
+                    let start = params.text_document_position.position.character
+                        - char_prefix.chars().count() as u32;
                     let replace_end = params.text_document_position.position.character;
                     let range = Range {
                         start: Position {
@@ -474,6 +2832,8 @@ impl BackendState {
                     Some(CompletionItem {
                         label: body.to_string(),
                         filter_text: Some(format!("{word_prefix}{prefix}")),
+                        // variants of the same prefix differ by key, like `thumbsup_dark`
+                        detail: Some(prefix.to_string()),
                         kind: Some(CompletionItemKind::TEXT),
                         text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
                             replace: range,
@@ -483,13 +2843,67 @@ impl BackendState {
                         ..Default::default()
                     })
                 })
-                .take(self.settings.max_completion_items - chars_snippets.len());
+                .take(self.max_items() - chars_snippets.len());
             chars_snippets.extend(items);
-            if chars_snippets.len() >= self.settings.max_completion_items {
+            if chars_snippets.len() >= self.max_items() {
                 break;
             }
         }
 
+        // typed word matched by aliases and description words of described entries
+        if word_prefix.chars().count() >= 2 && chars_snippets.len() < self.max_items() {
+            let mut matched = self
+                .unicode_input_info
+                .iter()
+                .filter(|(prefix, _)| {
+                    !chars_snippets
+                        .iter()
+                        .any(|i| i.detail.as_deref() == Some(prefix.as_str()))
+                })
+                .filter_map(|(prefix, info)| {
+                    let body = self.unicode_input.get(prefix)?;
+                    let rank = unicode_input::match_rank(word_prefix, prefix, Some(info))?;
+                    Some((rank, prefix, body, info))
+                })
+                .filter(|(_, _, body, _)| {
+                    categories.is_none_or(|categories| {
+                        categories
+                            .iter()
+                            .any(|c| c == unicode_input::category(body))
+                    })
+                })
+                .collect::<Vec<_>>();
+            matched.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+
+            let line = params.text_document_position.position.line;
+            let character = params.text_document_position.position.character;
+            let range = Range {
+                start: Position {
+                    line,
+                    character: character - word_prefix.chars().count() as u32,
+                },
+                end: Position { line, character },
+            };
+            let items = matched
+                .into_iter()
+                .map(|(_, prefix, body, info)| CompletionItem {
+                    label: body.to_string(),
+                    filter_text: Some(word_prefix.to_string()),
+                    detail: Some(prefix.to_string()),
+                    documentation: info.description.clone().map(Documentation::String),
+                    kind: Some(CompletionItemKind::TEXT),
+                    text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                        replace: range,
+                        insert: range,
+                        new_text: body.to_string(),
+                    })),
+                    ..Default::default()
+                })
+                .take(self.max_items() - chars_snippets.len())
+                .collect::<Vec<_>>();
+            chars_snippets.extend(items);
+        }
+
         chars_snippets.into_iter()
     }
 
@@ -526,10 +2940,10 @@ impl BackendState {
         {
             chars
         } else {
-            &chars[1..]
+            &chars[first_char.len_utf8()..]
         };
 
-        let chars_prefix_len = chars_prefix.len() as u32;
+        let chars_prefix_len = chars_prefix.chars().count() as u32;
 
         // expand tilde to home dir
         let (is_tilde_exapnded, chars_prefix) = if chars_prefix.starts_with("~/") {
@@ -571,9 +2985,7 @@ impl BackendState {
             .filter_map(|item| {
                 // convert to regular &str
                 let fname = item.file_name();
-                let Some(item_filename) = fname.to_str() else {
-                    return None;
-                };
+                let item_filename = fname.to_str()?;
                 let item_filename = item_filename.to_lowercase();
                 if !filename.is_empty() && !item_filename.starts_with(&filename) {
                     return None;
@@ -581,9 +2993,7 @@ impl BackendState {
 
                 // use fullpath
                 let path = item.path();
-                let Some(full_path) = path.to_str() else {
-                    return None;
-                };
+                let full_path = path.to_str()?;
 
                 // fold back to tilde
                 let full_path = if is_tilde_exapnded {
@@ -621,127 +3031,2195 @@ impl BackendState {
                     ..Default::default()
                 })
             })
-            .take(self.settings.max_completion_items)
+            .take(self.max_items())
             .collect::<Vec<_>>()
             .into_iter()
     }
 
-    pub async fn start(mut self) {
-        loop {
-            let Some(cmd) = self.rx.recv().await else {
-                continue;
-            };
+    /// `TODO`/`FIXME`/... tags typed after comment leader
+    fn comment_tags(
+        &self,
+        prefix: &str,
+        line_prefix: &str,
+        params: &CompletionParams,
+    ) -> Vec<CompletionItem> {
+        if !comment_tags::after_comment_leader(line_prefix) {
+            return Vec::new();
+        }
+        let position = params.text_document_position.position;
+        let range = Range {
+            start: Position {
+                line: position.line,
+                character: position.character - prefix.chars().count() as u32,
+            },
+            end: position,
+        };
+        let prefix = prefix.to_uppercase();
+        self.settings
+            .comment_tags
+            .iter()
+            .filter(|tag| tag.to_uppercase().starts_with(&prefix))
+            .filter_map(|tag| comment_tags::expand(tag, &self.settings.variables))
+            .map(|tag| CompletionItem {
+                label: tag.clone(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                detail: Some("Comment tag".to_string()),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: tag,
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
 
-            match cmd {
-                BackendRequest::NewDoc(params) => {
-                    self.docs.insert(
-                        params.text_document.uri.clone(),
-                        Document {
-                            uri: params.text_document.uri,
-                            text: Rope::from_str(&params.text_document.text),
-                            language_id: params.text_document.language_id,
-                        },
-                    );
-                }
-                BackendRequest::SaveDoc(params) => {
-                    if let Err(e) = self.save_doc(params) {
-                        tracing::error!("Error on save doc: {e}");
-                    }
-                }
-                BackendRequest::ChangeDoc(params) => {
-                    if let Err(e) = self.change_doc(params) {
-                        tracing::error!("Error on change doc: {e}");
-                    }
-                }
-                BackendRequest::ChangeConfiguration(params) => {
-                    if let Err(e) = self.change_configuration(params) {
-                        tracing::error!("Error on change configuration: {e}");
-                    }
+    /// Freshly generated UUIDs, ULIDs and random strings
+    fn generators(&self, prefix: &str, params: &CompletionParams) -> Vec<CompletionItem> {
+        let line = params.text_document_position.position.line;
+        let character = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: character - prefix.chars().count() as u32,
+            },
+            end: Position { line, character },
+        };
+        generators::generate(prefix)
+            .into_iter()
+            .map(|(name, value)| CompletionItem {
+                label: value.clone(),
+                filter_text: Some(name.clone()),
+                detail: Some(name),
+                kind: Some(CompletionItemKind::VALUE),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: value,
+                })),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn datetime(&self, prefix: &str, params: &CompletionParams) -> Vec<CompletionItem> {
+        // whole trigger word only, not any word starting like `d` or `t`
+        let Some(trigger) = DATETIME_TRIGGERS
+            .iter()
+            .find(|trigger| trigger.eq_ignore_ascii_case(prefix))
+        else {
+            return Vec::new();
+        };
+
+        let now = chrono::Local::now();
+
+        let line = params.text_document_position.position.line;
+        let start =
+            params.text_document_position.position.character - prefix.chars().count() as u32;
+        let replace_end = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: replace_end,
+            },
+        };
+
+        self.settings
+            .datetime_formats
+            .iter()
+            .filter_map(|format| {
+                // invalid user-defined format must not panic on render
+                let mut value = String::new();
+                if std::fmt::Write::write_fmt(&mut value, format_args!("{}", now.format(format)))
+                    .is_err()
+                {
+                    tracing::warn!("Invalid datetime format: {format}");
+                    return None;
                 }
-                BackendRequest::CompletionRequest((tx, params)) => {
-                    let now = std::time::Instant::now();
+                Some(CompletionItem {
+                    label: value.clone(),
+                    filter_text: Some(trigger.to_string()),
+                    detail: Some(format.to_string()),
+                    kind: Some(CompletionItemKind::CONSTANT),
+                    text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                        replace: range,
+                        insert: range,
+                        new_text: value,
+                    })),
+                    ..Default::default()
+                })
+            })
+            .take(self.max_items())
+            .collect()
+    }
 
-                    let Ok((prefix, doc)) = self.get_prefix(&params) else {
-                        if tx
-                            .send(Err(anyhow::anyhow!("Failed to get prefix")))
-                            .is_err()
-                        {
-                            tracing::error!("Error on send completion response");
-                        }
-                        continue;
-                    };
+    fn colors(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        // `#` and up to 8 hex digits
+        let Ok((Some(chars), doc)) = self.get_prefix_as_chars(params, 9) else {
+            return Vec::new();
+        };
 
-                    let results: Vec<CompletionItem> = Vec::new()
-                        .into_iter()
-                        .chain(
-                            if let Some(prefix) = &prefix {
-                                if self.settings.feature_snippets & self.settings.snippets_first {
-                                    Some(self.snippets(prefix, doc))
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            }
-                            .into_iter()
-                            .flatten(),
-                        )
-                        .chain(
-                            if let Some(prefix) = &prefix {
-                                if self.settings.feature_words {
-                                    Some(self.words(prefix, doc))
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            }
-                            .into_iter()
-                            .flatten(),
-                        )
-                        .chain(
-                            if let Some(prefix) = &prefix {
-                                if self.settings.feature_snippets & !self.settings.snippets_first {
-                                    Some(self.snippets(prefix, doc))
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            }
-                            .into_iter()
-                            .flatten(),
-                        )
-                        .chain(
-                            if self.settings.feature_unicode_input {
-                                Some(self.unicode_input(prefix.unwrap_or_default(), &params))
-                            } else {
-                                None
-                            }
-                            .into_iter()
-                            .flatten(),
-                        )
-                        .chain(
-                            if self.settings.feature_paths {
-                                Some(self.paths(prefix.unwrap_or_default(), &params))
-                            } else {
-                                None
-                            }
-                            .into_iter()
-                            .flatten(),
-                        )
-                        .collect();
+        if !colors::COLOR_LANGUAGES.contains(&doc.language_id.as_str()) {
+            return Vec::new();
+        }
 
-                    tracing::debug!(
-                        "completion request took {:.2}ms with {} result items",
-                        now.elapsed().as_millis(),
-                        results.len(),
-                    );
+        let Some(hash_idx) = chars.rfind('#') else {
+            return Vec::new();
+        };
+        let token = &chars[hash_idx + 1..];
+        if !token.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+            return Vec::new();
+        }
+
+        let line = params.text_document_position.position.line;
+        let start =
+            params.text_document_position.position.character - (token.chars().count() + 1) as u32;
+        let replace_end = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: replace_end,
+            },
+        };
+
+        let color_item = |label: &str, value: &str| CompletionItem {
+            label: label.to_string(),
+            filter_text: Some(label.trim_start_matches('#').to_string()),
+            kind: Some(CompletionItemKind::COLOR),
+            detail: Some(value.to_string()),
+            documentation: Some(Documentation::String(value.to_string())),
+            text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                replace: range,
+                insert: range,
+                new_text: value.to_string(),
+            })),
+            ..Default::default()
+        };
+
+        // previously used colors at first
+        let used = match self.used_colors(&format!("#{token}"), doc) {
+            Ok(words) => words,
+            Err(e) => {
+                tracing::error!("On complete by colors: {e}");
+                HashSet::new()
+            }
+        };
+        let mut used = used
+            .into_iter()
+            .filter(|word| colors::is_hex_color(word))
+            .collect::<Vec<_>>();
+        used.sort();
+
+        let token = token.to_lowercase();
+        used.iter()
+            .map(|hex| color_item(hex, hex))
+            .chain(
+                colors::NAMED_COLORS
+                    .iter()
+                    .filter(|(name, _)| name.starts_with(&token))
+                    .map(|(name, hex)| color_item(name, hex)),
+            )
+            .take(self.max_items())
+            .collect()
+    }
 
-                    let response =
-                        BackendResponse::CompletionResponse(CompletionResponse::Array(results));
+    fn git_refs(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let Ok((Some(chars), doc)) = self.get_prefix_as_chars(params, self.settings.max_path_chars)
+        else {
+            return Vec::new();
+        };
+
+        if !git::GIT_LANGUAGES.contains(&doc.language_id.as_str()) {
+            return Vec::new();
+        }
+
+        // skip surround chars like quotes
+        let offset = chars
+            .chars()
+            .rev()
+            .take_while(|ch| git::char_is_ref(*ch))
+            .count();
+        if offset == 0 {
+            return Vec::new();
+        }
+        let Some((start_idx, _)) = chars.char_indices().rev().nth(offset - 1) else {
+            return Vec::new();
+        };
+        let ref_prefix = &chars[start_idx..];
+
+        let Some(dir) = doc.dir() else {
+            return Vec::new();
+        };
+
+        let Some(refs) = self.git_lines(&dir, git::GitQuery::Refs) else {
+            return Vec::new();
+        };
+
+        let line = params.text_document_position.position.line;
+        let start = params.text_document_position.position.character - offset as u32;
+        let replace_end = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: replace_end,
+            },
+        };
+
+        refs.iter()
+            .filter_map(|refname| git::parse_ref(refname))
+            .filter(|(name, _)| name.starts_with(ref_prefix) && *name != ref_prefix)
+            .map(|(name, kind)| CompletionItem {
+                label: name.to_string(),
+                kind: Some(CompletionItemKind::REFERENCE),
+                detail: Some(kind.to_string()),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: name.to_string(),
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn git_commit(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        // subject line only
+        if params.text_document_position.position.line != 0 {
+            return Vec::new();
+        }
+
+        let Ok((chars, doc)) = self.get_prefix_as_chars(params, self.settings.max_path_chars)
+        else {
+            return Vec::new();
+        };
+
+        if !git::GIT_LANGUAGES.contains(&doc.language_id.as_str()) {
+            return Vec::new();
+        }
+
+        let line_prefix = chars.unwrap_or_default();
+        let character = params.text_document_position.position.character;
+        if line_prefix.chars().count() as u32 != character || line_prefix.contains(':') {
+            return Vec::new();
+        }
+
+        // types are offered while scopes are loaded
+        let scopes = doc
+            .dir()
+            .and_then(|dir| self.git_lines(&dir, git::GitQuery::Subjects))
+            .map(|subjects| git::conventional_scopes(&subjects))
+            .unwrap_or_default();
+
+        let range = Range {
+            start: Position {
+                line: 0,
+                character: 0,
+            },
+            end: Position { line: 0, character },
+        };
+
+        git::CONVENTIONAL_TYPES
+            .iter()
+            .map(|(commit_type, description)| (format!("{commit_type}: "), description))
+            .chain(scopes.iter().flat_map(|scope| {
+                git::CONVENTIONAL_TYPES
+                    .iter()
+                    .map(move |(commit_type, description)| {
+                        (format!("{commit_type}({scope}): "), description)
+                    })
+            }))
+            .filter(|(label, _)| label.starts_with(line_prefix))
+            .map(|(label, description)| CompletionItem {
+                label: label.trim_end().to_string(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                detail: Some(description.to_string()),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: label,
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn git_trailers(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let line = params.text_document_position.position.line;
+        if line == 0 {
+            return Vec::new();
+        }
+
+        let Ok((line_prefix, doc)) = self.get_line_prefix(params) else {
+            return Vec::new();
+        };
+
+        if !git::GIT_LANGUAGES.contains(&doc.language_id.as_str()) {
+            return Vec::new();
+        }
+
+        let character = params.text_document_position.position.character;
+
+        let Some((key, query)) = line_prefix.split_once(':') else {
+            // trailer key itself
+            if line_prefix.is_empty()
+                || !line_prefix.chars().all(|ch| char_is_word(ch) || ch == '-')
+            {
+                return Vec::new();
+            }
+            let range = Range {
+                start: Position { line, character: 0 },
+                end: Position { line, character },
+            };
+            return git::AUTHOR_TRAILERS
+                .iter()
+                .filter(|trailer| {
+                    trailer
+                        .to_lowercase()
+                        .starts_with(&line_prefix.to_lowercase())
+                })
+                .map(|trailer| CompletionItem {
+                    label: format!("{trailer}:"),
+                    kind: Some(CompletionItemKind::KEYWORD),
+                    text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                        replace: range,
+                        insert: range,
+                        new_text: format!("{trailer}: "),
+                    })),
+                    ..Default::default()
+                })
+                .collect();
+        };
+
+        if !git::AUTHOR_TRAILERS
+            .iter()
+            .any(|trailer| trailer.eq_ignore_ascii_case(key.trim()))
+        {
+            return Vec::new();
+        }
+
+        let Some(dir) = doc.dir() else {
+            return Vec::new();
+        };
+
+        let Some(lines) = self.git_lines(&dir, git::GitQuery::Authors) else {
+            return Vec::new();
+        };
+
+        let query = query.trim_start().to_lowercase();
+        let start = key.chars().count() as u32 + 1;
+        let range = Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position { line, character },
+        };
+
+        git::authors(&lines)
+            .into_iter()
+            .filter(|author| author.to_lowercase().contains(&query))
+            .map(|author| CompletionItem {
+                label: author.to_string(),
+                filter_text: Some(author.to_string()),
+                kind: Some(CompletionItemKind::TEXT),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: format!(" {author}"),
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn get_trigger_query(
+        &self,
+        params: &CompletionParams,
+        trigger: &str,
+        languages: &[String],
+    ) -> Option<(&str, Range, &Document)> {
+        let Ok((Some(chars), doc)) = self.get_prefix_as_chars(params, self.settings.max_path_chars)
+        else {
+            return None;
+        };
+
+        if !languages.is_empty() && !languages.iter().any(|l| l == doc.scope()) {
+            return None;
+        }
+
+        let trigger_idx = chars.rfind(trigger)?;
+        let query = &chars[trigger_idx + trigger.len()..];
+        if !query.chars().all(char_is_word) {
+            return None;
+        }
+
+        let line = params.text_document_position.position.line;
+        let start = params.text_document_position.position.character
+            - chars[trigger_idx..].chars().count() as u32;
+        let replace_end = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: replace_end,
+            },
+        };
+
+        Some((query, range, doc))
+    }
+
+    fn issues(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let Some(source) = &self.settings.issue_source else {
+            return Vec::new();
+        };
+
+        let Some((query, range, doc)) =
+            self.get_trigger_query(params, &source.trigger, &source.languages)
+        else {
+            return Vec::new();
+        };
+        let Some(dir) = doc.dir() else {
+            return Vec::new();
+        };
+        if !self.command_allowed(doc, &source.command) {
+            return Vec::new();
+        }
+
+        let issues_command = source.command.clone();
+        let Some(output) = self.command_output(
+            &dir,
+            &source.command,
+            command::COMMAND_CACHE_TTL,
+            move |dir| command::run(dir, &issues_command, command::COMMAND_TIMEOUT),
+        ) else {
+            return Vec::new();
+        };
+
+        let query = query.to_lowercase();
+        issues::parse_issues(&output)
+            .into_iter()
+            .filter(|issue| {
+                issue.id.to_lowercase().starts_with(&query)
+                    || issue.title.to_lowercase().contains(&query)
+            })
+            .map(|issue| CompletionItem {
+                label: format!("{}{}", source.trigger, issue.id),
+                filter_text: Some(format!("{} {}", issue.id, issue.title)),
+                kind: Some(CompletionItemKind::REFERENCE),
+                detail: Some(issue.title),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: format!("{}{}", source.trigger, issue.id),
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    /// Handles from people file and repository CODEOWNERS
+    fn mentions(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let source = &self.settings.mention_source;
+        let Some((query, range, doc)) =
+            self.get_trigger_query(params, &source.trigger, &source.languages)
+        else {
+            return Vec::new();
+        };
+
+        let people_path = source.people_path.as_ref().map(|path| {
+            std::path::PathBuf::from(if path.starts_with("~/") {
+                path.replacen('~', &self.home_dir, 1)
+            } else {
+                path.to_string()
+            })
+        });
+        let dir = doc.dir().filter(|_| source.codeowners);
+        let Some(people) = self.mention_people((people_path, dir)) else {
+            return Vec::new();
+        };
+
+        let query = query.to_lowercase();
+        people
+            .into_iter()
+            .filter(|person| {
+                person.handle.to_lowercase().starts_with(&query)
+                    || person.name.to_lowercase().contains(&query)
+            })
+            .map(|person| CompletionItem {
+                label: format!("{}{}", source.trigger, person.handle),
+                filter_text: Some(format!("{} {}", person.handle, person.name)),
+                kind: Some(CompletionItemKind::REFERENCE),
+                detail: (!person.name.is_empty()).then_some(person.name),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: format!("{}{}", source.trigger, person.handle),
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn gitmoji(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let Some((query, range, doc)) = self.get_trigger_query(params, ":", &[]) else {
+            return Vec::new();
+        };
+
+        if !git::GIT_LANGUAGES.contains(&doc.language_id.as_str()) {
+            return Vec::new();
+        }
+
+        let query = format!(":{}", query.to_lowercase());
+        gitmoji::GITMOJIS
+            .iter()
+            .filter(|(_, code, description)| {
+                code.starts_with(&query) || description.to_lowercase().contains(&query[1..])
+            })
+            .map(|(emoji, code, description)| {
+                let new_text = match self.settings.gitmoji_format {
+                    gitmoji::GitmojiFormat::Emoji => emoji,
+                    gitmoji::GitmojiFormat::Shortcode => code,
+                };
+                CompletionItem {
+                    label: format!("{emoji} {code}"),
+                    filter_text: Some(code.to_string()),
+                    kind: Some(CompletionItemKind::TEXT),
+                    detail: Some(description.to_string()),
+                    documentation: Some(Documentation::String(description.to_string())),
+                    text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                        replace: range,
+                        insert: range,
+                        new_text: new_text.to_string(),
+                    })),
+                    ..Default::default()
+                }
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn markdown_links(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let Ok((line_prefix, doc)) = self.get_line_prefix(params) else {
+            return Vec::new();
+        };
+
+        if doc.language_id != "markdown" {
+            return Vec::new();
+        }
+
+        let line = params.text_document_position.position.line;
+        let character = params.text_document_position.position.character;
+        let range = |query: &str| Range {
+            start: Position {
+                line,
+                character: character - query.chars().count() as u32,
+            },
+            end: Position { line, character },
+        };
+
+        // url of definition block `[label]: http...`
+        if let Some(definition) = markdown::parse_link_reference(&line_prefix) {
+            if line_prefix.ends_with(char::is_whitespace) && !definition.url.is_empty() {
+                return Vec::new();
+            }
+            let query = definition.url;
+            let range = range(&query);
+            return markdown::urls(&doc.text)
+                .into_iter()
+                .filter(|url| url.starts_with(&query) && *url != query)
+                .map(|url| CompletionItem {
+                    label: url.to_string(),
+                    kind: Some(CompletionItemKind::REFERENCE),
+                    text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                        replace: range,
+                        insert: range,
+                        new_text: url,
+                    })),
+                    ..Default::default()
+                })
+                .take(self.max_items())
+                .collect();
+        }
+
+        // label of reference link `[text][label`
+        let Some((_, query)) = line_prefix.rsplit_once("][") else {
+            return Vec::new();
+        };
+        if query.contains(']') {
+            return Vec::new();
+        }
+        let range = range(query);
+        let lowercase_query = query.to_lowercase();
+
+        markdown::link_references(&doc.text)
+            .into_iter()
+            .filter(|r| r.label.to_lowercase().starts_with(&lowercase_query))
+            .map(|r| CompletionItem {
+                label: r.label.to_string(),
+                kind: Some(CompletionItemKind::REFERENCE),
+                detail: Some(r.url),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: r.label,
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn frontmatter_tags(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let Ok((line_prefix, doc)) = self.get_line_prefix(params) else {
+            return Vec::new();
+        };
+
+        if doc.language_id != "markdown" {
+            return Vec::new();
+        }
+
+        let line = params.text_document_position.position.line;
+        let Some(query) = markdown::frontmatter_tag_query(&doc.text, line as usize, &line_prefix)
+        else {
+            return Vec::new();
+        };
+
+        let root = self.isolated_root(doc);
+        let roots = match root {
+            Some(root) => vec![root.clone()],
+            None => self.workspace_roots.clone(),
+        };
+        let is_expired = self
+            .tags
+            .borrow()
+            .as_ref()
+            .map(|(created, cached_roots, _)| {
+                created.elapsed() > TAGS_CACHE_TTL || *cached_roots != roots
+            })
+            .unwrap_or(true);
+        // notes are walked in background, outdated tags are offered meanwhile
+        if is_expired && !self.tags_loading.replace(true) {
+            let mut dirs = roots.clone();
+            if let Some(notes_path) = &self.settings.notes_path {
+                dirs.push(std::path::PathBuf::from(if notes_path.starts_with("~/") {
+                    notes_path.replacen('~', &self.home_dir, 1)
+                } else {
+                    notes_path.to_string()
+                }));
+            }
+            let roots = roots.clone();
+            self.in_background(move || {
+                Some(BackendRequest::LoadedTags((
+                    roots,
+                    markdown::aggregate_tags(&dirs),
+                )))
+            });
+        }
+
+        let mut tags = self
+            .tags
+            .borrow()
+            .as_ref()
+            .filter(|(_, cached_roots, _)| *cached_roots == roots)
+            .map(|(_, _, tags)| tags.clone())
+            .unwrap_or_default();
+        // include unsaved notes
+        for other in self.docs.values().filter(|d| {
+            d.uri != doc.uri
+                && d.language_id == "markdown"
+                && root.is_none_or(|root| uri_to_path(&d.uri).is_some_and(|p| p.starts_with(root)))
+        }) {
+            for tag in &other.tags {
+                *tags.entry(tag.to_string()).or_default() += 1;
+            }
+        }
+        let mut tags = tags.into_iter().collect::<Vec<_>>();
+        tags.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let character = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: character - query.chars().count() as u32,
+            },
+            end: Position { line, character },
+        };
+
+        let lowercase_query = query.to_lowercase();
+        tags.into_iter()
+            .filter(|(tag, _)| tag.to_lowercase().starts_with(&lowercase_query) && tag != query)
+            .map(|(tag, count)| CompletionItem {
+                label: tag.to_string(),
+                kind: Some(CompletionItemKind::VALUE),
+                detail: Some(format!("{count} notes")),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: tag.to_string(),
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn labels(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let Ok((line_prefix, doc)) = self.get_line_prefix(params) else {
+            return Vec::new();
+        };
+
+        let (query, languages): (_, &[&str]) =
+            if labels::LATEX_LANGUAGES.contains(&doc.language_id.as_str()) {
+                (
+                    labels::latex_label_query(&line_prefix),
+                    &labels::LATEX_LANGUAGES,
+                )
+            } else if labels::TYPST_LANGUAGES.contains(&doc.language_id.as_str()) {
+                (
+                    labels::typst_label_query(&line_prefix),
+                    &labels::TYPST_LANGUAGES,
+                )
+            } else {
+                return Vec::new();
+            };
+        let Some(query) = query else {
+            return Vec::new();
+        };
+
+        let line = params.text_document_position.position.line;
+        let character = params.text_document_position.position.character;
+        let range = Range {
+            start: Position {
+                line,
+                character: character - query.chars().count() as u32,
+            },
+            end: Position { line, character },
+        };
+
+        // current document at first
+        let mut docs = vec![doc];
+        docs.extend(
+            self.docs
+                .values()
+                .filter(|d| d.uri != doc.uri && languages.contains(&d.language_id.as_str())),
+        );
+
+        let mut seen = HashSet::new();
+        docs.into_iter()
+            .flat_map(|d| {
+                let file_name = uri_to_path(&d.uri)
+                    .and_then(|p| p.file_name().map(|f| f.to_string_lossy().to_string()))
+                    .unwrap_or_default();
+                d.labels
+                    .iter()
+                    .map(move |label| (label.to_string(), file_name.clone()))
+            })
+            .filter(|(label, _)| label.starts_with(query) && seen.insert(label.to_string()))
+            .map(|(label, file_name)| CompletionItem {
+                label: label.to_string(),
+                kind: Some(CompletionItemKind::REFERENCE),
+                detail: Some(file_name),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: label,
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    fn bibtex(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let Ok((line_prefix, doc)) = self.get_line_prefix(params) else {
+            return Vec::new();
+        };
+
+        if !bibtex::BIBTEX_LANGUAGES.contains(&doc.language_id.as_str()) {
+            return Vec::new();
+        }
+
+        let line = params.text_document_position.position.line;
+        let Some(context) = bibtex::context(&doc.text, line as usize, &line_prefix) else {
+            return Vec::new();
+        };
+
+        let character = params.text_document_position.position.character;
+        let item = |query: &str, label: &str, new_text: String, detail: Option<&str>| {
+            let range = Range {
+                start: Position {
+                    line,
+                    character: character - query.chars().count() as u32,
+                },
+                end: Position { line, character },
+            };
+            CompletionItem {
+                label: label.to_string(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                detail: detail.map(String::from),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text,
+                })),
+                ..Default::default()
+            }
+        };
+
+        match context {
+            bibtex::BibContext::EntryType(query) => bibtex::ENTRY_TYPES
+                .iter()
+                .filter(|(entry_type, _)| entry_type.starts_with(&query.to_lowercase()))
+                .map(|(entry_type, description)| {
+                    item(query, entry_type, entry_type.to_string(), Some(description))
+                })
+                .take(self.max_items())
+                .collect(),
+            bibtex::BibContext::Field(query) => bibtex::FIELDS
+                .iter()
+                .filter(|field| field.starts_with(&query.to_lowercase()))
+                .map(|field| item(query, field, format!("{field} = "), None))
+                .take(self.max_items())
+                .collect(),
+            bibtex::BibContext::Month(query) => bibtex::MONTHS
+                .iter()
+                .filter(|(month, name)| {
+                    month.starts_with(&query.to_lowercase())
+                        || name.to_lowercase().starts_with(&query.to_lowercase())
+                })
+                .map(|(month, name)| item(query, month, month.to_string(), Some(name)))
+                .collect(),
+        }
+    }
+
+    fn command_sources(&self, params: &CompletionParams) -> Vec<CompletionItem> {
+        let mut items = Vec::new();
+        for source in &self.settings.command_sources {
+            let Some((query, range, doc)) =
+                self.get_trigger_query(params, &source.trigger, &source.languages)
+            else {
+                continue;
+            };
+            let Some(dir) = doc.dir() else {
+                continue;
+            };
+            if !self.command_allowed(doc, &source.command) {
+                continue;
+            }
+
+            let (source_command, timeout) = (source.command.clone(), source.timeout());
+            let Some(output) =
+                self.command_output(&dir, &source.command, source.ttl(), move |dir| {
+                    command::run(dir, &source_command, timeout)
+                })
+            else {
+                continue;
+            };
+
+            let query = query.to_lowercase();
+            items.extend(
+                output
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && line.to_lowercase().contains(&query))
+                    .map(|line| CompletionItem {
+                        label: line.to_string(),
+                        kind: Some(CompletionItemKind::TEXT),
+                        detail: Some(source.command.to_string()),
+                        text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                            replace: range,
+                            insert: range,
+                            new_text: line.to_string(),
+                        })),
+                        ..Default::default()
+                    })
+                    .take(self.max_items()),
+            );
+        }
+        items
+    }
+
+    /// Shell command flags parsed from its `--help` output
+    fn cli_flags(
+        &self,
+        before_cursor: &str,
+        params: &CompletionParams,
+        doc: &Document,
+    ) -> Vec<CompletionItem> {
+        if !cli_flags::SHELL_LANGUAGES.contains(&doc.scope()) {
+            return Vec::new();
+        }
+        let Some((command, flag)) = cli_flags::command_and_flag(before_cursor) else {
+            return Vec::new();
+        };
+        if !cli_flags::is_allowed(command, &self.settings.cli_flags_commands) {
+            return Vec::new();
+        }
+
+        let help_command = format!("{command} --help");
+        if !self.command_allowed(doc, &help_command) {
+            return Vec::new();
+        }
+        let dir = doc.dir().unwrap_or_else(std::env::temp_dir);
+        let program = command.to_string();
+        let Some(help) =
+            self.command_output(&dir, &help_command, cli_flags::HELP_CACHE_TTL, move |dir| {
+                command::run_program(dir, &program, &["--help"], true, command::COMMAND_TIMEOUT)
+            })
+        else {
+            return Vec::new();
+        };
+
+        let position = params.text_document_position.position;
+        let range = Range {
+            start: Position {
+                line: position.line,
+                character: position.character - flag.chars().count() as u32,
+            },
+            end: position,
+        };
+        cli_flags::parse_flags(&help)
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(flag))
+            .map(|(name, description)| CompletionItem {
+                label: name.clone(),
+                kind: Some(CompletionItemKind::PROPERTY),
+                detail: (!description.is_empty()).then_some(description),
+                text_edit: Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    replace: range,
+                    insert: range,
+                    new_text: name,
+                })),
+                ..Default::default()
+            })
+            .take(self.max_items())
+            .collect()
+    }
+
+    /// Collect source items, streamed as partial result when client asked for it
+    fn source<I: IntoIterator<Item = CompletionItem>>(
+        &self,
+        before_cursor: &str,
+        source: &'static str,
+        f: impl FnOnce() -> I,
+    ) -> Vec<CompletionItem> {
+        let items = self.source_items(before_cursor, source, f);
+        let partial_results = self.partial_results.borrow();
+        let Some((tx, uri)) = partial_results.as_ref().filter(|_| !items.is_empty()) else {
+            return items;
+        };
+        let mut partial = items.clone();
+        if let Some(doc) = self.docs.get(uri) {
+            for item in &mut partial {
+                position::encode_item(item, &doc.text, &self.position_encoding);
+            }
+        }
+        match tx.send(partial) {
+            Ok(()) => Vec::new(),
+            // client is gone, keep items for response
+            Err(_) => items,
+        }
+    }
+
+    /// Collect source items if its line filter (when set) matches line text before cursor
+    fn source_items<I: IntoIterator<Item = CompletionItem>>(
+        &self,
+        before_cursor: &str,
+        source: &'static str,
+        f: impl FnOnce() -> I,
+    ) -> Vec<CompletionItem> {
+        if self.in_code_block.get() && fences::PROSE_SOURCES.contains(&source) {
+            return Vec::new();
+        }
+        if let Some(pattern) = self.settings.source_line_filters.get(source) {
+            // source is kept active on invalid filter
+            if self.regex_is_match(pattern, before_cursor) == Some(false) {
+                return Vec::new();
+            }
+        }
+        let Some(target) = self.settings.adaptive_latency_ms else {
+            let mut items = self.timed(source, f);
+            self.rank_accepted(&mut items);
+            self.set_commit_characters(source, &mut items);
+            set_source_label(source, &mut items);
+            return items;
+        };
+
+        let max = self.settings.max_completion_items;
+        let limit = self
+            .source_limits
+            .borrow()
+            .get(source)
+            .map_or(max, |limit| (*limit).min(max));
+        self.source_limit.set(Some(limit));
+        let now = std::time::Instant::now();
+        let mut items = self.timed(source, f);
+        let elapsed = now.elapsed();
+        self.source_limit.set(None);
+        items.truncate(limit);
+
+        let tuned = stats::tune_limit(
+            limit,
+            max,
+            elapsed,
+            std::time::Duration::from_millis(target),
+        );
+        if tuned != limit {
+            tracing::debug!(
+                source,
+                ?elapsed,
+                "Tune source items limit {limit} -> {tuned}"
+            );
+        }
+        self.source_limits.borrow_mut().insert(source, tuned);
+
+        self.rank_accepted(&mut items);
+        self.set_commit_characters(source, &mut items);
+        set_source_label(source, &mut items);
+        items
+    }
+
+    /// Commit characters of source items, only folders of paths are continued by them
+    fn set_commit_characters(&self, source: &str, items: &mut [CompletionItem]) {
+        let Some(chars) = self.settings.commit_characters.get(source) else {
+            return;
+        };
+        for item in items
+            .iter_mut()
+            .filter(|item| source != "paths" || item.kind == Some(CompletionItemKind::FOLDER))
+        {
+            item.commit_characters = Some(chars.clone());
+        }
+    }
+
+    /// Items limit of current source, tuned down for slow ones
+    fn max_items(&self) -> usize {
+        self.source_limit
+            .get()
+            .unwrap_or(self.settings.max_completion_items)
+    }
+
+    /// Rank previously accepted items first, attach command reporting acceptance
+    fn rank_accepted(&self, items: &mut [CompletionItem]) {
+        let scope = self.acceptance_scope.borrow();
+        let Some((workspace, language)) = scope.as_ref() else {
+            return;
+        };
+        for item in items {
+            if let Some(sort_text) = self.acceptance.sort_text(workspace, language, &item.label) {
+                item.sort_text = Some(sort_text);
+            }
+            // snippets report acceptance on their own
+            if item.command.is_none() {
+                item.command = Some(Command {
+                    title: "Completion accepted".to_string(),
+                    command: COMPLETION_ACCEPTED_COMMAND.to_string(),
+                    arguments: Some(vec![
+                        workspace.clone().into(),
+                        language.clone().into(),
+                        item.label.clone().into(),
+                    ]),
+                });
+            }
+        }
+    }
+
+    /// Command could run for document: its program is allowed and workspace is trusted,
+    /// user is asked on first command of workspace
+    fn command_allowed(&self, doc: &Document, command: &str) -> bool {
+        if !command::is_allowed(command, self.settings.command_allowlist.as_deref()) {
+            tracing::debug!("Command '{command}' is not in allowlist");
+            return false;
+        }
+        let Some(trust_requests) = self
+            .trust_requests
+            .as_ref()
+            .filter(|_| self.settings.require_workspace_trust)
+        else {
+            return true;
+        };
+        let workspace = self.workspace_of(doc);
+        if let Some(trusted) = self.workspace_trust.borrow().get(&workspace) {
+            return trusted.unwrap_or_default();
+        }
+        self.workspace_trust
+            .borrow_mut()
+            .insert(workspace.clone(), None);
+        if trust_requests
+            .send((workspace, command.to_string()))
+            .is_err()
+        {
+            tracing::error!("Error on send trust request");
+        }
+        false
+    }
+
+    /// Workspace folder of document when searches are constrained to it (several folders opened)
+    fn isolated_root(&self, doc: &Document) -> Option<&std::path::PathBuf> {
+        if !self.settings.isolate_workspace_folders || self.workspace_roots.len() < 2 {
+            return None;
+        }
+        let path = uri_to_path(&doc.uri)?;
+        self.workspace_roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+    }
+
+    /// Workspace of document, the nearest workspace root or document dir
+    fn workspace_of(&self, doc: &Document) -> String {
+        let path = uri_to_path(&doc.uri);
+        self.workspace_roots
+            .iter()
+            .filter(|root| path.as_ref().is_some_and(|p| p.starts_with(root)))
+            .max_by_key(|root| root.as_os_str().len())
+            .cloned()
+            .or_else(|| doc.dir())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Collect source items, recording its latency
+    fn timed<I: IntoIterator<Item = CompletionItem>>(
+        &self,
+        source: &'static str,
+        f: impl FnOnce() -> I,
+    ) -> Vec<CompletionItem> {
+        let _span = tracing::debug_span!("source", source).entered();
+        let now = std::time::Instant::now();
+        let items = f().into_iter().collect::<Vec<_>>();
+        let elapsed = now.elapsed();
+        tracing::trace!(
+            source,
+            elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+            items = items.len()
+        );
+        self.stats.borrow_mut().record(source, elapsed, items.len());
+        items
+    }
+
+    /// Problems of current settings, like missing configured paths
+    pub fn self_check(&self) -> Vec<String> {
+        let expand = |path: &str| {
+            std::path::PathBuf::from(if path.starts_with("~/") {
+                path.replacen('~', &self.home_dir, 1)
+            } else {
+                path.to_string()
+            })
+        };
+        let mut problems = Vec::new();
+        if let (true, Some(path)) = (
+            self.settings.feature_thesaurus,
+            &self.settings.thesaurus_path,
+        ) {
+            if !expand(path).is_file() {
+                problems.push(format!("Thesaurus file '{path}' not found"));
+            }
+        }
+        if let (true, Some(path)) = (
+            self.settings.feature_abbreviations,
+            &self.settings.abbreviations_path,
+        ) {
+            if !expand(path).is_file() {
+                problems.push(format!("Abbreviations file '{path}' not found"));
+            }
+        }
+        if let (true, Some(path)) = (
+            self.settings.feature_mentions,
+            &self.settings.mention_source.people_path,
+        ) {
+            if !expand(path).is_file() {
+                problems.push(format!("People file '{path}' not found"));
+            }
+        }
+        if let (true, Some(path)) = (
+            self.settings.feature_frontmatter_tags,
+            &self.settings.notes_path,
+        ) {
+            if !expand(path).is_dir() {
+                problems.push(format!("Notes dir '{path}' not found"));
+            }
+        }
+        for root in &self.workspace_roots {
+            let path = root.join(FOLDER_SETTINGS_FILENAME);
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Err(e) = toml::from_str::<PartialBackendSettings>(&content) {
+                problems.push(format!("Invalid folder settings {path:?}: {}", e.message()));
+            }
+        }
+        for (source, pattern) in &self.settings.source_line_filters {
+            if !SOURCES.contains(&source.as_str()) {
+                problems.push(format!("Unknown source '{source}' of line filter"));
+            }
+            if let Err(e) = regex::Regex::new(pattern) {
+                problems.push(format!("Invalid line filter of source '{source}': {e}"));
+            }
+        }
+        for source in self.settings.commit_characters.keys() {
+            if !SOURCES.contains(&source.as_str()) {
+                problems.push(format!("Unknown source '{source}' of commit characters"));
+            }
+        }
+        for pattern in &self.settings.words_exclude_patterns {
+            if let Err(e) = regex::Regex::new(pattern) {
+                problems.push(format!("Invalid words exclude pattern: {e}"));
+            }
+        }
+        for glob in &self.settings.index_exclude {
+            if let Err(e) = workspace_index::check_exclude(glob) {
+                problems.push(format!("Invalid index exclude glob '{glob}': {e}"));
+            }
+        }
+        for category in self.settings.unicode_input_categories.values().flatten() {
+            if !unicode_input::CATEGORIES.contains(&category.as_str()) {
+                problems.push(format!("Unknown unicode input category '{category}'"));
+            }
+        }
+        problems
+    }
+
+    /// Per-source completion latency stats
+    pub fn stats(&self) -> std::cell::Ref<'_, stats::CompletionStats> {
+        self.stats.borrow()
+    }
+
+    pub fn completion_items(&self, params: &CompletionParams) -> Result<Vec<CompletionItem>> {
+        let Ok((prefix, doc)) = self.get_prefix(params) else {
+            anyhow::bail!("Failed to get prefix")
+        };
+        // line text before cursor, for sources line filters
+        let before_cursor = self
+            .get_line_prefix(params)
+            .map(|(line, _)| line)
+            .unwrap_or_default();
+        self.acceptance_scope.replace(
+            self.settings
+                .acceptance_stats
+                .then(|| (self.workspace_of(doc), doc.scope().to_string())),
+        );
+        let code_block = doc.code_block(params.text_document_position.position.line);
+        self.in_code_block.set(code_block.is_some());
+        // snippets of fenced code block language
+        let scope = code_block
+            .filter(|language| !language.is_empty())
+            .unwrap_or(doc.scope());
+        // line text before typed word, for snippets conditions
+        let line_prefix =
+            &before_cursor[..before_cursor.len() - prefix.map(str::len).unwrap_or_default()];
+
+        let file_templates = if self.settings.feature_snippets {
+            self.source(&before_cursor, "file_templates", || {
+                self.file_templates(prefix, doc)
+            })
+        } else {
+            Vec::new()
+        };
+
+        if let (true, true, Some(prefix)) = (
+            self.settings.feature_snippets,
+            self.settings.snippets_exclusive_on_exact,
+            &prefix,
+        ) {
+            // exact snippet is a whole response, never streamed
+            let exact = self.source_items(&before_cursor, "snippets", || {
+                self.snippets(prefix, line_prefix, scope)
+                    .filter(|item| item.preselect == Some(true))
+            });
+            if !exact.is_empty() {
+                return Ok(exact);
+            }
+        }
+
+        Ok(file_templates
+            .into_iter()
+            .chain(
+                if let Some(prefix) = &prefix {
+                    if self.settings.feature_abbreviations {
+                        Some(self.source(&before_cursor, "abbreviations", || {
+                            self.abbreviations(prefix, params, doc)
+                        }))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if let Some(prefix) = &prefix {
+                    if self.settings.feature_snippets & self.settings.snippets_first {
+                        Some(self.source(&before_cursor, "snippets", || {
+                            self.snippets(prefix, line_prefix, scope)
+                        }))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if let Some(prefix) = &prefix {
+                    if self.settings.feature_words {
+                        Some(self.source(&before_cursor, "words", || {
+                            self.words(prefix, doc).chain(self.word_list(prefix, doc))
+                        }))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if let (true, Some(prefix)) = (self.settings.feature_tmux, &prefix) {
+                    self.capture_tmux();
+                    Some(self.source(&before_cursor, "tmux", || self.tmux_words(prefix)))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if let Some(prefix) = &prefix {
+                    if self.settings.feature_snippets & !self.settings.snippets_first {
+                        Some(self.source(&before_cursor, "snippets", || {
+                            self.snippets(prefix, line_prefix, scope)
+                        }))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if let Some(prefix) = &prefix {
+                    if self.settings.feature_thesaurus {
+                        Some(
+                            self.source(&before_cursor, "synonyms", || {
+                                self.synonyms(prefix, params)
+                            }),
+                        )
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(if self.settings.feature_case_styles {
+                self.source(&before_cursor, "case_styles", || {
+                    self.case_styles(&before_cursor, params, doc)
+                })
+            } else {
+                Vec::new()
+            })
+            .chain(
+                if let Some(prefix) = &prefix {
+                    if self.settings.feature_datetime {
+                        Some(
+                            self.source(&before_cursor, "datetime", || {
+                                self.datetime(prefix, params)
+                            }),
+                        )
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if let Some(prefix) = &prefix {
+                    if self.settings.feature_comment_tags {
+                        Some(self.source(&before_cursor, "comment_tags", || {
+                            self.comment_tags(prefix, line_prefix, params)
+                        }))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if let Some(prefix) = &prefix {
+                    if self.settings.feature_generators {
+                        Some(self.source(&before_cursor, "generators", || {
+                            self.generators(prefix, params)
+                        }))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_colors {
+                    Some(self.source(&before_cursor, "colors", || self.colors(params)))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_git_refs {
+                    Some(self.source(&before_cursor, "git_refs", || self.git_refs(params)))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_git_commit {
+                    Some(self.source(&before_cursor, "git_commit", || self.git_commit(params)))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_git_commit {
+                    Some(self.source(&before_cursor, "git_trailers", || self.git_trailers(params)))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_gitmoji {
+                    Some(self.source(&before_cursor, "gitmoji", || self.gitmoji(params)))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_markdown_links {
+                    Some(self.source(&before_cursor, "markdown_links", || {
+                        self.markdown_links(params)
+                    }))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_frontmatter_tags {
+                    Some(self.source(&before_cursor, "frontmatter_tags", || {
+                        self.frontmatter_tags(params)
+                    }))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_labels {
+                    Some(self.source(&before_cursor, "labels", || self.labels(params)))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_bibtex {
+                    Some(self.source(&before_cursor, "bibtex", || self.bibtex(params)))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(self.source(&before_cursor, "issues", || self.issues(params)))
+            .chain(self.source(&before_cursor, "command_sources", || {
+                self.command_sources(params)
+            }))
+            .chain(if self.settings.feature_mentions {
+                self.source(&before_cursor, "mentions", || self.mentions(params))
+            } else {
+                Vec::new()
+            })
+            .chain(if self.settings.feature_cli_flags {
+                self.source(&before_cursor, "cli_flags", || {
+                    self.cli_flags(&before_cursor, params, doc)
+                })
+            } else {
+                Vec::new()
+            })
+            .chain(
+                if self.settings.feature_unicode_input {
+                    Some(self.source(&before_cursor, "unicode_input", || {
+                        self.unicode_input(prefix.unwrap_or_default(), params)
+                    }))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .chain(
+                if self.settings.feature_paths {
+                    Some(self.source(&before_cursor, "paths", || {
+                        self.paths(prefix.unwrap_or_default(), params)
+                    }))
+                } else {
+                    None
+                }
+                .into_iter()
+                .flatten(),
+            )
+            .collect())
+    }
+
+    pub fn open_doc(&mut self, params: DidOpenTextDocumentParams) {
+        let words_filter = self.words_filter(&params.text_document.uri);
+        self.docs.insert(
+            params.text_document.uri.clone(),
+            Document::new(
+                params.text_document.uri,
+                Rope::from_str(&params.text_document.text),
+                params.text_document.language_id,
+                params.text_document.version,
+                words_filter,
+            ),
+        );
+    }
+
+    /// Convert position column from client encoding to char column
+    fn decode_position(&self, uri: &Url, position: &mut Position) {
+        if let Some(line) = self
+            .docs
+            .get(uri)
+            .and_then(|d| d.text.get_line(position.line as usize))
+        {
+            position.character =
+                position::to_char_column(line, position.character, &self.position_encoding);
+        }
+    }
+
+    /// Mark document as recently used, re-read its text from disk if it was evicted
+    fn touch_doc(&mut self, uri: &Url) {
+        self.docs_clock += 1;
+        if self.evicted_docs.contains_key(uri) && !self.restoring_docs.contains_key(uri) {
+            self.restore_doc(uri.clone());
+        }
+        if let Some(doc) = self.docs.get_mut(uri) {
+            doc.last_used = self.docs_clock;
+        }
+    }
+
+    /// Read evicted document from disk in background, its changes are queued until then.
+    /// Evicted documents are never dirty, so file content is the one client has
+    fn restore_doc(&mut self, uri: Url) {
+        self.docs_clock += 1;
+        let restore = self.docs_clock;
+        // read again after save drops queued changes, saved file has them
+        self.restoring_docs
+            .insert(uri.clone(), (restore, Vec::new()));
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let text = match uri_to_path(&uri) {
+                Some(path) => tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| tracing::warn!("Failed to restore evicted document {uri}: {e}"))
+                    .ok()
+                    .map(Rope::from),
+                None => None,
+            };
+            let _ = tx
+                .send(BackendRequest::RestoredDoc((uri, restore, text)))
+                .await;
+        });
+    }
+
+    /// Evicted document is read from disk, changes made meanwhile are applied to it
+    fn restored_doc(&mut self, uri: Url, restore: u64, text: Option<Rope>) {
+        // document is reopened or read again after save
+        if self
+            .restoring_docs
+            .get(&uri)
+            .is_none_or(|(current, _)| *current != restore)
+        {
+            return;
+        }
+        let Some((_, changes)) = self.restoring_docs.remove(&uri) else {
+            return;
+        };
+        let Some(evicted) = self.evicted_docs.remove(&uri) else {
+            return;
+        };
+        tracing::debug!("Restore evicted document {uri}");
+        let failed = text.is_none();
+        self.docs.insert(
+            uri.clone(),
+            Document::new(
+                uri.clone(),
+                text.unwrap_or_default(),
+                evicted.language_id,
+                evicted.version,
+                evicted.index.filter().clone(),
+            ),
+        );
+        // unreadable document waits for full content from client
+        if failed {
+            self.stale_docs.insert(uri.clone());
+        }
+        self.touch_doc(&uri);
+        for params in changes {
+            if let Err(e) = self.change_doc(params) {
+                tracing::error!("Error on change doc: {e}");
+            }
+        }
+    }
+
+    /// Evict least recently used documents over `max_resident_*` limits
+    fn evict_docs(&mut self) {
+        let over_limit = |docs: &HashMap<Url, Document>, settings: &BackendSettings| {
+            docs.len() > 1
+                && (settings
+                    .max_resident_documents
+                    .is_some_and(|max| docs.len() > max)
+                    || settings.max_resident_bytes.is_some_and(|max| {
+                        docs.values().map(|d| d.text.len_bytes()).sum::<usize>() > max
+                    }))
+        };
+
+        while over_limit(&self.docs, &self.settings) {
+            // path-less, changed and out of sync documents can't be restored, keep them resident
+            let Some(uri) = self
+                .docs
+                .values()
+                .filter(|d| {
+                    uri_to_path(&d.uri).is_some() && !d.dirty && !self.stale_docs.contains(&d.uri)
+                })
+                .min_by_key(|d| d.last_used)
+                .map(|d| d.uri.clone())
+            else {
+                break;
+            };
+            let Some(doc) = self.docs.remove(&uri) else {
+                break;
+            };
+            tracing::debug!("Evict document {uri}, keep {} words", doc.index.len());
+            self.evicted_docs.insert(
+                uri,
+                EvictedDocument {
+                    language_id: doc.language_id,
+                    version: doc.version,
+                    index: doc.index,
+                },
+            );
+        }
+    }
+
+    /// Requests waiting in channel and taken out of it ahead
+    fn queue_depth(&self) -> usize {
+        queue_depth(&self.tx) + self.queued.len()
+    }
+
+    /// Next request taken out of channel ahead of time or waiting in channel
+    fn try_next_request(&mut self) -> Option<BackendRequest> {
+        self.queued.pop_front().or_else(|| self.rx.try_recv().ok())
+    }
+
+    /// Merge queued sequential changes of the same document into one
+    fn coalesce_changes(
+        &mut self,
+        mut params: DidChangeTextDocumentParams,
+    ) -> DidChangeTextDocumentParams {
+        while let Some(next) = self.try_next_request() {
+            match next {
+                BackendRequest::ChangeDoc(next)
+                    if next.text_document.uri == params.text_document.uri
+                        && next.text_document.version > params.text_document.version =>
+                {
+                    params.text_document.version = next.text_document.version;
+                    params.content_changes.extend(next.content_changes);
+                }
+                next => {
+                    self.queued.push_front(next);
+                    break;
+                }
+            }
+        }
+        params
+    }
+
+    /// Document is changed and completed again by queued requests, this completion is outdated.
+    /// Back-to-back completions without change between are both computed, repeated one is
+    /// usually served by completion cache
+    fn completion_superseded(&mut self, uri: &Url) -> bool {
+        while self.queued.len() < SUPERSEDED_LOOKAHEAD {
+            let Ok(next) = self.rx.try_recv() else {
+                break;
+            };
+            self.queued.push_back(next);
+        }
+        let mut changed = false;
+        for next in &self.queued {
+            match next {
+                BackendRequest::ChangeDoc(params) if params.text_document.uri == *uri => {
+                    changed = true;
+                }
+                BackendRequest::CompletionRequest((_, params, _))
+                    if changed && params.text_document_position.text_document.uri == *uri =>
+                {
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    pub async fn start(mut self) {
+        loop {
+            let cmd = match self.queued.pop_front() {
+                Some(cmd) => cmd,
+                None => {
+                    let Some(cmd) = self.rx.recv().await else {
+                        continue;
+                    };
+                    cmd
+                }
+            };
+
+            let depth = self.queue_depth();
+            self.stats.borrow_mut().record_queue_depth(depth);
+            if depth >= QUEUE_DEPTH_WARNING {
+                tracing::warn!(queue_depth = depth, "Backend is falling behind");
+            }
+
+            // any other request may change completion results
+            if !matches!(
+                cmd,
+                BackendRequest::CompletionRequest(_)
+                    | BackendRequest::Metrics(_)
+                    | BackendRequest::Ping(_)
+                    | BackendRequest::Stats(_)
+                    | BackendRequest::SelfCheck(_)
+                    | BackendRequest::UnicodeByName(_)
+                    | BackendRequest::CodeAction(_)
+                    | BackendRequest::WorkspaceSymbol(_)
+                    | BackendRequest::DocumentLink(_)
+                    | BackendRequest::DocumentHighlight(_)
+                    | BackendRequest::ResolveCompletion(_)
+            ) {
+                self.completion_cache.clear();
+            }
+
+            match cmd {
+                BackendRequest::Initialize(params) => self.initialize(params),
+                BackendRequest::NewDoc(params) => {
+                    let uri = params.text_document.uri.clone();
+                    self.evicted_docs.remove(&uri);
+                    self.restoring_docs.remove(&uri);
+                    self.stale_docs.remove(&uri);
+                    self.open_doc(params);
+                    self.touch_doc(&uri);
+                    self.evict_docs();
+                }
+                BackendRequest::ChangeWatchedFiles(params) => {
+                    self.change_watched_files(params);
+                }
+                BackendRequest::SaveDoc(params) => {
+                    self.touch_doc(&params.text_document.uri);
+                    if let Some(path) = uri_to_path(&params.text_document.uri) {
+                        // editors not watching files report folder settings changes on save only
+                        if path.ends_with(FOLDER_SETTINGS_FILENAME) {
+                            self.load_folder_settings();
+                        }
+                        self.expire_mentions(&path);
+                    }
+                    if let Err(e) = self.save_doc(params) {
+                        tracing::error!("Error on save doc: {e}");
+                    }
+                    self.evict_docs();
+                }
+                BackendRequest::ChangeDoc(params) => {
+                    let params = self.coalesce_changes(params);
+                    // content read on save is outdated now
+                    self.pending_reads.remove(&params.text_document.uri);
+                    self.touch_doc(&params.text_document.uri);
+                    if let Err(e) = self.change_doc(params) {
+                        tracing::error!("Error on change doc: {e}");
+                    }
+                    self.evict_docs();
+                }
+                BackendRequest::TmuxWords(words) => {
+                    self.tmux_words = words;
+                }
+                BackendRequest::LoadedMentions((key, files, people)) => {
+                    self.mentions_loading.borrow_mut().remove(&key);
+                    let mut cached = self.mentions.borrow_mut();
+                    let people = people
+                        .or_else(|| cached.remove(&key).map(|(_, _, people)| people))
+                        .unwrap_or_default();
+                    cached.insert(key, (Some(std::time::Instant::now()), files, people));
+                }
+                BackendRequest::RanCommand((dir, command, output)) => {
+                    self.commands.loaded(dir, command, output);
+                }
+                BackendRequest::LoadedGit((dir, query, lines)) => {
+                    self.git.loaded(dir, query, lines);
+                }
+                BackendRequest::LoadedTags((roots, tags)) => {
+                    self.tags_loading.set(false);
+                    *self.tags.borrow_mut() = Some((std::time::Instant::now(), roots, tags));
+                }
+                BackendRequest::LoadedSources(sources) => {
+                    self.set_sources(sources.snippets, sources.unicode_input, sources.words);
+                    self.set_unicode_input_info(sources.unicode_input_info);
+                    self.sources_reloading = false;
+                    if std::mem::take(&mut self.sources_reload_pending) {
+                        self.reload_sources();
+                    }
+                }
+                BackendRequest::ReloadSnippets => self.reload_sources(),
+                BackendRequest::IndexedWorkspace(mut index) => {
+                    self.workspace_indexing = false;
+                    // exclusion changed while indexing
+                    for (_, index) in index
+                        .iter_mut()
+                        .filter(|(_, index)| *index.filter() != self.settings.words_filter)
+                    {
+                        index.refilter(self.settings.words_filter.clone());
+                    }
+                    self.workspace_index = Some(index);
+                }
+                BackendRequest::LoadedBibKeys(bib_keys) => {
+                    self.bib_keys.replace(Some(bib_keys));
+                }
+                BackendRequest::RestoredDoc((uri, restore, text)) => {
+                    self.restored_doc(uri, restore, text);
+                    self.evict_docs();
+                }
+                BackendRequest::LoadedDoc((uri, text)) => {
+                    let stale = self.stale_docs.remove(&uri);
+                    if self.pending_reads.remove(&uri) || stale {
+                        if let Some(doc) = self.docs.get_mut(&uri) {
+                            doc.set_text(text);
+                        }
+                    }
+                }
+                BackendRequest::ChangeConfiguration(params) => {
+                    if let Err(e) = self.change_configuration(params) {
+                        tracing::error!("Error on change configuration: {e}");
+                    }
+                }
+                BackendRequest::Metrics(tx) => {
+                    let metrics = self.stats.borrow().prometheus(
+                        self.docs.len(),
+                        self.evicted_docs.len(),
+                        self.queue_depth(),
+                    );
+                    if tx.send(metrics).is_err() {
+                        tracing::error!("Error on send metrics");
+                    }
+                }
+                BackendRequest::ToggleFeature((tx, feature)) => {
+                    if tx.send(self.toggle_feature(&feature)).is_err() {
+                        tracing::error!("Error on send toggled feature");
+                    }
+                }
+                BackendRequest::Stats(tx) => {
+                    let stats = self.stats.borrow();
+                    let summary = format!(
+                        "{} completion requests ({} from cache, {} superseded), {} documents ({} evicted). {}",
+                        stats.completion_requests,
+                        stats.completion_cache_hits,
+                        stats.completion_superseded,
+                        self.docs.len(),
+                        self.evicted_docs.len(),
+                        stats.summary()
+                    );
+                    if tx.send(summary.trim_end().to_string()).is_err() {
+                        tracing::error!("Error on send stats");
+                    }
+                }
+                BackendRequest::Ping(tx) => {
+                    if tx
+                        .send((self.settings.enabled_features(), self.queued.len()))
+                        .is_err()
+                    {
+                        tracing::error!("Error on send ping");
+                    }
+                }
+                BackendRequest::CompletionAccepted((workspace, language, label)) => {
+                    if self.settings.acceptance_stats {
+                        self.acceptance.record(&workspace, &language, &label);
+                        if let Err(e) = self.acceptance.save() {
+                            tracing::error!("Error on save acceptance stats: {e}");
+                        }
+                    }
+                }
+                BackendRequest::WorkspaceTrust((workspace, trusted)) => {
+                    tracing::info!("Workspace {workspace} trusted to run commands: {trusted}");
+                    self.workspace_trust
+                        .borrow_mut()
+                        .insert(workspace, Some(trusted));
+                }
+                BackendRequest::Shutdown(tx) => {
+                    if let Err(e) = self.save_warm_cache() {
+                        tracing::error!("Error on save warm cache: {e}");
+                    }
+                    if tx.send(()).is_err() {
+                        tracing::error!("Error on send shutdown");
+                    }
+                }
+                BackendRequest::SnippetAccepted(prefix) => {
+                    self.snippets_usage.record(&prefix);
+                    if let Err(e) = self.snippets_usage.save() {
+                        tracing::error!("Error on save snippets usage: {e}");
+                    }
+                }
+                BackendRequest::UnicodeByName((tx, fragment)) => {
+                    if tx.send(self.unicode_by_name(&fragment)).is_err() {
+                        tracing::error!("Error on send unicode input by name");
+                    }
+                }
+                BackendRequest::CodeAction((tx, mut params)) => {
+                    self.touch_doc(&params.text_document.uri);
+                    self.decode_position(&params.text_document.uri, &mut params.range.start);
+                    let folder = self.folder_settings(&params.text_document.uri);
+                    self.swap_settings(folder);
+                    let actions = self.code_actions(&params);
+                    self.swap_settings(folder);
+                    if tx.send(actions).is_err() {
+                        tracing::error!("Error on send code actions");
+                    }
+                }
+                BackendRequest::WorkspaceSymbol((tx, query)) => {
+                    self.workspace_symbols(query, tx);
+                }
+                BackendRequest::DocumentLink((tx, uri)) => {
+                    self.touch_doc(&uri);
+                    let folder = self.folder_settings(&uri);
+                    self.swap_settings(folder);
+                    let links = self.document_links(&uri).unwrap_or_else(|e| {
+                        tracing::error!("On document link: {e}");
+                        Vec::new()
+                    });
+                    self.swap_settings(folder);
+                    if tx.send(links).is_err() {
+                        tracing::error!("Error on send document links");
+                    }
+                }
+                BackendRequest::DocumentHighlight((tx, mut params)) => {
+                    self.touch_doc(&params.text_document.uri);
+                    self.decode_position(&params.text_document.uri, &mut params.position);
+                    let folder = self.folder_settings(&params.text_document.uri);
+                    self.swap_settings(folder);
+                    let highlights = self.document_highlights(&params).unwrap_or_else(|e| {
+                        tracing::error!("On document highlight: {e}");
+                        Vec::new()
+                    });
+                    self.swap_settings(folder);
+                    if tx.send(highlights).is_err() {
+                        tracing::error!("Error on send document highlights");
+                    }
+                }
+                BackendRequest::ResolveCompletion((tx, item)) => {
+                    if tx.send(self.resolve_completion(*item)).is_err() {
+                        tracing::error!("Error on send resolved completion");
+                    }
+                }
+                BackendRequest::SelfCheck(tx) => {
+                    if tx.send(self.self_check()).is_err() {
+                        tracing::error!("Error on send self check");
+                    }
+                }
+                BackendRequest::CompletionRequest((tx, mut params, partial_tx)) => {
+                    let now = std::time::Instant::now();
+                    self.stats.borrow_mut().completion_requests += 1;
+
+                    let uri = params.text_document_position.text_document.uri.clone();
+                    // user typed further, only the newest request is worth answering
+                    if self.completion_superseded(&uri) {
+                        self.stats.borrow_mut().completion_superseded += 1;
+                        tracing::debug!(document = %uri, "completion superseded by newer request");
+                        let response = BackendResponse::CompletionResponse(
+                            CompletionResponse::List(CompletionList {
+                                is_incomplete: true,
+                                items: Vec::new(),
+                            }),
+                        );
+                        if tx.send(Ok(response)).is_err() {
+                            tracing::error!("Error on send completion response");
+                        }
+                        continue;
+                    }
+                    let _span = tracing::debug_span!("completion", document = %uri).entered();
+                    self.touch_doc(&uri);
+                    // evicted document is read from disk, client asks again
+                    if self.restoring_docs.contains_key(&uri) {
+                        let response = BackendResponse::CompletionResponse(
+                            CompletionResponse::List(CompletionList {
+                                is_incomplete: true,
+                                items: Vec::new(),
+                            }),
+                        );
+                        if tx.send(Ok(response)).is_err() {
+                            tracing::error!("Error on send completion response");
+                        }
+                        continue;
+                    }
+
+                    // work with char columns, convert back to client encoding on response
+                    self.decode_position(&uri, &mut params.text_document_position.position);
+
+                    let cache_key = self.docs.get(&uri).map(|doc| CompletionCacheKey {
+                        uri: uri.clone(),
+                        version: doc.version,
+                        line: params.text_document_position.position.line,
+                        character: params.text_document_position.position.character,
+                        trigger: params
+                            .context
+                            .as_ref()
+                            .and_then(|c| c.trigger_character.clone()),
+                    });
+                    self.completion_cache
+                        .retain(|(_, created, _)| created.elapsed() < COMPLETION_CACHE_TTL);
+                    if let Some((_, _, items)) = self
+                        .completion_cache
+                        .iter()
+                        .find(|(key, _, _)| Some(key) == cache_key.as_ref())
+                    {
+                        self.stats.borrow_mut().completion_cache_hits += 1;
+                        tracing::debug!(document = %uri, "completion served from cache");
+                        let response = BackendResponse::CompletionResponse(
+                            CompletionResponse::Array(items.clone()),
+                        );
+                        if tx.send(Ok(response)).is_err() {
+                            tracing::error!("Error on send completion response");
+                        }
+                        continue;
+                    }
+
+                    // streamed items are not in results, cache keeps full lists only
+                    let streamed = partial_tx.is_some();
+                    self.partial_results
+                        .replace(partial_tx.map(|partial_tx| (partial_tx, uri.clone())));
+
+                    // settings of document folder are used for this request only
+                    let folder = self.folder_settings(&uri);
+                    self.swap_settings(folder);
+
+                    self.completion_incomplete.set(false);
+                    let results = self.completion_items(&params).map(|mut items| {
+                        if let Some(doc) = self.docs.get(&uri) {
+                            for item in &mut items {
+                                position::encode_item(item, &doc.text, &self.position_encoding);
+                            }
+                        }
+                        items
+                    });
+
+                    if let Ok(results) = &results {
+                        let prefix_len = self
+                            .get_prefix(&params)
+                            .ok()
+                            .and_then(|(prefix, _)| prefix)
+                            .map(|prefix| prefix.chars().count())
+                            .unwrap_or_default();
+                        tracing::debug!(
+                            method = "textDocument/completion",
+                            document = %uri,
+                            prefix_len,
+                            elapsed_ms = now.elapsed().as_millis() as u64,
+                            items = results.len(),
+                            "completion request took {:.2}ms with {} result items",
+                            now.elapsed().as_millis(),
+                            results.len(),
+                        );
+                    }
+
+                    if let Some(summary) = self.stats.borrow_mut().periodic_summary() {
+                        tracing::info!("Completion sources latency: {summary}");
+                    }
+
+                    self.swap_settings(folder);
+                    // close partial results stream
+                    self.partial_results.replace(None);
+
+                    // items of sources waiting for background output are missing yet
+                    let is_incomplete = self.completion_incomplete.get();
+                    if let (Some(key), Ok(results), false, false) =
+                        (cache_key, &results, streamed, is_incomplete)
+                    {
+                        if self.completion_cache.len() >= COMPLETION_CACHE_SIZE {
+                            self.completion_cache.remove(0);
+                        }
+                        self.completion_cache.push((
+                            key,
+                            std::time::Instant::now(),
+                            results.clone(),
+                        ));
+                    }
+
+                    let response = results.map(|results| {
+                        BackendResponse::CompletionResponse(if is_incomplete {
+                            CompletionResponse::List(CompletionList {
+                                is_incomplete,
+                                items: results,
+                            })
+                        } else {
+                            CompletionResponse::Array(results)
+                        })
+                    });
 
-                    if tx.send(Ok(response)).is_err() {
+                    if tx.send(response).is_err() {
                         tracing::error!("Error on send completion response");
                     }
                 }
@@ -0,0 +1,52 @@
+// chars surrounding paths and urls in text, like markdown `[label](path)` or quotes
+const DELIMITERS: [char; 11] = ['"', '\'', '`', '<', '>', '(', ')', '[', ']', '{', '}'];
+
+// trailing punctuation of sentence, not a part of link
+const TRAILING: [char; 6] = ['.', ',', ';', ':', '!', '?'];
+
+#[derive(Debug, PartialEq)]
+pub enum LinkKind {
+    Url,
+    Path,
+}
+
+/// Candidates of urls and paths in line as (start char, end char, text, kind)
+pub fn find_links(line: &str, max_chars: usize) -> Vec<(usize, usize, &str, LinkKind)> {
+    let mut result = Vec::new();
+    let mut start = None;
+    // char index and byte offset of each char, with line end sentinel
+    let chars = line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ' ')))
+        .enumerate();
+    for (idx, (offset, ch)) in chars {
+        let is_delimiter = ch.is_whitespace() || DELIMITERS.contains(&ch);
+        match (start, is_delimiter) {
+            (None, false) => start = Some((idx, offset)),
+            (Some((start_idx, start_offset)), true) => {
+                start = None;
+                let token = line[start_offset..offset].trim_end_matches(TRAILING);
+                let len = token.chars().count();
+                if len > max_chars {
+                    continue;
+                }
+                let kind = if token.starts_with("https://") || token.starts_with("http://") {
+                    LinkKind::Url
+                } else if token.contains('/')
+                    && !token.contains("://")
+                    && token
+                        .chars()
+                        .next()
+                        .is_some_and(|c| c.is_alphanumeric() || c == '/' || c == '~' || c == '.')
+                {
+                    LinkKind::Path
+                } else {
+                    continue;
+                };
+                result.push((start_idx, start_idx + len, token, kind));
+            }
+            _ => {}
+        }
+    }
+    result
+}
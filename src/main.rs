@@ -1,42 +1,26 @@
 use etcetera::base_strategy::{choose_base_strategy, BaseStrategy};
 use std::collections::HashMap;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::io::Read;
+use tower_lsp::lsp_types;
+use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt, Layer};
 use xshell::{cmd, Shell};
 
 use simple_completion_language_server::{
-    server,
-    snippets::config::{load_snippets, load_unicode_input_from_path},
+    acceptance::AcceptanceStats,
+    logging, selfcheck, server,
+    snippets::config::{
+        append_snippet, load_external_words, load_snippets, load_unicode_input_from_path,
+        load_words_from_file, UnicodeInput,
+    },
     snippets::external::ExternalSnippets,
-    StartOptions,
+    snippets::search,
+    snippets::usage::SnippetUsage,
+    snippets::validate::{self, FindingKind},
+    snippets::{Snippet, WordEntry},
+    BackendState, LoadedSources, StartOptions,
 };
 
-async fn serve(start_options: &StartOptions) {
-    let _quard = if let Ok(log_file) = &std::env::var("LOG_FILE") {
-        let log_file = std::path::Path::new(log_file);
-        let file_appender = tracing_appender::rolling::never(
-            log_file
-                .parent()
-                .expect("Failed to parse LOG_FILE parent part"),
-            log_file
-                .file_name()
-                .expect("Failed to parse LOG_FILE file_name part"),
-        );
-        let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-        tracing_subscriber::registry()
-            .with(tracing_subscriber::EnvFilter::new(
-                std::env::var("RUST_LOG")
-                    .unwrap_or_else(|_| "info,simple-comletion-language-server=info".into()),
-            ))
-            .with(tracing_subscriber::fmt::layer().with_writer(non_blocking))
-            .init();
-        Some(_guard)
-    } else {
-        None
-    };
-
-    let stdin = tokio::io::stdin();
-    let stdout = tokio::io::stdout();
-
+fn load_sources(start_options: &StartOptions) -> (Vec<Snippet>, UnicodeInput, Vec<WordEntry>) {
     let snippets = load_snippets(start_options).unwrap_or_else(|e| {
         tracing::error!("On read snippets: {e}");
         Vec::new()
@@ -45,15 +29,167 @@ async fn serve(start_options: &StartOptions) {
     let unicode_input = load_unicode_input_from_path(&start_options.unicode_input_path)
         .unwrap_or_else(|e| {
             tracing::error!("On read 'unicode input' config: {e}");
-            HashMap::new()
+            UnicodeInput::default()
+        });
+
+    let mut words = if start_options.words_path.exists() {
+        load_words_from_file(&start_options.words_path).unwrap_or_else(|e| {
+            tracing::error!("On read words: {e}");
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    words.extend(load_external_words(start_options).unwrap_or_else(|e| {
+        tracing::error!("On read external words: {e}");
+        Vec::new()
+    }));
+
+    (snippets, unicode_input, words)
+}
+
+fn init_logging<W>(writer: W)
+where
+    W: for<'w> MakeWriter<'w> + Clone + Send + Sync + 'static,
+{
+    let json = std::env::var("LOG_FORMAT").is_ok_and(|f| f == "json");
+    // filter per log layer, profile records spans regardless of log level
+    let env_filter = || {
+        tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG")
+                .unwrap_or_else(|_| "info,simple-comletion-language-server=info".into()),
+        )
+    };
+
+    #[cfg(feature = "profiling")]
+    let profile = Some(
+        simple_completion_language_server::profile::ProfileLayer.with_filter(
+            tracing_subscriber::filter::filter_fn(|metadata| {
+                metadata.is_span()
+                    && metadata
+                        .target()
+                        .starts_with("simple_completion_language_server")
+            }),
+        ),
+    );
+    #[cfg(not(feature = "profiling"))]
+    let profile: Option<tracing_subscriber::layer::Identity> = None;
+
+    // export spans to OpenTelemetry collector when endpoint is configured
+    #[cfg(feature = "otlp")]
+    let otlp = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .map(|endpoint| {
+            simple_completion_language_server::otlp::OtlpLayer::new(endpoint).with_filter(
+                tracing_subscriber::filter::filter_fn(|metadata| {
+                    metadata.is_span()
+                        && metadata
+                            .target()
+                            .starts_with("simple_completion_language_server")
+                }),
+            )
         });
+    #[cfg(not(feature = "otlp"))]
+    let otlp: Option<tracing_subscriber::layer::Identity> = None;
+
+    tracing_subscriber::registry()
+        .with(profile)
+        .with(otlp)
+        .with((!json).then(|| {
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer.clone())
+                .with_filter(env_filter())
+        }))
+        .with(json.then(|| {
+            tracing_subscriber::fmt::layer()
+                .event_format(logging::JsonFormat)
+                .with_writer(writer)
+                .with_filter(env_filter())
+        }))
+        .init();
+}
+
+async fn serve(start_options: &StartOptions, metrics_addr: Option<std::net::SocketAddr>) {
+    let _quard = if let Ok(log_file) = &std::env::var("LOG_FILE") {
+        let log_file = logging::expand_log_path(log_file);
+        let log_file = std::path::Path::new(&log_file);
+        let directory = log_file
+            .parent()
+            .expect("Failed to parse LOG_FILE parent part");
+        let file_name = log_file
+            .file_name()
+            .expect("Failed to parse LOG_FILE file_name part");
+        let (non_blocking, _guard) =
+            if let Ok(max_size) = std::env::var("LOG_MAX_SIZE").map(|v| v.parse::<u64>()) {
+                let max_files = std::env::var("LOG_MAX_FILES")
+                    .ok()
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(3);
+                tracing_appender::non_blocking(
+                    logging::SizeRotatingWriter::new(
+                        log_file.to_path_buf(),
+                        max_size.expect("Failed to parse LOG_MAX_SIZE"),
+                        max_files,
+                    )
+                    .expect("Failed to open LOG_FILE"),
+                )
+            } else {
+                let rotation = match std::env::var("LOG_ROTATION").as_deref() {
+                    Ok("minutely") => tracing_appender::rolling::Rotation::MINUTELY,
+                    Ok("hourly") => tracing_appender::rolling::Rotation::HOURLY,
+                    Ok("daily") => tracing_appender::rolling::Rotation::DAILY,
+                    _ => tracing_appender::rolling::Rotation::NEVER,
+                };
+                tracing_appender::non_blocking(tracing_appender::rolling::RollingFileAppender::new(
+                    rotation, directory, file_name,
+                ))
+            };
+        init_logging(non_blocking);
+        Some(_guard)
+    } else {
+        None
+    };
+
+    let stdin = tokio::io::stdin();
+    let stdout = tokio::io::stdout();
+
+    // large snippets collections are loaded after initialization, server is ready immediately
+    let load_options = start_options.clone();
+    let load_sources = move || {
+        let (snippets, unicode_input, words) = load_sources(&load_options);
+        LoadedSources {
+            snippets,
+            unicode_input: unicode_input.bodies,
+            unicode_input_info: unicode_input.info,
+            words,
+            problems: selfcheck::check_start_options(&load_options),
+        }
+    };
 
     server::start(
         stdin,
         stdout,
-        snippets,
-        unicode_input,
+        Vec::new(),
+        HashMap::new(),
+        Vec::new(),
         start_options.home_dir.clone(),
+        server::ServerOptions {
+            metrics_addr,
+            snippets_usage_path: Some(start_options.snippets_usage_path.clone()),
+            acceptance_path: Some(start_options.acceptance_path.clone()),
+            warm_cache_path: Some(start_options.warm_cache_path.clone()),
+            load_sources: Some(std::sync::Arc::new(load_sources)),
+            sources_paths: start_options
+                .snippets_paths
+                .iter()
+                .chain([
+                    &start_options.external_snippets_config_path,
+                    &start_options.unicode_input_path,
+                ])
+                .cloned()
+                .collect(),
+            ..Default::default()
+        },
     )
     .await;
 }
@@ -61,16 +197,130 @@ async fn serve(start_options: &StartOptions) {
 fn help() {
     println!(
         "usage:
-simple-completion-language-server feth-external-snippets
+simple-completion-language-server init [--force]
+    Create config dir with commented starter files (existed files are kept without --force).
+simple-completion-language-server fetch-external-snippets [--dry-run] [--offline]
     Fetch external snippets (git clone or git pull).
-simple-completion-language-server validate-snippets
+    With --dry-run only show what would be cloned or updated.
+    With --offline skip network and report stale or missing sources.
+simple-completion-language-server validate-snippets [--format plain|json]
     Read all snippets to ensure correctness.
-simple-completion-language-server
-    Start language server protocol on stdin+stdout."
+simple-completion-language-server search-snippets <query>
+    Fuzzy search snippets by prefix, description and body over all sources.
+    Prints prefix, scope, description and source (tab separated).
+simple-completion-language-server analyze [--path <path>] [--top <n>]
+    Print top words, candidate stop-words and suggested `words_exclude` for the project.
+simple-completion-language-server complete --file <path> --line <line> --col <col> [--stdin] [--language <id>] [--format plain|json]
+    Print completions at position (1-based line and column) of the file.
+    With --stdin read file content from stdin.
+simple-completion-language-server snippet add --prefix <prefix> [--scope <scope>] [--description <text>] [--body <text>|-] [--when <regex>] [--force]
+    Append snippet to <scope>.toml (or snippets.toml) at snippets dir.
+    Body is read from stdin on `--body -` or from $EDITOR when omitted.
+simple-completion-language-server snippet usage [--reset]
+    Print accepted snippets counters (used to rank snippets), most used first.
+simple-completion-language-server accepted [--reset]
+    Print accepted completions counters (used to rank items of any source) by workspace and language.
+simple-completion-language-server [--metrics-addr <addr>]
+    Start language server protocol on stdin+stdout.
+    With --metrics-addr (e.g. 127.0.0.1:9090) serve Prometheus metrics, requires `metrics` feature.
+
+Options of any command:
+    --config-dir <path>  Use config dir (also `SCLS_CONFIG_DIR` env) for all files instead of platform dirs.
+    --portable           Use binary dir (also `SCLS_PORTABLE` env) as config dir.
+    --snippets-path <path>  Snippets dir searched after previous ones, may be repeated (also `SNIPPETS_PATH` env,
+                         list separated like `PATH`), later dirs override earlier snippets of the same prefix and scope.
+                         New snippets are written to the last dir."
     );
 }
 
-fn fetch_external_snippets(start_options: &StartOptions) -> anyhow::Result<()> {
+const INIT_SNIPPETS: &str = r#"# Snippets, filename used as scope (e.g. `python.toml`),
+# `snippets.toml` snippets are available for any language.
+#
+# [[snippets]]
+# prefix = "ld"
+# scope = [ "python" ]
+# body = 'log.debug("$1")'
+# description = "log debug"
+"#;
+
+const INIT_UNICODE_INPUT: &str = r#"# Unicode input, key is text to type and value is inserted text.
+#
+# alpha = "α"
+# betta = "β"
+# fire = "🔥"
+#
+# several items with variants (named by skin tone or index) or named variants
+# thumbsup = ["👍", "👍🏻", "👍🏿"]
+# heart = { default = "❤️", orange = "🧡", green = "💚" }
+"#;
+
+const INIT_EXTERNAL_SNIPPETS: &str = r#"# External snippets sources, run `simple-completion-language-server fetch-external-snippets` to fetch.
+#
+# [[sources]]
+# name = "friendly-snippets"
+# git = "https://github.com/rafamadriz/friendly-snippets.git"
+#
+# [[sources.paths]]
+# scope = ["python"]
+# path = "snippets/python/python.json"
+#
+# [[sources]]
+# name = "vscode-go"
+# git = "https://github.com/golang/vscode-go.git"
+#
+# [[sources.paths]]
+# scope = ["go"]
+# path = "extension/snippets/go.json"
+#
+# plain word lists (`.txt` or `.txt.gz`, one entry per line) on sources paths are loaded as words
+"#;
+
+fn init(start_options: &StartOptions, args: &[String]) -> anyhow::Result<()> {
+    let force = match args {
+        [] => false,
+        [flag] if flag == "--force" => true,
+        _ => anyhow::bail!("Unknown arguments: {args:?}"),
+    };
+
+    let snippets_path = start_options
+        .user_snippets_path()
+        .ok_or_else(|| anyhow::anyhow!("Snippets path is not set"))?;
+    for (path, content) in [
+        (snippets_path.join("snippets.toml"), INIT_SNIPPETS),
+        (
+            start_options.unicode_input_path.join("base.toml"),
+            INIT_UNICODE_INPUT,
+        ),
+        (
+            start_options.external_snippets_config_path.clone(),
+            INIT_EXTERNAL_SNIPPETS,
+        ),
+    ] {
+        if path.exists() && !force {
+            tracing::info!("Skip existed: {path:?}");
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content)?;
+        tracing::info!("Created: {path:?}");
+    }
+
+    Ok(())
+}
+
+fn fetch_external_snippets(start_options: &StartOptions, args: &[String]) -> anyhow::Result<()> {
+    let mut dry_run = false;
+    let mut offline = false;
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--offline" => offline = true,
+            _ => anyhow::bail!("Unknown argument: {arg}"),
+        }
+    }
+
     tracing::info!(
         "Try read config from: {:?}",
         start_options.external_snippets_config_path
@@ -95,44 +345,482 @@ fn fetch_external_snippets(start_options: &StartOptions) -> anyhow::Result<()> {
         .map_err(|e| anyhow::anyhow!(e))?;
 
     let sh = Shell::new()?;
+    let mut missing = Vec::new();
     for source in sources {
         let git_repo = &source.git;
         let destination_path = base_path.join(source.destination_path()?);
 
+        if offline {
+            if !destination_path.exists() {
+                tracing::warn!("Missing (not cloned): {git_repo}");
+                missing.push(git_repo.clone());
+                continue;
+            }
+            // FETCH_HEAD is touched on each pull, HEAD only on clone
+            let git_dir = destination_path.join(".git");
+            let updated = ["FETCH_HEAD", "HEAD"]
+                .iter()
+                .find_map(|f| std::fs::metadata(git_dir.join(f)).ok()?.modified().ok())
+                .and_then(|t| t.elapsed().ok());
+            match updated {
+                Some(elapsed) => tracing::warn!(
+                    "Stale: {git_repo} (last updated {} days ago)",
+                    elapsed.as_secs() / 86400
+                ),
+                None => tracing::warn!("Stale: {git_repo} (last update unknown)"),
+            }
+            continue;
+        }
+
         // TODO don't fetch full history?
         if destination_path.exists() {
-            sh.change_dir(&destination_path);
             tracing::info!("Try update: {:?}", destination_path);
+            if dry_run {
+                continue;
+            }
+            sh.change_dir(&destination_path);
             cmd!(sh, "git pull --rebase").run()?;
         } else {
             tracing::info!("Try clone {} to {:?}", git_repo, destination_path);
+            if dry_run {
+                continue;
+            }
             sh.create_dir(&destination_path)?;
             cmd!(sh, "git clone {git_repo} {destination_path}").run()?;
         }
     }
 
+    if !missing.is_empty() {
+        anyhow::bail!("Not available offline: {}", missing.join(", "));
+    }
+
     Ok(())
 }
 
-fn validate_snippets(start_options: &StartOptions) -> anyhow::Result<()> {
-    let snippets = load_snippets(start_options)?;
-    tracing::info!("Successful. Total: {}", snippets.len());
+fn language_id_from_path(path: &std::path::Path) -> String {
+    if path.file_name().and_then(|f| f.to_str()) == Some("COMMIT_EDITMSG") {
+        return "git-commit".to_string();
+    }
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("md") => "markdown",
+        Some("rs") => "rust",
+        Some("py") => "python",
+        Some("js") => "javascript",
+        Some("ts") => "typescript",
+        Some("tex") => "latex",
+        Some("typ") => "typst",
+        Some("bib") => "bibtex",
+        Some("sh") => "bash",
+        Some(ext) => ext,
+        None => "text",
+    }
+    .to_string()
+}
+
+async fn complete(start_options: &StartOptions, args: &[String]) -> anyhow::Result<()> {
+    let mut file = None;
+    let mut line = None;
+    let mut col = None;
+    let mut language_id = None;
+    let mut format = "plain".to_string();
+    let mut from_stdin = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Value for {arg} required"))
+        };
+        match arg.as_str() {
+            "--file" => file = Some(std::path::PathBuf::from(value()?)),
+            "--line" => line = Some(value()?.parse::<u32>()?),
+            "--col" => col = Some(value()?.parse::<u32>()?),
+            "--language" => language_id = Some(value()?),
+            "--format" => format = value()?,
+            "--stdin" => from_stdin = true,
+            _ => anyhow::bail!("Unknown argument: {arg}"),
+        }
+    }
+
+    let Some(file) = file else {
+        anyhow::bail!("--file required")
+    };
+    let (Some(line), Some(col)) = (line, col) else {
+        anyhow::bail!("--line and --col required")
+    };
+
+    let text = if from_stdin {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        std::fs::read_to_string(&file)?
+    };
+
+    let path = std::path::absolute(&file)?;
+    let uri = lsp_types::Url::from_file_path(&path)
+        .map_err(|_| anyhow::anyhow!("Failed to build uri from {path:?}"))?;
+
+    let (snippets, unicode_input, words) = load_sources(start_options);
+    let (_tx, mut state) = BackendState::new(
+        start_options.home_dir.clone(),
+        snippets,
+        unicode_input.bodies,
+        words,
+    )
+    .await;
+    state.set_unicode_input_info(unicode_input.info);
+
+    state.open_doc(lsp_types::DidOpenTextDocumentParams {
+        text_document: lsp_types::TextDocumentItem {
+            uri: uri.clone(),
+            language_id: language_id.unwrap_or_else(|| language_id_from_path(&path)),
+            version: 0,
+            text,
+        },
+    });
+
+    let items = state.completion_items(&lsp_types::CompletionParams {
+        text_document_position: lsp_types::TextDocumentPositionParams {
+            text_document: lsp_types::TextDocumentIdentifier { uri },
+            position: lsp_types::Position {
+                line: line.saturating_sub(1),
+                character: col.saturating_sub(1),
+            },
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: None,
+    })?;
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string(&items)?),
+        "plain" => {
+            for item in items {
+                println!("{}", item.label);
+            }
+        }
+        _ => anyhow::bail!("Unsupported format: {format}"),
+    }
+
+    Ok(())
+}
+
+fn snippet_usage(start_options: &StartOptions, args: &[String]) -> anyhow::Result<()> {
+    let path = &start_options.snippets_usage_path;
+    if args.iter().any(|arg| arg == "--reset") {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        println!("Snippets usage is reset");
+        return Ok(());
+    }
+
+    let usage = SnippetUsage::load(path)?;
+    for (prefix, count) in usage.top() {
+        println!("{count}\t{prefix}");
+    }
+    Ok(())
+}
+
+fn accepted(start_options: &StartOptions, args: &[String]) -> anyhow::Result<()> {
+    let path = &start_options.acceptance_path;
+    if args.iter().any(|arg| arg == "--reset") {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        println!("Acceptance stats are reset");
+        return Ok(());
+    }
+
+    let stats = AcceptanceStats::load(path)?;
+    for (workspace, language, label, count) in stats.top() {
+        println!("{count}\t{workspace}\t{language}\t{label}");
+    }
+    Ok(())
+}
+
+fn snippet_add(start_options: &StartOptions, args: &[String]) -> anyhow::Result<()> {
+    let mut prefix = None;
+    let mut scope = Vec::new();
+    let mut description = None;
+    let mut body = None;
+    let mut when = None;
+    let mut force = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Value for {arg} required"))
+        };
+        match arg.as_str() {
+            "--prefix" => prefix = Some(value()?),
+            "--scope" => scope.extend(value()?.split(',').map(|s| s.trim().to_string())),
+            "--description" => description = Some(value()?),
+            "--body" => body = Some(value()?),
+            "--when" => when = Some(value()?),
+            "--force" => force = true,
+            _ => anyhow::bail!("Unknown argument: {arg}"),
+        }
+    }
+
+    let Some(prefix) = prefix else {
+        anyhow::bail!("--prefix required")
+    };
+
+    let body = match body.as_deref() {
+        Some("-") => {
+            let mut body = String::new();
+            std::io::stdin().read_to_string(&mut body)?;
+            body
+        }
+        Some(body) => body.to_string(),
+        None => {
+            let path = std::env::temp_dir().join(format!("scls-snippet-{prefix}.txt"));
+            std::fs::write(&path, "")?;
+            let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+            let status = std::process::Command::new(editor).arg(&path).status()?;
+            if !status.success() {
+                anyhow::bail!("Editor exited with {status}");
+            }
+            let body = std::fs::read_to_string(&path)?;
+            let _ = std::fs::remove_file(&path);
+            body
+        }
+    };
+    // drop trailing newline added by stdin or editor
+    let body = body.strip_suffix('\n').unwrap_or(&body).to_string();
+    if body.is_empty() {
+        anyhow::bail!("Empty snippet body");
+    }
+
+    // use scope as file name to follow snippets dir convention
+    let (file_name, scope) = match scope.as_slice() {
+        [single] => (format!("{single}.toml"), None),
+        [] => ("snippets.toml".to_string(), None),
+        _ => ("snippets.toml".to_string(), Some(scope)),
+    };
+    let path = start_options
+        .user_snippets_path()
+        .ok_or_else(|| anyhow::anyhow!("Snippets path is not set"))?
+        .join(file_name);
+
+    append_snippet(
+        &path,
+        Snippet {
+            scope,
+            prefix,
+            body,
+            description,
+            when,
+            ..Default::default()
+        },
+        force,
+    )?;
+
+    tracing::info!("Snippet added to {path:?}");
+    Ok(())
+}
+
+fn validate_snippets(start_options: &StartOptions, args: &[String]) -> anyhow::Result<()> {
+    let format = match args {
+        [] => "plain",
+        [flag, format] if flag == "--format" => format.as_str(),
+        _ => anyhow::bail!("Unknown arguments: {args:?}"),
+    };
+
+    let findings = validate::validate_snippets(start_options)?;
+    let failed = findings
+        .iter()
+        .any(|f| matches!(f.kind, FindingKind::ParseError | FindingKind::InvalidWhen));
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&findings)?),
+        "plain" => {
+            for finding in &findings {
+                let position = match (finding.line, finding.column) {
+                    (Some(line), Some(column)) => format!(":{line}:{column}"),
+                    _ => String::new(),
+                };
+                tracing::warn!("{:?}{position}: {}", finding.file, finding.message);
+            }
+            if !failed {
+                let snippets = load_snippets(start_options)?;
+                tracing::info!("Successful. Total: {}", snippets.len());
+            }
+        }
+        _ => anyhow::bail!("Unknown format: {format}"),
+    }
+
+    if failed {
+        anyhow::bail!("Invalid snippets");
+    }
+    Ok(())
+}
+
+fn search_snippets(start_options: &StartOptions, args: &[String]) -> anyhow::Result<()> {
+    let query = args.join(" ");
+    if query.is_empty() {
+        anyhow::bail!("Query required");
+    }
+
+    for m in search::search_snippets(start_options, &query)? {
+        let scope = m
+            .snippet
+            .scope
+            .map(|s| s.join(","))
+            .unwrap_or_else(|| "*".to_string());
+        let description = m
+            .snippet
+            .description
+            .as_deref()
+            .and_then(|d| d.lines().find(|l| !l.trim().is_empty()))
+            .unwrap_or_default()
+            .to_string();
+        println!("{}\t{scope}\t{description}\t{}", m.snippet.prefix, m.source);
+    }
+    Ok(())
+}
+
+fn analyze(args: &[String]) -> anyhow::Result<()> {
+    let mut path = std::path::PathBuf::from(".");
+    let mut top = 20;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Value for {arg} required"))
+        };
+        match arg.as_str() {
+            "--path" => path = std::path::PathBuf::from(value()?),
+            "--top" => top = value()?.parse::<usize>()?,
+            _ => anyhow::bail!("Unknown argument: {arg}"),
+        }
+    }
+
+    let analysis = simple_completion_language_server::analyze::analyze(&path)?;
+
+    println!(
+        "Files: {}, unique words: {}",
+        analysis.files,
+        analysis.words.len()
+    );
+    println!("\nTop words (count, files):");
+    for (word, stats) in analysis.top(top) {
+        println!("  {word}\t{}\t{}", stats.count, stats.files);
+    }
+    println!("\nCandidate stop-words (found in most files):");
+    for (word, stats) in analysis.stop_words(top) {
+        println!("  {word}\t{}\t{}", stats.count, stats.files);
+    }
+    let exclude = analysis
+        .suggested_exclude(top)
+        .map(|w| format!("{w:?}"))
+        .collect::<Vec<_>>();
+    println!(
+        "\nSuggested config:\nwords_exclude = [{}]",
+        exclude.join(", ")
+    );
+
     Ok(())
 }
 
 fn validate_unicode_input(start_options: &StartOptions) -> anyhow::Result<()> {
     let unicode_input = load_unicode_input_from_path(&start_options.unicode_input_path)?;
-    tracing::info!("Successful. Total: {}", unicode_input.len());
+    tracing::info!("Successful. Total: {}", unicode_input.bodies.len());
     Ok(())
 }
 
+/// Remove flag from args, true when it was given
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let found = args.iter().position(|arg| arg == flag);
+    if let Some(i) = found {
+        args.remove(i);
+    }
+    found.is_some()
+}
+
+/// Remove option with its value from args
+fn take_option(args: &mut Vec<String>, option: &str) -> Option<String> {
+    let i = args.iter().position(|arg| arg == option)?;
+    args.remove(i);
+    (i < args.len()).then(|| args.remove(i))
+}
+
+// editors config subdirectories probed for snippets config, empty is config root itself
+const CONFIG_SUBDIRECTORIES: [&str; 4] = ["helix", "nvim", "kate", ""];
+
+/// Editor subdirectory of config root (`SCLS_CONFIG_SUBDIRECTORY` or first one with
+/// `snippets` or `external-snippets.toml`), `helix` when nothing is found
+fn editor_config_dir(config_root: &std::path::Path) -> std::path::PathBuf {
+    if let Ok(subdirectory) = std::env::var("SCLS_CONFIG_SUBDIRECTORY") {
+        return config_root.join(subdirectory);
+    }
+    CONFIG_SUBDIRECTORIES
+        .iter()
+        .map(|subdirectory| match *subdirectory {
+            "" => config_root.to_path_buf(),
+            subdirectory => config_root.join(subdirectory),
+        })
+        .find(|dir| dir.join("snippets").exists() || dir.join("external-snippets.toml").exists())
+        .unwrap_or_else(|| config_root.join("helix"))
+}
+
+/// Config, data and cache dirs. Overridden (`--config-dir` or `SCLS_CONFIG_DIR`) and portable
+/// (`--portable` or `SCLS_PORTABLE`, binary dir) config dir keeps all files, platform dirs aren't used
+fn base_dirs(
+    config_dir: Option<std::path::PathBuf>,
+    portable: bool,
+) -> (std::path::PathBuf, std::path::PathBuf, std::path::PathBuf) {
+    let config_dir = config_dir.or_else(|| {
+        portable.then(|| {
+            std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(std::path::Path::to_path_buf))
+                .expect("Unable to get binary dir for portable mode!")
+        })
+    });
+    if let Some(config_dir) = config_dir {
+        return (config_dir.clone(), config_dir.clone(), config_dir);
+    }
+    let strategy = choose_base_strategy().expect("Unable to find the config directory!");
+    (
+        editor_config_dir(&strategy.config_dir()),
+        strategy.data_dir(),
+        strategy.cache_dir(),
+    )
+}
+
 #[tokio::main]
 async fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
 
-    let strategy = choose_base_strategy().expect("Unable to find the config directory!");
-    let mut config_dir = strategy.config_dir();
-    config_dir.push("helix");
+    let config_dir_override = take_option(&mut args, "--config-dir")
+        .or_else(|| std::env::var("SCLS_CONFIG_DIR").ok())
+        .map(std::path::PathBuf::from);
+    let portable = take_flag(&mut args, "--portable") || std::env::var("SCLS_PORTABLE").is_ok();
+    let (config_dir, data_dir, cache_dir) = base_dirs(config_dir_override, portable);
+
+    let mut snippets_paths = Vec::new();
+    while let Some(path) = take_option(&mut args, "--snippets-path") {
+        snippets_paths.push(std::path::PathBuf::from(path));
+    }
+    if snippets_paths.is_empty() {
+        snippets_paths = match std::env::var_os("SNIPPETS_PATH") {
+            Some(paths) => std::env::split_paths(&paths).collect(),
+            None => vec![
+                data_dir
+                    .join("simple-completion-language-server")
+                    .join("snippets"),
+                config_dir.join("snippets"),
+            ],
+        };
+    }
 
     let start_options = StartOptions {
         home_dir: etcetera::home_dir()
@@ -140,38 +828,62 @@ async fn main() {
             .to_str()
             .expect("Unable to get home dir as string!")
             .to_string(),
-        snippets_path: std::env::var("SNIPPETS_PATH")
+        snippets_paths,
+        external_snippets_config_path: std::env::var("EXTERNAL_SNIPPETS_CONFIG")
             .map(std::path::PathBuf::from)
             .unwrap_or_else(|_| {
                 let mut filepath = config_dir.clone();
-                filepath.push("snippets");
+                filepath.push("external-snippets.toml");
                 filepath
             }),
-        external_snippets_config_path: std::env::var("EXTERNAL_SNIPPETS_CONFIG")
+        unicode_input_path: std::env::var("UNICODE_INPUT_PATH")
             .map(std::path::PathBuf::from)
             .unwrap_or_else(|_| {
                 let mut filepath = config_dir.clone();
-                filepath.push("external-snippets.toml");
+                filepath.push("unicode-input");
                 filepath
             }),
-        unicode_input_path: std::env::var("UNICODE_INPUT_PATH")
+        words_path: std::env::var("WORDS_PATH")
             .map(std::path::PathBuf::from)
             .unwrap_or_else(|_| {
                 let mut filepath = config_dir.clone();
-                filepath.push("unicode-input");
+                filepath.push(simple_completion_language_server::WORDS_FILENAME);
+                filepath
+            }),
+        snippets_usage_path: std::env::var("SNIPPETS_USAGE_PATH")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|_| {
+                let mut filepath = data_dir.clone();
+                filepath.push("simple-completion-language-server");
+                filepath.push("snippets-usage.json");
+                filepath
+            }),
+        acceptance_path: std::env::var("ACCEPTANCE_PATH")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|_| {
+                let mut filepath = data_dir.clone();
+                filepath.push("simple-completion-language-server");
+                filepath.push("acceptance.json");
+                filepath
+            }),
+        warm_cache_path: std::env::var("WARM_CACHE_PATH")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|_| {
+                let mut filepath = cache_dir.clone();
+                filepath.push("simple-completion-language-server");
+                filepath.push("warm-cache.json");
                 filepath
             }),
     };
 
     match args.len() {
+        // serve logging is initialized on its own
+        3 if args[1] == "--metrics-addr" => {
+            let addr = args[2].parse().expect("Failed to parse --metrics-addr");
+            serve(&start_options, Some(addr)).await
+        }
         2.. => {
-            tracing_subscriber::registry()
-                .with(tracing_subscriber::EnvFilter::new(
-                    std::env::var("RUST_LOG")
-                        .unwrap_or_else(|_| "info,simple-comletion-language-server=info".into()),
-                ))
-                .with(tracing_subscriber::fmt::layer())
-                .init();
+            init_logging(std::io::stderr);
 
             let cmd = args[1].parse::<String>().expect("command required");
 
@@ -181,16 +893,33 @@ async fn main() {
             }
 
             match cmd.as_str() {
-                "fetch-external-snippets" => fetch_external_snippets(&start_options)
+                "init" => init(&start_options, &args[2..]).expect("Failed to init config"),
+                "fetch-external-snippets" => fetch_external_snippets(&start_options, &args[2..])
                     .expect("Failed to fetch external snippets"),
-                "validate-snippets" => {
-                    validate_snippets(&start_options).expect("Failed to validate snippets")
+                "validate-snippets" => validate_snippets(&start_options, &args[2..])
+                    .expect("Failed to validate snippets"),
+                "search-snippets" => {
+                    search_snippets(&start_options, &args[2..]).expect("Failed to search snippets")
                 }
+                "analyze" => analyze(&args[2..]).expect("Failed to analyze"),
                 "validate-unicode-input" => validate_unicode_input(&start_options)
                     .expect("Failed to validate 'unicode input' config"),
+                "complete" => complete(&start_options, &args[2..])
+                    .await
+                    .expect("Failed to complete"),
+                "snippet" if args.get(2).map(String::as_str) == Some("add") => {
+                    snippet_add(&start_options, &args[3..]).expect("Failed to add snippet")
+                }
+                "snippet" if args.get(2).map(String::as_str) == Some("usage") => {
+                    snippet_usage(&start_options, &args[3..])
+                        .expect("Failed to read snippets usage")
+                }
+                "accepted" => {
+                    accepted(&start_options, &args[2..]).expect("Failed to read acceptance stats")
+                }
                 _ => help(),
             }
         }
-        _ => serve(&start_options).await,
+        _ => serve(&start_options, None).await,
     };
 }
@@ -0,0 +1,205 @@
+use ropey::Rope;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// limit of files to read on tags aggregation
+const MAX_NOTES: usize = 5000;
+
+/// Link reference definition like `[label]: https://example.com "title"`
+#[derive(Debug, PartialEq)]
+pub struct LinkReference {
+    pub label: String,
+    pub url: String,
+}
+
+/// Parse link reference definition line
+pub fn parse_link_reference(line: &str) -> Option<LinkReference> {
+    // up to 3 spaces of indentation
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let rest = trimmed.strip_prefix('[')?;
+    let (label, rest) = rest.split_once("]:")?;
+    if label.is_empty() || label.contains('[') || label.contains(']') {
+        return None;
+    }
+    let url = rest
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('<')
+        .trim_end_matches('>');
+    Some(LinkReference {
+        label: label.to_string(),
+        url: url.to_string(),
+    })
+}
+
+pub fn link_references(text: &Rope) -> Vec<LinkReference> {
+    text.lines()
+        .filter_map(|line| parse_link_reference(&line.to_string()))
+        .collect()
+}
+
+/// Collect `http(s)://` urls mentioned anywhere in the text
+pub fn urls(text: &Rope) -> Vec<String> {
+    let mut result = Vec::new();
+    for line in text.lines() {
+        let line = line.to_string();
+        let mut rest = line.as_str();
+        while let Some(idx) = rest.find("http") {
+            let candidate = &rest[idx..];
+            let end = candidate
+                .find(|ch: char| ch.is_whitespace() || matches!(ch, ')' | '>' | ']' | '"' | '\''))
+                .unwrap_or(candidate.len());
+            let url = &candidate[..end];
+            if (url.starts_with("http://") || url.starts_with("https://"))
+                && !result.iter().any(|u| u == url)
+            {
+                result.push(url.to_string());
+            }
+            rest = &candidate[end.max(4)..];
+        }
+    }
+    result
+}
+
+/// Leading frontmatter block of document, the rest of text is not converted
+pub fn frontmatter_head(text: &Rope) -> String {
+    let mut head = String::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = std::borrow::Cow::<str>::from(line);
+        let is_fence = line.trim_end() == "---";
+        if idx == 0 && !is_fence {
+            break;
+        }
+        head.push_str(&line);
+        if idx > 0 && is_fence {
+            break;
+        }
+    }
+    head
+}
+
+/// Lines of YAML frontmatter between leading `---` and closing `---`
+fn frontmatter(text: &str) -> Option<Vec<&str>> {
+    let mut lines = text.lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+    Some(lines.take_while(|line| line.trim_end() != "---").collect())
+}
+
+fn split_tags(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(|tag| tag.trim().trim_matches(|ch| ch == '"' || ch == '\''))
+        .filter(|tag| !tag.is_empty())
+}
+
+/// Tags of frontmatter like `tags: [a, b]`, `tags: a, b` or YAML list under `tags:`
+pub fn frontmatter_tags(text: &str) -> Vec<String> {
+    let Some(lines) = frontmatter(text) else {
+        return Vec::new();
+    };
+    let mut tags = Vec::new();
+    let mut in_tags = false;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("tags:") {
+            tags.extend(split_tags(value).map(String::from));
+            in_tags = true;
+            continue;
+        }
+        if in_tags {
+            if let Some(tag) = line.trim_start().strip_prefix("- ") {
+                tags.extend(split_tags(tag).map(String::from));
+                continue;
+            }
+        }
+        in_tags = false;
+    }
+    tags
+}
+
+/// Partial tag at cursor when cursor is on frontmatter `tags:` value
+pub fn frontmatter_tag_query<'a>(
+    text: &Rope,
+    line: usize,
+    line_prefix: &'a str,
+) -> Option<&'a str> {
+    if line == 0 || text.line(0).to_string().trim_end() != "---" {
+        return None;
+    }
+    // cursor must be inside frontmatter
+    for idx in 1..line {
+        if text.line(idx).to_string().trim_end() == "---" {
+            return None;
+        }
+    }
+
+    if let Some(value) = line_prefix.strip_prefix("tags:") {
+        let query = value.rsplit([',', '[']).next().unwrap_or(value);
+        return Some(query.trim_start().trim_start_matches(['"', '\'']));
+    }
+
+    let query = line_prefix.trim_start().strip_prefix("- ")?;
+    // list must belong to `tags:` key
+    for idx in (1..line).rev() {
+        let prev = text.line(idx).to_string();
+        if prev.starts_with("tags:") {
+            return Some(query.trim_start_matches(['"', '\'']));
+        }
+        if !prev.trim_start().starts_with("- ") {
+            return None;
+        }
+    }
+    None
+}
+
+/// Collect up to limit files with extension under dir, skipping hidden and build dirs
+pub fn collect_files(dir: &Path, extension: &str, limit: usize, result: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        if result.len() >= limit {
+            return;
+        }
+        let path = entry.path();
+        let hidden = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .map(|f| f.starts_with('.') || f == "node_modules" || f == "target")
+            .unwrap_or(true);
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, extension, limit, result);
+        } else if path.extension().and_then(|e| e.to_str()) == Some(extension) {
+            result.push(path);
+        }
+    }
+}
+
+/// Count frontmatter tags of markdown notes under dirs
+pub fn aggregate_tags(dirs: &[PathBuf]) -> HashMap<String, usize> {
+    let mut notes = Vec::new();
+    for dir in dirs {
+        collect_files(dir, "md", MAX_NOTES, &mut notes);
+    }
+    let mut counter = HashMap::new();
+    for note in notes {
+        let Ok(content) = std::fs::read_to_string(&note) else {
+            continue;
+        };
+        for tag in frontmatter_tags(&content) {
+            *counter.entry(tag).or_default() += 1;
+        }
+    }
+    counter
+}
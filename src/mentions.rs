@@ -0,0 +1,153 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+// CODEOWNERS locations relative to repository root
+pub const CODEOWNERS_PATHS: [&str; 3] = ["CODEOWNERS", ".github/CODEOWNERS", "docs/CODEOWNERS"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MentionSource {
+    #[serde(default = "default_trigger")]
+    pub trigger: String,
+    #[serde(default = "default_languages")]
+    pub languages: Vec<String>,
+    // lines of `handle Full Name`
+    pub people_path: Option<String>,
+    #[serde(default = "default_codeowners")]
+    pub codeowners: bool,
+}
+
+impl Default for MentionSource {
+    fn default() -> Self {
+        Self {
+            trigger: default_trigger(),
+            languages: default_languages(),
+            people_path: None,
+            codeowners: default_codeowners(),
+        }
+    }
+}
+
+fn default_trigger() -> String {
+    "@".to_string()
+}
+
+fn default_languages() -> Vec<String> {
+    vec!["markdown".to_string(), "git-commit".to_string()]
+}
+
+fn default_codeowners() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Person {
+    pub handle: String,
+    pub name: String,
+}
+
+/// Parse people file lines like `@jane Jane Doe`, `#` starts comment
+pub fn parse_people(text: &str) -> Vec<Person> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (handle, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let handle = handle.trim_start_matches('@');
+            (!handle.is_empty()).then(|| Person {
+                handle: handle.to_string(),
+                name: name.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Owners handles (without `@`) of CODEOWNERS rules like `/docs/ @jane @org/docs-team`
+pub fn parse_codeowners(text: &str) -> Vec<String> {
+    let mut handles: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        for owner in line.split_whitespace().skip(1) {
+            if let Some(handle) = owner.strip_prefix('@') {
+                if !handle.is_empty() && !handles.iter().any(|h| h == handle) {
+                    handles.push(handle.to_string());
+                }
+            }
+        }
+    }
+    handles
+}
+
+/// CODEOWNERS file of repository containing dir
+pub fn find_codeowners(dir: &Path) -> Option<PathBuf> {
+    for ancestor in dir.ancestors() {
+        if let Some(path) = CODEOWNERS_PATHS
+            .iter()
+            .map(|path| ancestor.join(path))
+            .find(|path| path.is_file())
+        {
+            return Some(path);
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+// file path with its modification time, None if it can't be read
+type FileStamp = (PathBuf, Option<u128>);
+
+/// People file and CODEOWNERS of repository, with modification times to know they are changed
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MentionFiles {
+    pub people: Option<FileStamp>,
+    pub codeowners: Option<FileStamp>,
+}
+
+impl MentionFiles {
+    /// Files of people path and CODEOWNERS of repository containing dir (if given)
+    pub fn new(people_path: Option<&Path>, dir: Option<&Path>) -> Self {
+        let stamp = |path: PathBuf| {
+            let mtime = crate::warm::mtime(&path);
+            (path, mtime)
+        };
+        Self {
+            people: people_path.map(|path| stamp(path.to_path_buf())),
+            codeowners: dir.and_then(find_codeowners).map(stamp),
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        [&self.people, &self.codeowners]
+            .into_iter()
+            .flatten()
+            .any(|(p, _)| p == path)
+    }
+
+    /// People of people file, then CODEOWNERS handles not listed there
+    pub fn load(&self) -> Vec<Person> {
+        let mut people = Vec::new();
+        if let Some((path, _)) = &self.people {
+            match std::fs::read_to_string(path) {
+                Ok(text) => people.extend(parse_people(&text)),
+                Err(e) => tracing::warn!("On read people file {path:?}: {e}"),
+            }
+        }
+        if let Some((path, _)) = &self.codeowners {
+            match std::fs::read_to_string(path) {
+                Ok(text) => {
+                    for handle in parse_codeowners(&text) {
+                        if !people.iter().any(|p| p.handle == handle) {
+                            people.push(Person {
+                                handle,
+                                name: String::new(),
+                            });
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("On read {path:?}: {e}"),
+            }
+        }
+        people
+    }
+}
@@ -0,0 +1,44 @@
+use crate::BackendRequest;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+
+/// Serve metrics in Prometheus text format on any path of a local HTTP port
+pub async fn serve(addr: SocketAddr, tx: mpsc::Sender<BackendRequest>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("Serve metrics on http://{}/metrics", listener.local_addr()?);
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            // request itself is not interesting, read only its head
+            let mut buf = [0; 1024];
+            if stream.read(&mut buf).await.is_err() {
+                return;
+            }
+
+            let (metrics_tx, metrics_rx) = oneshot::channel();
+            // busy backend is not loaded with metrics requests
+            let response = match tx
+                .try_send(BackendRequest::Metrics(metrics_tx))
+                .ok()
+                .map(|_| metrics_rx)
+            {
+                Some(rx) => match rx.await {
+                    Ok(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    ),
+                    Err(_) => "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                },
+                None => "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            };
+
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                tracing::warn!("Error on write metrics response: {e}");
+            }
+        });
+    }
+}
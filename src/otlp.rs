@@ -0,0 +1,218 @@
+use crate::logging::JsonVisitor;
+use serde_json::{Map, Value};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+// how often finished spans are exported
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+// finished spans kept while collector is unavailable
+const MAX_PENDING_SPANS: usize = 10_000;
+// export failures are logged once per interval while collector is unavailable
+const FAILURE_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Span data collected until span is closed
+struct SpanData {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    start: SystemTime,
+    attributes: Map<String, Value>,
+}
+
+/// Layer exporting closed spans to OpenTelemetry collector by OTLP/HTTP (JSON encoding)
+pub struct OtlpLayer {
+    spans: Arc<Mutex<Vec<Value>>>,
+}
+
+/// Random hex id of `bytes` length
+fn random_id(bytes: usize) -> String {
+    (0..bytes.div_ceil(8))
+        .map(|_| {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_u128(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos(),
+            );
+            format!("{:016x}", hasher.finish())
+        })
+        .collect::<String>()[..bytes * 2]
+        .to_string()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// Span fields as OTLP attributes
+fn attributes(fields: &Map<String, Value>) -> Vec<Value> {
+    fields
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::Bool(v) => serde_json::json!({ "boolValue": v }),
+                Value::Number(v) if v.is_f64() => serde_json::json!({ "doubleValue": v }),
+                Value::Number(v) => serde_json::json!({ "intValue": v.to_string() }),
+                Value::String(v) => serde_json::json!({ "stringValue": v }),
+                v => serde_json::json!({ "stringValue": v.to_string() }),
+            };
+            serde_json::json!({ "key": key, "value": value })
+        })
+        .collect()
+}
+
+/// OTLP/HTTP request body of spans
+pub fn export_request(spans: Vec<Value>) -> Value {
+    serde_json::json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [{
+                    "key": "service.name",
+                    "value": { "stringValue": env!("CARGO_PKG_NAME") }
+                }]
+            },
+            "scopeSpans": [{
+                "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans
+            }]
+        }]
+    })
+}
+
+/// POST body to plain `http://` endpoint
+async fn post(endpoint: &str, body: &[u8]) -> anyhow::Result<()> {
+    let Some(address) = endpoint.strip_prefix("http://") else {
+        anyhow::bail!("Only http:// endpoint is supported: {endpoint}");
+    };
+    let (host, base) = address.split_once('/').unwrap_or((address, ""));
+    let path = format!("/{}/v1/traces", base.trim_end_matches('/')).replace("//", "/");
+
+    let mut stream = tokio::net::TcpStream::connect(host).await?;
+    stream
+        .write_all(
+            format!(
+                "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    stream.write_all(body).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let status = String::from_utf8_lossy(&response)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    if !status.contains(" 2") {
+        anyhow::bail!("Unexpected collector response: {status}");
+    }
+    Ok(())
+}
+
+impl OtlpLayer {
+    /// Layer with background export of spans to endpoint (like `http://localhost:4318`)
+    pub fn new(endpoint: String) -> Self {
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let pending = spans.clone();
+        tokio::spawn(async move {
+            let mut last_failure_logged: Option<std::time::Instant> = None;
+            loop {
+                tokio::time::sleep(EXPORT_INTERVAL).await;
+                let batch = pending
+                    .lock()
+                    .map(|mut spans| std::mem::take(&mut *spans))
+                    .unwrap_or_default();
+                if batch.is_empty() {
+                    continue;
+                }
+                let body = export_request(batch).to_string();
+                // export task is out of spans, so its events are never exported themselves
+                match post(&endpoint, body.as_bytes()).await {
+                    Ok(()) => last_failure_logged = None,
+                    Err(e) => {
+                        if last_failure_logged.is_none_or(|t| t.elapsed() > FAILURE_LOG_INTERVAL) {
+                            tracing::warn!("Failed to export spans to {endpoint}: {e}");
+                            last_failure_logged = Some(std::time::Instant::now());
+                        }
+                    }
+                }
+            }
+        });
+        Self { spans }
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanData>()
+                .map(|data| (data.trace_id.clone(), data.span_id.clone()))
+        });
+        let mut attributes = Map::new();
+        attrs.record(&mut JsonVisitor(&mut attributes));
+        let data = SpanData {
+            trace_id: parent
+                .as_ref()
+                .map(|(trace_id, _)| trace_id.clone())
+                .unwrap_or_else(|| random_id(16)),
+            span_id: random_id(8),
+            parent_span_id: parent.map(|(_, span_id)| span_id),
+            start: SystemTime::now(),
+            attributes,
+        };
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(data) = extensions.get_mut::<SpanData>() {
+            values.record(&mut JsonVisitor(&mut data.attributes));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let extensions = span.extensions();
+        let Some(data) = extensions.get::<SpanData>() else {
+            return;
+        };
+        let mut otlp_span = serde_json::json!({
+            "traceId": data.trace_id,
+            "spanId": data.span_id,
+            "name": span.name(),
+            "kind": 1,
+            "startTimeUnixNano": unix_nanos(data.start),
+            "endTimeUnixNano": unix_nanos(SystemTime::now()),
+            "attributes": attributes(&data.attributes),
+        });
+        if let Some(parent_span_id) = &data.parent_span_id {
+            otlp_span["parentSpanId"] = parent_span_id.clone().into();
+        }
+        if let Ok(mut spans) = self.spans.lock() {
+            if spans.len() < MAX_PENDING_SPANS {
+                spans.push(otlp_span);
+            }
+        }
+    }
+}
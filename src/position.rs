@@ -0,0 +1,87 @@
+use ropey::RopeSlice;
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionTextEdit, InitializeParams, Position, PositionEncodingKind,
+};
+
+/// Choose position encoding offered by client, prefer char (utf-32) columns used internally
+pub fn negotiate(params: &InitializeParams) -> PositionEncodingKind {
+    let offered = params
+        .capabilities
+        .general
+        .as_ref()
+        .and_then(|g| g.position_encodings.as_ref());
+    match offered {
+        Some(e) if e.contains(&PositionEncodingKind::UTF32) => PositionEncodingKind::UTF32,
+        Some(e) if e.contains(&PositionEncodingKind::UTF8) => PositionEncodingKind::UTF8,
+        // mandatory one
+        _ => PositionEncodingKind::UTF16,
+    }
+}
+
+/// Convert column in encoding units to char column of the line
+pub fn to_char_column(line: RopeSlice, column: u32, encoding: &PositionEncodingKind) -> u32 {
+    let column = column as usize;
+    let chars = if *encoding == PositionEncodingKind::UTF16 {
+        line.try_utf16_cu_to_char(column)
+            .unwrap_or_else(|_| line.len_chars())
+    } else if *encoding == PositionEncodingKind::UTF8 {
+        line.try_byte_to_char(column)
+            .unwrap_or_else(|_| line.len_chars())
+    } else {
+        column
+    };
+    chars as u32
+}
+
+/// Convert char column of the line to column in encoding units
+pub fn from_char_column(line: RopeSlice, column: u32, encoding: &PositionEncodingKind) -> u32 {
+    let column = (column as usize).min(line.len_chars());
+    let units = if *encoding == PositionEncodingKind::UTF16 {
+        line.char_to_utf16_cu(column)
+    } else if *encoding == PositionEncodingKind::UTF8 {
+        line.char_to_byte(column)
+    } else {
+        return column as u32;
+    };
+    units as u32
+}
+
+/// Position of char index in text, with column in encoding units
+pub fn from_char_index(
+    text: &ropey::Rope,
+    idx: usize,
+    encoding: &PositionEncodingKind,
+) -> Position {
+    let line = text.char_to_line(idx);
+    let column = (idx - text.line_to_char(line)) as u32;
+    Position {
+        line: line as u32,
+        character: from_char_column(text.line(line), column, encoding),
+    }
+}
+
+/// Convert char columns of completion item edits to encoding units
+pub fn encode_item(item: &mut CompletionItem, text: &ropey::Rope, encoding: &PositionEncodingKind) {
+    let encode = |position: &mut Position| {
+        if let Some(line) = text.get_line(position.line as usize) {
+            position.character = from_char_column(line, position.character, encoding);
+        }
+    };
+    match &mut item.text_edit {
+        Some(CompletionTextEdit::Edit(edit)) => {
+            encode(&mut edit.range.start);
+            encode(&mut edit.range.end);
+        }
+        Some(CompletionTextEdit::InsertAndReplace(edit)) => {
+            encode(&mut edit.insert.start);
+            encode(&mut edit.insert.end);
+            encode(&mut edit.replace.start);
+            encode(&mut edit.replace.end);
+        }
+        None => {}
+    }
+    for edit in item.additional_text_edits.iter_mut().flatten() {
+        encode(&mut edit.range.start);
+        encode(&mut edit.range.end);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::logging::JsonVisitor;
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Spans recorder, active only while profile is recorded
+pub struct Profiler {
+    active: AtomicBool,
+    recording: Mutex<Option<Recording>>,
+}
+
+struct Recording {
+    started: Instant,
+    events: Vec<Value>,
+}
+
+pub static PROFILER: Profiler = Profiler {
+    active: AtomicBool::new(false),
+    recording: Mutex::new(None),
+};
+
+impl Profiler {
+    /// Start recording, false if already started
+    pub fn start(&self) -> bool {
+        let Ok(mut recording) = self.recording.lock() else {
+            return false;
+        };
+        if recording.is_some() {
+            return false;
+        }
+        *recording = Some(Recording {
+            started: Instant::now(),
+            events: Vec::new(),
+        });
+        self.active.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Stop recording, returns spans in Chrome trace event format
+    pub fn stop(&self) -> Option<Value> {
+        self.active.store(false, Ordering::Relaxed);
+        let recording = self.recording.lock().ok()?.take()?;
+        Some(serde_json::json!({ "traceEvents": recording.events }))
+    }
+
+    fn record(&self, name: &str, target: &str, phase: &str, args: Option<&Map<String, Value>>) {
+        if !self.active.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(mut recording) = self.recording.lock() else {
+            return;
+        };
+        let Some(recording) = recording.as_mut() else {
+            return;
+        };
+        // thread id as number, like `ThreadId(3)` -> 3
+        let tid = format!("{:?}", std::thread::current().id())
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>()
+            .parse::<u64>()
+            .unwrap_or_default();
+        let mut event = serde_json::json!({
+            "name": name,
+            "cat": target,
+            "ph": phase,
+            "ts": recording.started.elapsed().as_micros() as u64,
+            "pid": std::process::id(),
+            "tid": tid,
+        });
+        if let Some(args) = args {
+            event["args"] = Value::Object(args.clone());
+        }
+        recording.events.push(event);
+    }
+}
+
+/// Span fields, stored on span creation
+struct SpanArgs(Map<String, Value>);
+
+/// Layer feeding spans enter/exit to `PROFILER`
+pub struct ProfileLayer;
+
+impl<S> Layer<S> for ProfileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut args = Map::new();
+        attrs.record(&mut JsonVisitor(&mut args));
+        span.extensions_mut().insert(SpanArgs(args));
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let extensions = span.extensions();
+        PROFILER.record(
+            span.name(),
+            span.metadata().target(),
+            "B",
+            extensions.get::<SpanArgs>().map(|args| &args.0),
+        );
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        PROFILER.record(span.name(), span.metadata().target(), "E", None);
+    }
+}
+
+/// Temp file path for profile trace
+pub fn trace_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!(
+        "scls-profile-{}-{}.json",
+        std::process::id(),
+        chrono::Local::now().format("%Y%m%dT%H%M%S")
+    ))
+}
+
+/// Record spans for duration and write them to Chrome trace file (open by `chrome://tracing` or Perfetto)
+pub async fn record(duration: Duration, path: &std::path::Path) -> anyhow::Result<()> {
+    if !PROFILER.start() {
+        anyhow::bail!("Profile is already recording");
+    }
+    tokio::time::sleep(duration).await;
+    let trace = PROFILER
+        .stop()
+        .ok_or_else(|| anyhow::anyhow!("Profile recording is lost"))?;
+    std::fs::write(path, serde_json::to_vec(&trace)?)?;
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use crate::snippets::config::load_unicode_input_from_file;
+use crate::snippets::external::ExternalSnippets;
+use crate::snippets::validate::{validate_snippets, FindingKind};
+use crate::StartOptions;
+
+/// Quick check of config files on startup, returns found problems
+pub fn check_start_options(start_options: &StartOptions) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for snippets_path in &start_options.snippets_paths {
+        if snippets_path.is_dir() {
+            if let Err(e) = std::fs::read_dir(snippets_path) {
+                problems.push(format!(
+                    "Snippets dir {snippets_path:?} is not readable: {e}"
+                ));
+            }
+        }
+    }
+
+    match validate_snippets(start_options) {
+        Ok(findings) => problems.extend(
+            findings
+                .into_iter()
+                .filter(|f| matches!(f.kind, FindingKind::ParseError | FindingKind::InvalidWhen))
+                .map(|f| format!("Failed to parse snippets {:?}: {}", f.file, f.message)),
+        ),
+        Err(e) => problems.push(format!("Failed to read snippets: {e}")),
+    }
+
+    // broken files in unicode input dir are skipped on load, check them one by one
+    let unicode_input_path = &start_options.unicode_input_path;
+    let unicode_input_files = if unicode_input_path.is_dir() {
+        let mut files = std::fs::read_dir(unicode_input_path)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.is_file())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        files.sort();
+        files
+    } else if unicode_input_path.exists() {
+        vec![unicode_input_path.clone()]
+    } else {
+        Vec::new()
+    };
+    for path in unicode_input_files {
+        if let Err(e) = load_unicode_input_from_file(&path) {
+            problems.push(format!("Failed to parse unicode input {path:?}: {e}"));
+        }
+    }
+
+    let path = &start_options.external_snippets_config_path;
+    let sources = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| toml::from_str::<ExternalSnippets>(&content).ok())
+        .map(|config| config.sources)
+        .unwrap_or_default();
+    if !sources.is_empty() {
+        let git = std::process::Command::new("git")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !git {
+            problems.push("git not found, it's required to fetch external snippets".to_string());
+        }
+        let base_path = path
+            .parent()
+            .map(|p| p.join("external-snippets"))
+            .unwrap_or_default();
+        for source in sources {
+            if source
+                .destination_path()
+                .is_ok_and(|p| !base_path.join(p).exists())
+            {
+                problems.push(format!(
+                    "External snippets '{}' are not fetched, run `simple-completion-language-server fetch-external-snippets`",
+                    source.name.as_ref().unwrap_or(&source.git)
+                ));
+            }
+        }
+    }
+
+    problems
+}
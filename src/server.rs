@@ -1,29 +1,213 @@
-use crate::{snippets::Snippet, BackendRequest, BackendResponse, BackendState};
+use crate::{
+    snippets::{Snippet, WordEntry},
+    BackendRequest, BackendResponse, BackendState, LoadedSources, COMPLETION_ACCEPTED_COMMAND,
+    SNIPPET_ACCEPTED_COMMAND,
+};
+use serde::Deserialize;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::{mpsc, oneshot};
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-#[derive(Debug)]
+/// Minimal level of log messages forwarded to client by `window/logMessage`
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum ClientLogLevel {
+    Info = 0,
+    Warn = 1,
+    Error = 2,
+    Off = 3,
+}
+
+/// Record tracing spans for N seconds (argument, 10 by default) to Chrome trace file
+pub const PROFILE_COMMAND: &str = "scls.profile";
+
+/// Flip feature flag by name (`{"feature": "paths"}` argument) until restart or configuration change
+pub const TOGGLE_FEATURE_COMMAND: &str = "scls.toggleFeature";
+
+/// Reload snippets, unicode input and words from disk
+pub const RELOAD_SNIPPETS_COMMAND: &str = "scls.reloadSnippets";
+
+/// Show completion stats summary by message
+pub const SHOW_STATS_COMMAND: &str = "scls.showStats";
+
+/// Insert unicode input by name fragment (first argument) at position (document uri and position arguments)
+pub const INSERT_UNICODE_COMMAND: &str = "scls.insertUnicode";
+
+// files cached by backend, changes are reported by client
+const WATCHED_FILES: [&str; 4] = [
+    "**/*.bib",
+    "**/.scls-words",
+    "**/.scls.toml",
+    "**/CODEOWNERS",
+];
+
+// clients known to send whole text on each change
+const FULL_SYNC_CLIENTS: [&str; 1] = ["acme-lsp"];
+
+/// Partial result of request, sent by `$/progress` notification
+enum PartialResult {}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PartialResultParams {
+    token: ProgressToken,
+    value: Vec<CompletionItem>,
+}
+
+impl notification::Notification for PartialResult {
+    type Params = PartialResultParams;
+    const METHOD: &'static str = "$/progress";
+}
+
+/// Response of `scls/ping` health-check request
+#[derive(Debug, Serialize)]
+pub struct PingResponse {
+    pub version: &'static str,
+    pub uptime_secs: u64,
+    pub features: Vec<&'static str>,
+    pub queue_depth: usize,
+}
+
 pub struct Backend {
     client: Client,
-    tx: mpsc::UnboundedSender<BackendRequest>,
+    tx: mpsc::Sender<BackendRequest>,
+    client_log_level: AtomicU8,
+    // client supports dynamic registration of watched files
+    watch_files: AtomicBool,
+    // config problems found on startup
+    problems: Vec<String>,
+    // client supports server initiated `$/progress`
+    work_done_progress: AtomicBool,
+    // sources are loaded in background after initialization
+    load_sources: Option<SourcesLoader>,
+    // files of loaded sources, watched to reload them
+    sources_paths: Vec<std::path::PathBuf>,
+    started: std::time::Instant,
     _task: tokio::task::JoinHandle<()>,
 }
 
 impl Backend {
+    fn forward_log(&self, level: ClientLogLevel) -> bool {
+        self.client_log_level.load(Ordering::Relaxed) <= level as u8
+    }
+    fn update_client_log_level(&self, settings: Option<&serde_json::Value>) {
+        let Some(value) = settings.and_then(|s| s.get("client_log_level")) else {
+            return;
+        };
+        match serde_json::from_value::<ClientLogLevel>(value.clone()) {
+            Ok(level) => self.client_log_level.store(level as u8, Ordering::Relaxed),
+            Err(e) => tracing::error!("Invalid client_log_level: {e}"),
+        }
+    }
     async fn log_info(&self, message: &str) {
         tracing::info!(message);
-        self.client.log_message(MessageType::INFO, message).await;
+        if self.forward_log(ClientLogLevel::Info) {
+            self.client.log_message(MessageType::INFO, message).await;
+        }
     }
     async fn log_err(&self, message: &str) {
         tracing::error!(message);
-        self.client.log_message(MessageType::ERROR, message).await;
+        if self.forward_log(ClientLogLevel::Error) {
+            self.client.log_message(MessageType::ERROR, message).await;
+        }
+    }
+    /// Report startup and settings problems by single message
+    async fn self_check(&self) {
+        let mut problems = self.problems.clone();
+        let (tx, rx) = oneshot::channel();
+        if self
+            .send_request(BackendRequest::SelfCheck(tx))
+            .await
+            .is_ok()
+        {
+            problems.extend(rx.await.unwrap_or_default());
+        }
+        report_problems(&self.client, problems).await;
+    }
+    /// Ask client to report changes of files cached by backend
+    async fn register_watched_files(&self) {
+        let sources_globs = self
+            .load_sources
+            .is_some()
+            .then_some(&self.sources_paths)
+            .into_iter()
+            .flatten()
+            .flat_map(|path| {
+                [
+                    path.to_string_lossy().into_owned(),
+                    path.join("**").to_string_lossy().into_owned(),
+                ]
+            });
+        let watchers = WATCHED_FILES
+            .iter()
+            .map(|glob| glob.to_string())
+            .chain(sources_globs)
+            .map(|glob| FileSystemWatcher {
+                glob_pattern: GlobPattern::String(glob),
+                kind: None,
+            })
+            .collect();
+        let registration = Registration {
+            id: "scls-watched-files".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                watchers,
+            })
+            .ok(),
+        };
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            self.log_err(&format!("Failed to register watched files: {e}"))
+                .await;
+        }
+    }
+    /// Health-check, answered by backend to ensure it's responsive
+    async fn ping(&self) -> Result<PingResponse> {
+        // queued before ping
+        let queue_depth = crate::queue_depth(&self.tx);
+        let (tx, rx) = oneshot::channel();
+        self.send_request(BackendRequest::Ping(tx))
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        let (features, queued_ahead) = rx
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        Ok(PingResponse {
+            version: env!("CARGO_PKG_VERSION"),
+            uptime_secs: self.started.elapsed().as_secs(),
+            features,
+            queue_depth: queue_depth + queued_ahead,
+        })
     }
     async fn send_request(&self, request: BackendRequest) -> anyhow::Result<()> {
-        if self.tx.send(request).is_err() {
+        let request = match self.tx.try_send(request) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(request)) => request,
+            Err(TrySendError::Closed(_)) => {
+                self.log_err("error on send request").await;
+                anyhow::bail!("Failed to send request");
+            }
+        };
+
+        // backend is overloaded, drop completion instead of queueing it
+        if let BackendRequest::CompletionRequest((tx, _, _)) = request {
+            tracing::warn!("Backend queue is full, completion request is dropped");
+            let _ = tx.send(Ok(BackendResponse::CompletionResponse(
+                CompletionResponse::List(CompletionList {
+                    is_incomplete: true,
+                    items: Vec::new(),
+                }),
+            )));
+            return Ok(());
+        }
+
+        // documents changes are never dropped, wait for free slot
+        if self.tx.send(request).await.is_err() {
             self.log_err("error on send request").await;
             anyhow::bail!("Failed to send request");
         }
@@ -33,14 +217,74 @@ impl Backend {
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let position_encoding = crate::position::negotiate(&params);
+        self.update_client_log_level(params.initialization_options.as_ref());
+        let watch_files = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.did_change_watched_files)
+            .and_then(|w| w.dynamic_registration)
+            .unwrap_or_default();
+        self.watch_files.store(watch_files, Ordering::Relaxed);
+        let work_done_progress = params
+            .capabilities
+            .window
+            .as_ref()
+            .and_then(|w| w.work_done_progress)
+            .unwrap_or_default();
+        self.work_done_progress
+            .store(work_done_progress, Ordering::Relaxed);
+        let full_sync = params
+            .initialization_options
+            .as_ref()
+            .and_then(|o| o.get("full_sync"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or_else(|| detect_full_sync(&params));
+        let _ = self
+            .send_request(BackendRequest::Initialize(Box::new(params)))
+            .await;
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::INCREMENTAL,
+                position_encoding: Some(position_encoding),
+                // full content changes are handled in incremental mode too
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(if full_sync {
+                            TextDocumentSyncKind::FULL
+                        } else {
+                            TextDocumentSyncKind::INCREMENTAL
+                        }),
+                        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                        ..Default::default()
+                    },
                 )),
-                completion_provider: Some(CompletionOptions {
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: [
+                        SNIPPET_ACCEPTED_COMMAND,
+                        COMPLETION_ACCEPTED_COMMAND,
+                        INSERT_UNICODE_COMMAND,
+                        TOGGLE_FEATURE_COMMAND,
+                        RELOAD_SNIPPETS_COMMAND,
+                        SHOW_STATS_COMMAND,
+                    ]
+                    .into_iter()
+                    .chain(cfg!(feature = "profiling").then_some(PROFILE_COMMAND))
+                    .map(String::from)
+                    .collect(),
+                    ..Default::default()
+                }),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                document_link_provider: Some(DocumentLinkOptions {
                     resolve_provider: Some(false),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
+                completion_provider: Some(CompletionOptions {
+                    resolve_provider: Some(true),
                     trigger_characters: Some(vec![std::path::MAIN_SEPARATOR_STR.to_string()]),
                     ..CompletionOptions::default()
                 }),
@@ -52,41 +296,110 @@ impl LanguageServer for Backend {
 
     async fn initialized(&self, _: InitializedParams) {
         self.log_info("server initialized!").await;
+        if let Some(load_sources) = &self.load_sources {
+            tokio::spawn(load_in_background(
+                self.client.clone(),
+                self.tx.clone(),
+                load_sources.clone(),
+                self.work_done_progress.load(Ordering::Relaxed),
+            ));
+        }
+        self.self_check().await;
+        if self.watch_files.load(Ordering::Relaxed) {
+            self.register_watched_files().await;
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .send_request(BackendRequest::Shutdown(tx))
+            .await
+            .is_ok()
+            && rx.await.is_err()
+        {
+            self.log_err("error on shutdown").await;
+        }
         Ok(())
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        self.log_info(&format!("Did open: {}", params.text_document.uri.as_str()))
-            .await;
+        let message = format!("Did open: {}", params.text_document.uri.as_str());
+        // forward to backend before logging to keep order with next requests
         let _ = self.send_request(BackendRequest::NewDoc(params)).await;
+        self.log_info(&message).await;
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        tracing::debug!("Did save: {params:?}");
+        tracing::debug!(
+            method = "textDocument/didSave",
+            document = %params.text_document.uri,
+            "Did save: {params:?}"
+        );
         let _ = self.send_request(BackendRequest::SaveDoc(params)).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        tracing::debug!("Did change: {params:?}");
+        tracing::debug!(
+            method = "textDocument/didChange",
+            document = %params.text_document.uri,
+            "Did change: {params:?}"
+        );
         let _ = self.send_request(BackendRequest::ChangeDoc(params)).await;
     }
 
-    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
-        self.log_info(&format!("Did change configuration: {params:?}"))
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let sources_changed = params.changes.iter().any(|change| {
+            crate::uri_to_path(&change.uri).is_some_and(|path| {
+                self.sources_paths
+                    .iter()
+                    .any(|sources_path| path.starts_with(sources_path))
+            })
+        });
+        if sources_changed {
+            self.log_info("Sources files changed, reload").await;
+            let _ = self.send_request(BackendRequest::ReloadSnippets).await;
+        }
+        let _ = self
+            .send_request(BackendRequest::ChangeWatchedFiles(params))
             .await;
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let message = format!("Did change configuration: {params:?}");
+        self.update_client_log_level(Some(&params.settings));
         let _ = self
             .send_request(BackendRequest::ChangeConfiguration(params))
             .await;
+        self.log_info(&message).await;
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         tracing::debug!("Completion: {params:?}");
         let (tx, rx) = oneshot::channel::<anyhow::Result<BackendResponse>>();
 
-        self.send_request(BackendRequest::CompletionRequest((tx, params)))
+        // items of each source are sent by `$/progress` as soon as source is done
+        let (partial_tx, partial_task) =
+            match params.partial_result_params.partial_result_token.clone() {
+                Some(token) => {
+                    let (partial_tx, mut partial_rx) = mpsc::unbounded_channel();
+                    let client = self.client.clone();
+                    let task = tokio::spawn(async move {
+                        while let Some(items) = partial_rx.recv().await {
+                            client
+                                .send_notification::<PartialResult>(PartialResultParams {
+                                    token: token.clone(),
+                                    value: items,
+                                })
+                                .await;
+                        }
+                    });
+                    (Some(partial_tx), Some(task))
+                }
+                None => (None, None),
+            };
+
+        self.send_request(BackendRequest::CompletionRequest((tx, params, partial_tx)))
             .await
             .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
 
@@ -95,6 +408,11 @@ impl LanguageServer for Backend {
             return Err(tower_lsp::jsonrpc::Error::internal_error());
         };
 
+        // partial results must precede response
+        if let Some(task) = partial_task {
+            let _ = task.await;
+        }
+
         match result {
             Ok(BackendResponse::CompletionResponse(r)) => Ok(Some(r)),
             Err(e) => {
@@ -104,9 +422,388 @@ impl LanguageServer for Backend {
         }
     }
 
-    // mock completionItem/resolve
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        match params.command.as_str() {
+            SNIPPET_ACCEPTED_COMMAND => {
+                let Some(prefix) = params.arguments.first().and_then(|v| v.as_str()) else {
+                    return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                        "Snippet prefix expected",
+                    ));
+                };
+                let _ = self
+                    .send_request(BackendRequest::SnippetAccepted(prefix.to_string()))
+                    .await;
+                Ok(None)
+            }
+            COMPLETION_ACCEPTED_COMMAND => {
+                let mut arguments = params
+                    .arguments
+                    .into_iter()
+                    .map(|v| v.as_str().map(String::from));
+                let (Some(Some(workspace)), Some(Some(language)), Some(Some(label))) =
+                    (arguments.next(), arguments.next(), arguments.next())
+                else {
+                    return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                        "Workspace, language and label expected",
+                    ));
+                };
+                let _ = self
+                    .send_request(BackendRequest::CompletionAccepted((
+                        workspace, language, label,
+                    )))
+                    .await;
+                Ok(None)
+            }
+            INSERT_UNICODE_COMMAND => {
+                let mut arguments = params.arguments.into_iter();
+                let (Some(name), Some(uri), Some(position)) = (
+                    arguments.next().and_then(|v| v.as_str().map(String::from)),
+                    arguments
+                        .next()
+                        .and_then(|v| serde_json::from_value::<Url>(v).ok()),
+                    arguments
+                        .next()
+                        .and_then(|v| serde_json::from_value::<Position>(v).ok()),
+                ) else {
+                    return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                        "Character name, document uri and position expected",
+                    ));
+                };
+                let (tx, rx) = oneshot::channel();
+                self.send_request(BackendRequest::UnicodeByName((tx, name.clone())))
+                    .await
+                    .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+                let Some((_, body)) = rx.await.unwrap_or_default().into_iter().next() else {
+                    self.client
+                        .show_message(MessageType::WARNING, format!("No character named '{name}'"))
+                        .await;
+                    return Ok(None);
+                };
+                let edit = WorkspaceEdit {
+                    changes: Some(HashMap::from_iter([(
+                        uri,
+                        vec![TextEdit {
+                            range: Range {
+                                start: position,
+                                end: position,
+                            },
+                            new_text: body.clone(),
+                        }],
+                    )])),
+                    ..Default::default()
+                };
+                if let Err(e) = self.client.apply_edit(edit).await {
+                    self.log_err(&format!("Failed to insert character: {e}"))
+                        .await;
+                }
+                Ok(Some(serde_json::Value::String(body)))
+            }
+            TOGGLE_FEATURE_COMMAND => {
+                let Some(feature) = params
+                    .arguments
+                    .first()
+                    .and_then(|v| v.get("feature"))
+                    .and_then(|v| v.as_str())
+                else {
+                    return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                        "Feature name expected, like {\"feature\": \"paths\"}",
+                    ));
+                };
+                let (tx, rx) = oneshot::channel();
+                self.send_request(BackendRequest::ToggleFeature((tx, feature.to_string())))
+                    .await
+                    .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+                let enabled = rx
+                    .await
+                    .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?
+                    .map_err(|e| tower_lsp::jsonrpc::Error::invalid_params(e.to_string()))?;
+                let state = if enabled { "enabled" } else { "disabled" };
+                self.client
+                    .show_message(MessageType::INFO, format!("Feature {feature} is {state}"))
+                    .await;
+                Ok(Some(
+                    serde_json::json!({ "feature": feature, "enabled": enabled }),
+                ))
+            }
+            RELOAD_SNIPPETS_COMMAND => {
+                let _ = self.send_request(BackendRequest::ReloadSnippets).await;
+                Ok(None)
+            }
+            SHOW_STATS_COMMAND => {
+                let (tx, rx) = oneshot::channel();
+                self.send_request(BackendRequest::Stats(tx))
+                    .await
+                    .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+                let stats = rx
+                    .await
+                    .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+                self.client.show_message(MessageType::INFO, &stats).await;
+                Ok(Some(serde_json::Value::String(stats)))
+            }
+            #[cfg(feature = "profiling")]
+            PROFILE_COMMAND => {
+                let seconds = params
+                    .arguments
+                    .first()
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(10);
+                let path = crate::profile::trace_path();
+                let client = self.client.clone();
+                let result_path = path.clone();
+                tokio::spawn(async move {
+                    let (typ, message) = match crate::profile::record(
+                        std::time::Duration::from_secs(seconds),
+                        &path,
+                    )
+                    .await
+                    {
+                        Ok(()) => (MessageType::INFO, format!("Profile is written to {path:?}")),
+                        Err(e) => (MessageType::ERROR, format!("Failed to record profile: {e}")),
+                    };
+                    tracing::info!(message);
+                    client.show_message(typ, message).await;
+                });
+                Ok(Some(serde_json::json!({ "path": result_path })))
+            }
+            _ => Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "Unknown command: {}",
+                params.command
+            ))),
+        }
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_request(BackendRequest::DocumentHighlight((
+            tx,
+            params.text_document_position_params,
+        )))
+        .await
+        .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        let highlights = rx
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        Ok(Some(highlights))
+    }
+
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_request(BackendRequest::DocumentLink((tx, params.text_document.uri)))
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        let links = rx
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        Ok(Some(links))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_request(BackendRequest::CodeAction((tx, params)))
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        let actions = rx
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        Ok(Some(actions))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_request(BackendRequest::WorkspaceSymbol((tx, params.query)))
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        let symbols = rx
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        Ok(Some(symbols))
+    }
+
     async fn completion_resolve(&self, params: CompletionItem) -> Result<CompletionItem> {
-        Ok(params)
+        // only word items carry data to resolve
+        if params.data.is_none() {
+            return Ok(params);
+        }
+        let (tx, rx) = oneshot::channel();
+        self.send_request(BackendRequest::ResolveCompletion((tx, Box::new(params))))
+            .await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        rx.await
+            .map_err(|_| tower_lsp::jsonrpc::Error::internal_error())
+    }
+}
+
+/// Loads snippets, unicode input and words, runs on blocking pool after initialization
+/// and again when watched sources files are changed
+pub type SourcesLoader = std::sync::Arc<dyn Fn() -> LoadedSources + Send + Sync>;
+
+/// Server options besides loaded sources
+#[derive(Default)]
+pub struct ServerOptions {
+    // serve Prometheus metrics on the address
+    pub metrics_addr: Option<std::net::SocketAddr>,
+    // config problems found on startup, reported to client after initialization
+    pub problems: Vec<String>,
+    // accepted snippets counters, used to rank snippets
+    pub snippets_usage_path: Option<std::path::PathBuf>,
+    // accepted completions counters, used to rank items of any source
+    pub acceptance_path: Option<std::path::PathBuf>,
+    // words of last session documents, saved on shutdown
+    pub warm_cache_path: Option<std::path::PathBuf>,
+    // server starts with given (empty) sources, these are swapped in when loaded
+    pub load_sources: Option<SourcesLoader>,
+    // snippets dirs, external snippets config and unicode input dir, sources are reloaded on their changes
+    pub sources_paths: Vec<std::path::PathBuf>,
+}
+
+/// Show config problems to user by single message
+/// Client without incremental sync: known by name or not declaring document synchronization
+fn detect_full_sync(params: &InitializeParams) -> bool {
+    let known = params
+        .client_info
+        .as_ref()
+        .is_some_and(|info| FULL_SYNC_CLIENTS.contains(&info.name.as_str()));
+    let no_synchronization = params
+        .capabilities
+        .text_document
+        .as_ref()
+        .and_then(|t| t.synchronization.as_ref())
+        .is_none();
+    known || no_synchronization
+}
+
+async fn report_problems(client: &Client, problems: Vec<String>) {
+    if problems.is_empty() {
+        return;
+    }
+    for problem in &problems {
+        tracing::warn!("Self-check: {problem}");
+    }
+    client
+        .show_message(
+            MessageType::WARNING,
+            format!(
+                "simple-completion-language-server: {} problem(s) found: {}",
+                problems.len(),
+                problems.join("; ")
+            ),
+        )
+        .await;
+}
+
+/// Load sources on blocking pool and swap them into backend, loading is reported by `$/progress`
+async fn load_in_background(
+    client: Client,
+    tx: mpsc::Sender<BackendRequest>,
+    load_sources: SourcesLoader,
+    work_done_progress: bool,
+) {
+    let token = ProgressToken::String("scls/loadSources".to_string());
+    let progress = work_done_progress
+        && client
+            .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .is_ok();
+    let report = |value: WorkDoneProgress| {
+        let client = client.clone();
+        let token = token.clone();
+        async move {
+            if progress {
+                client
+                    .send_notification::<notification::Progress>(ProgressParams {
+                        token,
+                        value: ProgressParamsValue::WorkDone(value),
+                    })
+                    .await;
+            }
+        }
+    };
+
+    report(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+        title: "Loading snippets and words".to_string(),
+        ..Default::default()
+    }))
+    .await;
+
+    let now = std::time::Instant::now();
+    let mut sources = match tokio::task::spawn_blocking(move || load_sources()).await {
+        Ok(sources) => sources,
+        Err(e) => {
+            tracing::error!("Error on load sources: {e}");
+            report(WorkDoneProgress::End(WorkDoneProgressEnd {
+                message: Some("failed".to_string()),
+            }))
+            .await;
+            return;
+        }
+    };
+    let message = format!(
+        "{} snippets, {} unicode inputs, {} words",
+        sources.snippets.len(),
+        sources.unicode_input.len(),
+        sources.words.len()
+    );
+    tracing::info!("Loaded {message} in {:?}", now.elapsed());
+    let problems = std::mem::take(&mut sources.problems);
+    if tx
+        .send(BackendRequest::LoadedSources(Box::new(sources)))
+        .await
+        .is_err()
+    {
+        tracing::error!("Error on send loaded sources");
+    }
+
+    report(WorkDoneProgress::End(WorkDoneProgressEnd {
+        message: Some(message),
+    }))
+    .await;
+    report_problems(&client, problems).await;
+}
+
+/// Ask user to trust workspace before its first command runs, answer is sent to backend
+async fn ask_workspace_trust(
+    client: Client,
+    tx: mpsc::Sender<BackendRequest>,
+    mut requests: mpsc::UnboundedReceiver<(String, String)>,
+) {
+    while let Some((workspace, command)) = requests.recv().await {
+        let allow = MessageActionItem {
+            title: "Allow".to_string(),
+            properties: HashMap::new(),
+        };
+        let deny = MessageActionItem {
+            title: "Deny".to_string(),
+            properties: HashMap::new(),
+        };
+        let answer = client
+            .show_message_request(
+                MessageType::WARNING,
+                format!(
+                    "simple-completion-language-server: allow running configured commands (like `{command}`) in {workspace}?"
+                ),
+                Some(vec![allow.clone(), deny]),
+            )
+            .await;
+        // dismissed prompt is not asked again in this session
+        let trusted = matches!(answer, Ok(Some(item)) if item == allow);
+        if tx
+            .send(BackendRequest::WorkspaceTrust((workspace, trusted)))
+            .await
+            .is_err()
+        {
+            break;
+        }
     }
 }
 
@@ -115,19 +812,64 @@ pub async fn start<I, O>(
     write: O,
     snippets: Vec<Snippet>,
     unicode_input: HashMap<String, String>,
+    words: Vec<WordEntry>,
     home_dir: String,
+    options: ServerOptions,
 ) where
     I: AsyncRead + Unpin,
     O: AsyncWrite,
 {
-    let (tx, backend_state) = BackendState::new(home_dir, snippets, unicode_input).await;
+    let (tx, mut backend_state) = BackendState::new(home_dir, snippets, unicode_input, words).await;
+    if let Some(path) = &options.snippets_usage_path {
+        backend_state.load_snippets_usage(path);
+    }
+    if let Some(path) = &options.acceptance_path {
+        backend_state.load_acceptance(path);
+    }
+    if let Some(path) = &options.warm_cache_path {
+        backend_state.set_warm_cache_path(path);
+    }
+    if let Some(load_sources) = &options.load_sources {
+        backend_state.set_sources_loader(load_sources.clone(), options.sources_paths.clone());
+    }
+    let trust_requests = backend_state.trust_requests();
+
+    if let Some(addr) = options.metrics_addr {
+        #[cfg(feature = "metrics")]
+        {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = crate::metrics::serve(addr, tx).await {
+                    tracing::error!("Error on serve metrics: {e}");
+                }
+            });
+        }
+        #[cfg(not(feature = "metrics"))]
+        tracing::warn!("Metrics on {addr} are disabled, build with `metrics` feature");
+    }
 
     let task = tokio::spawn(backend_state.start());
 
-    let (service, socket) = LspService::new(|client| Backend {
-        client,
-        tx,
-        _task: task,
-    });
+    let (service, socket) = LspService::build(|client| {
+        tokio::spawn(ask_workspace_trust(
+            client.clone(),
+            tx.clone(),
+            trust_requests,
+        ));
+        Backend {
+            client,
+            tx,
+            client_log_level: AtomicU8::new(ClientLogLevel::Info as u8),
+            watch_files: AtomicBool::new(false),
+            problems: options.problems,
+            work_done_progress: AtomicBool::new(false),
+            load_sources: options.load_sources,
+            sources_paths: options.sources_paths,
+            started: std::time::Instant::now(),
+            _task: task,
+        }
+    })
+    .custom_method("scls/ping", Backend::ping)
+    .finish();
     Server::new(read, write, socket).serve(service).await;
 }
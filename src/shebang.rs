@@ -0,0 +1,40 @@
+// language ids of documents without specific language, scope is inferred from shebang
+pub const GENERIC_LANGUAGES: [&str; 4] = ["plaintext", "text", "file", ""];
+
+// interpreter name -> language id
+const INTERPRETERS: [(&str, &str); 17] = [
+    ("sh", "bash"),
+    ("bash", "bash"),
+    ("zsh", "bash"),
+    ("dash", "bash"),
+    ("ksh", "bash"),
+    ("fish", "fish"),
+    ("python", "python"),
+    ("node", "javascript"),
+    ("deno", "typescript"),
+    ("ruby", "ruby"),
+    ("perl", "perl"),
+    ("php", "php"),
+    ("lua", "lua"),
+    ("awk", "awk"),
+    ("julia", "julia"),
+    ("Rscript", "r"),
+    ("pwsh", "powershell"),
+];
+
+/// Language id by script shebang line, like `#!/usr/bin/env python3`
+pub fn language_id(first_line: &str) -> Option<&'static str> {
+    let command = first_line.strip_prefix("#!")?;
+    let mut args = command.split_whitespace();
+    let mut interpreter = args.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // skip env options, like `env -S`
+        interpreter = args.find(|arg| !arg.starts_with('-'))?;
+    }
+    // strip version suffix, like python3.12
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    INTERPRETERS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, language_id)| *language_id)
+}
@@ -2,30 +2,176 @@ use crate::snippets::external::ExternalSnippets;
 use crate::snippets::vscode::VSSnippetsConfig;
 use crate::StartOptions;
 use anyhow::Result;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct SnippetsConfig {
+    #[serde(default)]
     pub snippets: Vec<Snippet>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Snippet {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub scope: Option<Vec<String>>,
     pub prefix: String,
     pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // regex on line text before typed word, snippet is offered on match only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    // whole file content, offered in empty documents only
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub file_template: bool,
+    // file name glob (like `main.rs` or `*.tex`) of file template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_glob: Option<String>,
+    // file snippet is loaded from
+    #[serde(skip)]
+    pub file: Option<std::path::PathBuf>,
+}
+
+/// Snippets of YAML file, documents (separated by `---`) are merged,
+/// each one is list of snippets (like TOML file) or single snippet
+pub fn parse_yaml_snippets(content: &str) -> Result<Vec<Snippet>, serde_yaml::Error> {
+    let mut snippets = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = serde_yaml::Value::deserialize(document)?;
+        if value.is_null() {
+            continue;
+        }
+        if value.get("snippets").is_some() {
+            snippets.extend(serde_yaml::from_value::<SnippetsConfig>(value)?.snippets);
+        } else {
+            snippets.push(serde_yaml::from_value::<Snippet>(value)?);
+        }
+    }
+    Ok(snippets)
+}
+
+/// Snippets file with scope override and external source name
+#[derive(Debug)]
+pub struct SnippetsFile {
+    pub path: std::path::PathBuf,
+    pub scope: Option<Vec<String>>,
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordEntry {
+    pub word: String,
     pub description: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct UnicodeInputConfig {
     #[serde(flatten)]
-    pub inner: HashMap<String, String>,
+    pub inner: HashMap<String, UnicodeInputBody>,
+}
+
+/// Unicode input value: single body, list of variants, described body or named variants
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum UnicodeInputBody {
+    Single(String),
+    Variants(Vec<String>),
+    Described {
+        body: String,
+        #[serde(flatten)]
+        info: UnicodeInputInfo,
+    },
+    Named(std::collections::BTreeMap<String, String>),
+}
+
+/// Aliases and description of unicode input entry, matched besides its prefix
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UnicodeInputInfo {
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub description: Option<String>,
+}
+
+/// Unicode input bodies by prefix, with aliases and descriptions of described ones
+#[derive(Debug, Default)]
+pub struct UnicodeInput {
+    pub bodies: HashMap<String, String>,
+    pub info: HashMap<String, UnicodeInputInfo>,
+}
+
+// emoji skin tone modifiers, used to name variants
+const SKIN_TONES: [(char, &str); 5] = [
+    ('\u{1F3FB}', "light"),
+    ('\u{1F3FC}', "medium_light"),
+    ('\u{1F3FD}', "medium"),
+    ('\u{1F3FE}', "medium_dark"),
+    ('\u{1F3FF}', "dark"),
+];
+
+impl UnicodeInputConfig {
+    /// Flatten variants to distinct prefixes, like `thumbsup_dark` or `family_2`
+    pub fn into_inner(self) -> UnicodeInput {
+        let mut result = HashMap::new();
+        let mut info = HashMap::new();
+        for (prefix, body) in self.inner {
+            match body {
+                UnicodeInputBody::Single(body) => {
+                    result.insert(prefix, body);
+                }
+                UnicodeInputBody::Described { body, info: i } => {
+                    info.insert(prefix.clone(), i);
+                    result.insert(prefix, body);
+                }
+                UnicodeInputBody::Variants(bodies) => {
+                    for (idx, body) in bodies.into_iter().enumerate() {
+                        let key = if idx == 0 {
+                            prefix.clone()
+                        } else if let Some((_, tone)) =
+                            SKIN_TONES.iter().find(|(ch, _)| body.contains(*ch))
+                        {
+                            format!("{prefix}_{tone}")
+                        } else {
+                            format!("{prefix}_{idx}")
+                        };
+                        result.insert(key, body);
+                    }
+                }
+                UnicodeInputBody::Named(bodies) => {
+                    for (name, body) in bodies {
+                        let key = if name == "default" {
+                            prefix.clone()
+                        } else {
+                            format!("{prefix}_{name}")
+                        };
+                        result.insert(key, body);
+                    }
+                }
+            }
+        }
+        UnicodeInput {
+            bodies: result,
+            info,
+        }
+    }
 }
 
 pub fn load_snippets(start_options: &StartOptions) -> Result<Vec<Snippet>> {
-    let mut snippets = load_snippets_from_path(&start_options.snippets_path, &None)?;
+    let mut snippets: Vec<Snippet> = Vec::new();
+    for snippets_path in &start_options.snippets_paths {
+        if !snippets_path.exists() {
+            tracing::debug!("Skip missed snippets path: {snippets_path:?}");
+            continue;
+        }
+        let loaded = load_snippets_from_path(snippets_path, &None)?;
+        // later paths override earlier ones
+        let overridden = loaded
+            .iter()
+            .map(|s| (s.prefix.clone(), s.scope.clone()))
+            .collect::<std::collections::HashSet<_>>();
+        snippets.retain(|s| !overridden.contains(&(s.prefix.clone(), s.scope.clone())));
+        snippets.extend(loaded);
+    }
 
     tracing::info!(
         "Try read config from: {:?}",
@@ -70,9 +216,127 @@ pub fn load_snippets(start_options: &StartOptions) -> Result<Vec<Snippet>> {
         }
     }
 
+    resolve_includes(&mut snippets);
+
     Ok(snippets)
 }
 
+// nested includes deeper than it are treated as cycle
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Replace `{{> prefix}}` in snippets bodies by body of snippet with the prefix,
+/// snippet of the same scope is preferred
+pub fn resolve_includes(snippets: &mut [Snippet]) {
+    let include = regex::Regex::new(r"\{\{>\s*([^}\s]+)\s*\}\}").expect("valid include regex");
+
+    fn resolve(
+        body: &str,
+        scope: &Option<Vec<String>>,
+        snippets: &[Snippet],
+        include: &regex::Regex,
+        depth: usize,
+    ) -> String {
+        include
+            .replace_all(body, |caps: &regex::Captures| {
+                let name = &caps[1];
+                let found = snippets
+                    .iter()
+                    .filter(|s| s.prefix == name)
+                    .min_by_key(|s| s.scope != *scope);
+                match found {
+                    Some(_) if depth >= MAX_INCLUDE_DEPTH => {
+                        tracing::warn!("Snippet include '{name}' is too deep (cycle?)");
+                        caps[0].to_string()
+                    }
+                    Some(found) => resolve(&found.body, &found.scope, snippets, include, depth + 1),
+                    None => {
+                        tracing::warn!("Snippet include '{name}' not found");
+                        caps[0].to_string()
+                    }
+                }
+            })
+            .to_string()
+    }
+
+    let resolved = snippets
+        .iter()
+        .map(|s| {
+            include
+                .is_match(&s.body)
+                .then(|| resolve(&s.body, &s.scope, snippets, &include, 0))
+        })
+        .collect::<Vec<_>>();
+    for (snippet, body) in snippets.iter_mut().zip(resolved) {
+        if let Some(body) = body {
+            snippet.body = body;
+        }
+    }
+}
+
+/// List snippets files of user snippets dir and external sources
+pub fn snippets_files(start_options: &StartOptions) -> Result<Vec<SnippetsFile>> {
+    fn collect(
+        path: std::path::PathBuf,
+        scope: &Option<Vec<String>>,
+        source: Option<&String>,
+        files: &mut Vec<SnippetsFile>,
+    ) {
+        let paths = if path.is_file() {
+            vec![path]
+        } else {
+            let Ok(entries) = std::fs::read_dir(&path) else {
+                return;
+            };
+            let mut paths = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+        files.extend(
+            paths
+                .into_iter()
+                .filter(|p| !is_wordlist(p))
+                .map(|path| SnippetsFile {
+                    path,
+                    scope: scope.clone(),
+                    source: source.cloned(),
+                }),
+        );
+    }
+
+    let mut files = Vec::new();
+    for snippets_path in &start_options.snippets_paths {
+        collect(snippets_path.clone(), &None, None, &mut files);
+    }
+
+    let path = &start_options.external_snippets_config_path;
+    if path.exists() {
+        let Some(base_path) = path.parent() else {
+            anyhow::bail!("Failed to get base path")
+        };
+        let base_path = base_path.join("external-snippets");
+        let content = std::fs::read_to_string(path)?;
+        let sources = toml::from_str::<ExternalSnippets>(&content)
+            .map(|sc| sc.sources)
+            .map_err(|e| anyhow::anyhow!(e))?;
+        for source in sources {
+            let source_name = source.name.as_ref().unwrap_or(&source.git);
+            for item in &source.paths {
+                collect(
+                    base_path.join(source.destination_path()?).join(&item.path),
+                    &item.scope,
+                    Some(source_name),
+                    &mut files,
+                );
+            }
+        }
+    }
+
+    Ok(files)
+}
+
 pub fn load_snippets_from_file(
     path: &std::path::PathBuf,
     scope: &Option<Vec<String>>,
@@ -108,12 +372,16 @@ pub fn load_snippets_from_file(
                     .collect()
             })
             .map_err(|e| anyhow::anyhow!(e)),
+        Some("yaml" | "yml") => parse_yaml_snippets(&content).map_err(|e| anyhow::anyhow!(e)),
         _ => {
             anyhow::bail!("Unsupported snipptes format: {path:?}")
         }
     };
 
-    let snippets = result?;
+    let mut snippets = result?;
+    for snippet in &mut snippets {
+        snippet.file = Some(path.clone());
+    }
 
     if let Some(scope) = scope {
         // add global scope to each snippet
@@ -121,8 +389,11 @@ pub fn load_snippets_from_file(
             .into_iter()
             .map(|mut s| {
                 s.scope = Some(if let Some(mut v) = s.scope {
-                    // TODO unique scope items
-                    v.extend(scope.clone());
+                    for item in &scope {
+                        if !v.contains(item) {
+                            v.push(item.clone());
+                        }
+                    }
                     v
                 } else {
                     scope.clone()
@@ -139,6 +410,9 @@ pub fn load_snippets_from_path(
     snippets_path: &std::path::PathBuf,
     scope: &Option<Vec<String>>,
 ) -> Result<Vec<Snippet>> {
+    if is_wordlist(snippets_path) {
+        return Ok(Vec::new());
+    }
     if snippets_path.is_file() {
         return load_snippets_from_file(snippets_path, scope);
     }
@@ -150,7 +424,7 @@ pub fn load_snippets_from_path(
                 let Ok(entry) = entry else { continue };
 
                 let path = entry.path();
-                if path.is_dir() {
+                if path.is_dir() || is_wordlist(&path) {
                     continue;
                 };
 
@@ -169,7 +443,7 @@ pub fn load_snippets_from_path(
     Ok(snippets)
 }
 
-pub fn load_unicode_input_from_file(path: &std::path::PathBuf) -> Result<HashMap<String, String>> {
+pub fn load_unicode_input_from_file(path: &std::path::PathBuf) -> Result<UnicodeInput> {
     tracing::info!("Try load 'unicode input' config from: {path:?}");
 
     let content = std::fs::read_to_string(path)?;
@@ -177,7 +451,7 @@ pub fn load_unicode_input_from_file(path: &std::path::PathBuf) -> Result<HashMap
     let result = match path.extension().and_then(|v| v.to_str()) {
         Some("toml") => toml::from_str::<UnicodeInputConfig>(&content)
             .map_err(|e| anyhow::anyhow!(e))
-            .map(|sc| sc.inner),
+            .map(UnicodeInputConfig::into_inner),
         _ => {
             anyhow::bail!("Unsupported snipptes format: {path:?}")
         }
@@ -186,14 +460,12 @@ pub fn load_unicode_input_from_file(path: &std::path::PathBuf) -> Result<HashMap
     result
 }
 
-pub fn load_unicode_input_from_path(
-    snippets_path: &std::path::PathBuf,
-) -> Result<HashMap<String, String>> {
+pub fn load_unicode_input_from_path(snippets_path: &std::path::PathBuf) -> Result<UnicodeInput> {
     if snippets_path.is_file() {
         return load_unicode_input_from_file(snippets_path);
     }
 
-    let mut result = HashMap::new();
+    let mut result = UnicodeInput::default();
     match std::fs::read_dir(snippets_path) {
         Ok(entries) => {
             for entry in entries {
@@ -205,7 +477,10 @@ pub fn load_unicode_input_from_path(
                 };
 
                 match load_unicode_input_from_file(&path) {
-                    Ok(r) => result.extend(r),
+                    Ok(r) => {
+                        result.bodies.extend(r.bodies);
+                        result.info.extend(r.info);
+                    }
                     Err(e) => {
                         tracing::error!("On read 'unicode input' config from {path:?}: {e}");
                         continue;
@@ -218,3 +493,118 @@ pub fn load_unicode_input_from_path(
 
     Ok(result)
 }
+
+/// Read word list: one term per line, optionally with description after a tab
+/// Plain word list of external source, one entry per line (`.txt` or gzip-compressed `.txt.gz`)
+pub fn is_wordlist(path: &std::path::Path) -> bool {
+    path.file_name()
+        .and_then(|v| v.to_str())
+        .is_some_and(|name| name.ends_with(".txt") || name.ends_with(".txt.gz"))
+}
+
+/// Word lists of external sources paths
+pub fn load_external_words(start_options: &StartOptions) -> Result<Vec<WordEntry>> {
+    let path = &start_options.external_snippets_config_path;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let Some(base_path) = path.parent() else {
+        anyhow::bail!("Failed to get base path")
+    };
+    let base_path = base_path.join("external-snippets");
+    let content = std::fs::read_to_string(path)?;
+    let sources = toml::from_str::<ExternalSnippets>(&content)
+        .map(|sc| sc.sources)
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    let mut words = Vec::new();
+    for source in sources {
+        for item in &source.paths {
+            let path = base_path.join(source.destination_path()?).join(&item.path);
+            let mut paths = if path.is_file() {
+                vec![path]
+            } else {
+                std::fs::read_dir(&path)
+                    .map(|entries| entries.filter_map(|e| e.ok().map(|e| e.path())).collect())
+                    .unwrap_or_default()
+            };
+            paths.sort();
+            for path in paths.into_iter().filter(|p| is_wordlist(p)) {
+                match load_words_from_file(&path) {
+                    Ok(r) => words.extend(r),
+                    Err(e) => tracing::error!("On read words from {path:?}: {e}"),
+                }
+            }
+        }
+    }
+    Ok(words)
+}
+
+pub fn load_words_from_file(path: &std::path::Path) -> Result<Vec<WordEntry>> {
+    tracing::info!("Try load words from: {path:?}");
+
+    let content = if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut content = String::new();
+        std::io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(std::fs::File::open(path)?),
+            &mut content,
+        )?;
+        content
+    } else {
+        std::fs::read_to_string(path)?
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let (word, description) = match line.split_once('\t') {
+                Some((word, description)) => (word, Some(description.trim().to_string())),
+                None => (line, None),
+            };
+            let word = word.trim();
+            if word.is_empty() || word.starts_with('#') {
+                return None;
+            }
+            Some(WordEntry {
+                word: word.to_string(),
+                description: description.filter(|d| !d.is_empty()),
+            })
+        })
+        .collect())
+}
+
+/// Append snippet to toml snippets file, fails on existed prefix with the same scope
+pub fn append_snippet(path: &std::path::PathBuf, snippet: Snippet, force: bool) -> Result<()> {
+    if path.exists() {
+        let existed = load_snippets_from_file(path, &None)?;
+        let file_scope = path
+            .file_stem()
+            .and_then(|v| v.to_str())
+            .filter(|v| *v != "snippets")
+            .map(|v| vec![v.to_string()]);
+        let scope = snippet.scope.as_ref().or(file_scope.as_ref());
+        let duplicate = existed
+            .iter()
+            .any(|s| s.prefix == snippet.prefix && s.scope.as_ref() == scope);
+        if duplicate && !force {
+            anyhow::bail!(
+                "Snippet with prefix '{}' already exists in {path:?}",
+                snippet.prefix
+            );
+        }
+    } else if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let content = toml::to_string(&SnippetsConfig {
+        snippets: vec![snippet],
+    })?;
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    std::io::Write::write_all(&mut file, format!("\n{content}").as_bytes())?;
+
+    Ok(())
+}
@@ -4,6 +4,7 @@ use std::str::FromStr;
 
 #[derive(Debug, Deserialize)]
 pub struct ExternalSnippets {
+    #[serde(default)]
     pub sources: Vec<SnippetSource>,
 }
 
@@ -0,0 +1,72 @@
+use crate::snippets::Snippet;
+use std::collections::HashMap;
+
+// (prefix, position in snippets list) sorted by prefix
+type PrefixIndex = Vec<(String, usize)>;
+
+/// Snippets grouped by scope at load time (plus global bucket for unscoped ones),
+/// each bucket is sorted by prefix for range lookups
+#[derive(Default)]
+pub struct SnippetIndex {
+    global: PrefixIndex,
+    scoped: HashMap<String, PrefixIndex>,
+}
+
+fn range<'a>(index: &'a PrefixIndex, prefix: &'a str) -> impl Iterator<Item = usize> + 'a {
+    let start = index.partition_point(|(p, _)| p.as_str() < prefix);
+    index[start..]
+        .iter()
+        .take_while(move |(p, _)| p.starts_with(prefix))
+        .map(|(_, i)| *i)
+}
+
+impl SnippetIndex {
+    pub fn new(snippets: &[Snippet]) -> Self {
+        let mut global = Vec::new();
+        let mut scoped: HashMap<String, PrefixIndex> = HashMap::new();
+
+        // file templates are offered in empty documents only
+        for (i, snippet) in snippets
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| !s.file_template)
+        {
+            match &snippet.scope {
+                Some(scope) if !scope.is_empty() => {
+                    for s in scope {
+                        scoped
+                            .entry(s.to_string())
+                            .or_default()
+                            .push((snippet.prefix.to_string(), i));
+                    }
+                }
+                _ => global.push((snippet.prefix.to_string(), i)),
+            }
+        }
+
+        global.sort();
+        for index in scoped.values_mut() {
+            index.sort();
+            // snippet could list the same scope twice
+            index.dedup();
+        }
+
+        tracing::info!(
+            "Snippets grouped: {} global, {} scopes",
+            global.len(),
+            scoped.len()
+        );
+
+        Self { global, scoped }
+    }
+
+    /// Positions of snippets starting with prefix for scope, in snippets list order
+    pub fn lookup(&self, prefix: &str, scope: &str) -> Vec<usize> {
+        let mut result = range(&self.global, prefix).collect::<Vec<_>>();
+        if let Some(index) = self.scoped.get(scope) {
+            result.extend(range(index, prefix));
+        }
+        result.sort_unstable();
+        result
+    }
+}
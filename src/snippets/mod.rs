@@ -1,5 +1,10 @@
 pub mod config;
 pub mod external;
+pub mod index;
+pub mod plain;
+pub mod search;
+pub mod usage;
+pub mod validate;
 pub mod vscode;
 
-pub use config::{Snippet, SnippetsConfig};
+pub use config::{Snippet, SnippetsConfig, SnippetsFile, WordEntry};
@@ -0,0 +1,83 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Snippet body as plain text for clients without snippet support:
+/// tab stops are dropped, placeholders, first choices and variables defaults are kept
+pub fn to_plain_text(body: &str) -> String {
+    let mut chars = body.chars().peekable();
+    let mut result = String::with_capacity(body.len());
+    parse(&mut chars, &mut result, false);
+    result
+}
+
+/// Parse text until end (or `}` closing placeholder when nested)
+fn parse(chars: &mut Peekable<Chars>, result: &mut String, nested: bool) {
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.peek() {
+                Some('$' | '}' | '\\') => result.extend(chars.next()),
+                _ => result.push(ch),
+            },
+            '}' if nested => return,
+            '$' => parse_dollar(chars, result),
+            _ => result.push(ch),
+        }
+    }
+}
+
+fn parse_dollar(chars: &mut Peekable<Chars>, result: &mut String) {
+    match chars.peek() {
+        // `$1` tab stop or `$NAME` variable
+        Some(c) if c.is_ascii_alphanumeric() || *c == '_' => {
+            while chars
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+            {
+                chars.next();
+            }
+        }
+        Some('{') => {
+            chars.next();
+            while chars
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+            {
+                chars.next();
+            }
+            match chars.next() {
+                // `${1:placeholder}` or `${NAME:default}`, may contain nested ones
+                Some(':') => parse(chars, result, true),
+                // `${1|first,second|}`
+                Some('|') => {
+                    let mut first = true;
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '|' if chars.peek() == Some(&'}') => {
+                                chars.next();
+                                break;
+                            }
+                            ',' => first = false,
+                            '\\' => {
+                                if let Some(c) = chars.next().filter(|_| first) {
+                                    result.push(c);
+                                }
+                            }
+                            c if first => result.push(c),
+                            _ => {}
+                        }
+                    }
+                }
+                // `${1}`, `${NAME}` or transform, skipped up to closing brace
+                Some('}') | None => {}
+                Some(_) => {
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        _ => result.push('$'),
+    }
+}
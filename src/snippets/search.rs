@@ -0,0 +1,95 @@
+use crate::snippets::config::{load_snippets_from_file, snippets_files};
+use crate::snippets::{Snippet, SnippetsFile};
+use crate::StartOptions;
+use anyhow::Result;
+
+pub struct SearchMatch {
+    pub snippet: Snippet,
+    /// Snippets file and external source name if any
+    pub source: String,
+    pub score: usize,
+}
+
+/// Fuzzy (subsequence) match score, case insensitive.
+/// Consecutive and word start matches are ranked higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut prev_matched = false;
+    let mut prev_char = None::<char>;
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let Some(q) = query.peek() else { break };
+        if *q == c {
+            score += 1;
+            if prev_matched {
+                score += 2;
+            }
+            if prev_char.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            prev_matched = true;
+            query.next();
+        } else {
+            prev_matched = false;
+        }
+        prev_char = Some(c);
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
+pub fn search_snippets(start_options: &StartOptions, query: &str) -> Result<Vec<SearchMatch>> {
+    let mut result = Vec::new();
+
+    for SnippetsFile {
+        path,
+        scope,
+        source,
+    } in snippets_files(start_options)?
+    {
+        let snippets = match load_snippets_from_file(&path, &scope) {
+            Ok(snippets) => snippets,
+            Err(e) => {
+                tracing::error!("On read snippets from {path:?}: {e}");
+                continue;
+            }
+        };
+        let source = match &source {
+            Some(name) => format!("{name}: {}", path.display()),
+            None => path.display().to_string(),
+        };
+
+        for snippet in snippets {
+            // prefer prefix matches over description and body
+            let score = [
+                fuzzy_score(query, &snippet.prefix).map(|s| s * 3),
+                snippet
+                    .description
+                    .as_deref()
+                    .and_then(|d| fuzzy_score(query, d))
+                    .map(|s| s * 2),
+                fuzzy_score(query, &snippet.body),
+            ]
+            .into_iter()
+            .flatten()
+            .max();
+
+            if let Some(score) = score {
+                result.push(SearchMatch {
+                    snippet,
+                    source: source.clone(),
+                    score,
+                });
+            }
+        }
+    }
+
+    result.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.snippet.prefix.cmp(&b.snippet.prefix))
+    });
+
+    Ok(result)
+}
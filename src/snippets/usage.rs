@@ -0,0 +1,61 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Accepted snippets counters by prefix, persisted as JSON
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SnippetUsage {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(default)]
+    pub counts: BTreeMap<String, u64>,
+}
+
+impl SnippetUsage {
+    /// Load counters from file, missing file is empty usage
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut usage = if path.exists() {
+            serde_json::from_str::<Self>(&std::fs::read_to_string(path)?)?
+        } else {
+            Self::default()
+        };
+        usage.path = Some(path.to_path_buf());
+        Ok(usage)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, prefix: &str) {
+        *self.counts.entry(prefix.to_string()).or_default() += 1;
+    }
+
+    /// Sort text placing used snippets first, most used at the top
+    pub fn sort_text(&self, prefix: &str) -> Option<String> {
+        let count = *self.counts.get(prefix)?;
+        Some(format!(
+            "!{:06}{prefix}",
+            999_999u64.saturating_sub(count.min(999_999))
+        ))
+    }
+
+    /// Counters sorted by usage, most used first
+    pub fn top(&self) -> Vec<(&str, u64)> {
+        let mut top = self
+            .counts
+            .iter()
+            .map(|(prefix, count)| (prefix.as_str(), *count))
+            .collect::<Vec<_>>();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        top
+    }
+}
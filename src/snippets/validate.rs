@@ -0,0 +1,260 @@
+use crate::snippets::config::{load_snippets_from_file, parse_yaml_snippets, snippets_files};
+use crate::snippets::vscode::VSSnippetsConfig;
+use crate::snippets::{SnippetsConfig, SnippetsFile};
+use crate::StartOptions;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Language ids known by editors, used to detect typos in snippets scopes
+pub const KNOWN_SCOPES: &[&str] = &[
+    "ada",
+    "astro",
+    "awk",
+    "bash",
+    "bibtex",
+    "c",
+    "c-sharp",
+    "clojure",
+    "cmake",
+    "common-lisp",
+    "cpp",
+    "css",
+    "csv",
+    "d",
+    "dart",
+    "dockerfile",
+    "elixir",
+    "elm",
+    "erlang",
+    "fish",
+    "fortran",
+    "gleam",
+    "git-commit",
+    "go",
+    "graphql",
+    "haskell",
+    "hcl",
+    "html",
+    "ini",
+    "java",
+    "javascript",
+    "jinja",
+    "json",
+    "jsx",
+    "julia",
+    "kotlin",
+    "latex",
+    "lua",
+    "make",
+    "markdown",
+    "nix",
+    "nim",
+    "ocaml",
+    "odin",
+    "perl",
+    "php",
+    "powershell",
+    "prolog",
+    "protobuf",
+    "python",
+    "r",
+    "racket",
+    "ruby",
+    "rust",
+    "scala",
+    "scheme",
+    "scss",
+    "sql",
+    "svelte",
+    "swift",
+    "text",
+    "toml",
+    "tsx",
+    "typescript",
+    "typst",
+    "vue",
+    "xml",
+    "yaml",
+    "zig",
+];
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    ParseError,
+    DuplicatePrefix,
+    UnknownScope,
+    InvalidWhen,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub file: PathBuf,
+    pub kind: FindingKind,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+/// Parse error position as (line, column), both 1-based
+fn parse_error(path: &PathBuf) -> Option<(String, Option<usize>, Option<usize>)> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return Some((e.to_string(), None, None)),
+    };
+    match path.extension().and_then(|v| v.to_str()) {
+        Some("toml") => toml::from_str::<SnippetsConfig>(&content).err().map(|e| {
+            let position = e.span().map(|span| {
+                let before = &content[..span.start];
+                let line = before.matches('\n').count() + 1;
+                let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+                (line, column)
+            });
+            (
+                e.message().to_string(),
+                position.map(|p| p.0),
+                position.map(|p| p.1),
+            )
+        }),
+        Some("json") => serde_json::from_str::<VSSnippetsConfig>(&content)
+            .err()
+            .map(|e| (e.to_string(), Some(e.line()), Some(e.column()))),
+        Some("yaml" | "yml") => parse_yaml_snippets(&content).err().map(|e| {
+            let location = e.location();
+            (
+                e.to_string(),
+                location.as_ref().map(|l| l.line()),
+                location.as_ref().map(|l| l.column()),
+            )
+        }),
+        _ => None,
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current.push((prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+pub fn validate_snippets(start_options: &StartOptions) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    // (prefix, scope) -> first file
+    let mut seen: HashMap<(String, Option<String>), PathBuf> = HashMap::new();
+
+    for SnippetsFile {
+        path: file, scope, ..
+    } in snippets_files(start_options)?
+    {
+        if let Some((message, line, column)) = parse_error(&file) {
+            findings.push(Finding {
+                file,
+                kind: FindingKind::ParseError,
+                message,
+                line,
+                column,
+            });
+            continue;
+        }
+
+        let snippets = match load_snippets_from_file(&file, &scope) {
+            Ok(snippets) => snippets,
+            Err(e) => {
+                findings.push(Finding {
+                    file,
+                    kind: FindingKind::ParseError,
+                    message: e.to_string(),
+                    line: None,
+                    column: None,
+                });
+                continue;
+            }
+        };
+
+        for snippet in snippets {
+            if let Some(Err(e)) = snippet.when.as_deref().map(regex::Regex::new) {
+                findings.push(Finding {
+                    file: file.clone(),
+                    kind: FindingKind::InvalidWhen,
+                    message: format!("Invalid `when` regex for prefix '{}': {e}", snippet.prefix),
+                    line: None,
+                    column: None,
+                });
+            }
+
+            let scopes = match &snippet.scope {
+                Some(scopes) => scopes.iter().cloned().map(Some).collect(),
+                None => vec![None],
+            };
+            for scope in scopes {
+                if let Some(scope) = scope
+                    .as_ref()
+                    .filter(|s| !KNOWN_SCOPES.contains(&s.as_str()))
+                {
+                    let suggestion = KNOWN_SCOPES
+                        .iter()
+                        .map(|known| (edit_distance(scope, known), known))
+                        .filter(|(distance, _)| *distance <= 2)
+                        .min()
+                        .map(|(_, known)| format!(", did you mean '{known}'?"))
+                        .unwrap_or_default();
+                    findings.push(Finding {
+                        file: file.clone(),
+                        kind: FindingKind::UnknownScope,
+                        message: format!(
+                            "Unknown scope '{scope}' for prefix '{}'{suggestion}",
+                            snippet.prefix
+                        ),
+                        line: None,
+                        column: None,
+                    });
+                }
+
+                let key = (snippet.prefix.clone(), scope.clone());
+                let search_path = |file: &PathBuf| {
+                    start_options
+                        .snippets_paths
+                        .iter()
+                        .position(|p| file.starts_with(p))
+                };
+                if let Some(first) = seen.get(&key) {
+                    // snippet of later search path overrides earlier one, it's expected
+                    if matches!(
+                        (search_path(first), search_path(&file)),
+                        (Some(a), Some(b)) if a != b
+                    ) {
+                        seen.insert(key, file.clone());
+                        continue;
+                    }
+                    findings.push(Finding {
+                        file: file.clone(),
+                        kind: FindingKind::DuplicatePrefix,
+                        message: format!(
+                            "Prefix '{}' for scope '{}' already defined in {first:?}",
+                            snippet.prefix,
+                            scope.as_deref().unwrap_or("*")
+                        ),
+                        line: None,
+                        column: None,
+                    });
+                } else {
+                    seen.insert(key, file.clone());
+                }
+            }
+        }
+    }
+
+    Ok(findings)
+}
@@ -21,6 +21,8 @@ pub struct VSCodeSnippet {
     pub prefix: Option<VSCodeSnippetValue>,
     pub body: VSCodeSnippetValue,
     pub description: Option<VSCodeSnippetValue>,
+    #[serde(default, rename = "isFileTemplate")]
+    pub is_file_template: bool,
 }
 
 impl VSCodeSnippet {
@@ -60,6 +62,8 @@ impl From<VSCodeSnippet> for Vec<Snippet> {
                     prefix,
                     body,
                     description,
+                    file_template: value.is_file_template,
+                    ..Default::default()
                 }]
             }
             Some(VSCodeSnippetValue::List(prefixes)) => prefixes
@@ -69,6 +73,8 @@ impl From<VSCodeSnippet> for Vec<Snippet> {
                     prefix,
                     body: body.clone(),
                     description: description.clone(),
+                    file_template: value.is_file_template,
+                    ..Default::default()
                 })
                 .collect(),
             None => Vec::new(),
@@ -0,0 +1,194 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+// how often sources latency summary is logged
+pub const SUMMARY_INTERVAL: Duration = Duration::from_secs(300);
+
+// upper bounds of source latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 7] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+
+// adaptive items limit of slow source is not tuned below
+pub const MIN_ADAPTIVE_ITEMS: usize = 5;
+
+/// Halve items limit of source slower than target, double it back (up to max) when well under it
+pub fn tune_limit(limit: usize, max: usize, elapsed: Duration, target: Duration) -> usize {
+    if elapsed > target {
+        (limit / 2).max(MIN_ADAPTIVE_ITEMS.min(max))
+    } else if elapsed * 2 < target {
+        (limit * 2).min(max)
+    } else {
+        limit
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SourceStats {
+    pub requests: usize,
+    pub items: usize,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    // requests count per latency bucket (not cumulative)
+    #[serde(skip)]
+    pub buckets: [usize; LATENCY_BUCKETS.len()],
+}
+
+/// Per-source completion latency aggregated over server lifetime
+#[derive(Debug)]
+pub struct CompletionStats {
+    pub sources: BTreeMap<&'static str, SourceStats>,
+    pub completion_requests: usize,
+    pub automata_hits: usize,
+    pub automata_misses: usize,
+    // completion requests served from results cache
+    pub completion_cache_hits: usize,
+    // completion requests answered empty as newer one of the same document was queued
+    pub completion_superseded: usize,
+    // max backend queue depth since the last summary
+    pub max_queue_depth: usize,
+    last_summary: Instant,
+}
+
+impl Default for CompletionStats {
+    fn default() -> Self {
+        Self {
+            sources: BTreeMap::new(),
+            completion_requests: 0,
+            automata_hits: 0,
+            automata_misses: 0,
+            completion_cache_hits: 0,
+            completion_superseded: 0,
+            max_queue_depth: 0,
+            last_summary: Instant::now(),
+        }
+    }
+}
+
+impl CompletionStats {
+    pub fn record(&mut self, source: &'static str, elapsed: Duration, items: usize) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let stats = self.sources.entry(source).or_default();
+        stats.requests += 1;
+        stats.items += items;
+        stats.total_ms += ms;
+        stats.max_ms = stats.max_ms.max(ms);
+        if let Some(i) = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| elapsed.as_secs_f64() <= *bound)
+        {
+            stats.buckets[i] += 1;
+        }
+    }
+
+    pub fn record_queue_depth(&mut self, depth: usize) {
+        self.max_queue_depth = self.max_queue_depth.max(depth);
+    }
+
+    /// Summary line like `words: avg 0.12ms max 1.30ms (42 requests, 120 items)`,
+    /// slowest sources first
+    pub fn summary(&self) -> String {
+        let mut sources = self.sources.iter().collect::<Vec<_>>();
+        sources.sort_by(|a, b| b.1.total_ms.total_cmp(&a.1.total_ms));
+        sources
+            .into_iter()
+            .map(|(source, s)| {
+                format!(
+                    "{source}: avg {:.2}ms max {:.2}ms ({} requests, {} items)",
+                    s.total_ms / s.requests.max(1) as f64,
+                    s.max_ms,
+                    s.requests,
+                    s.items,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// Summary to log if interval is passed since the last one
+    pub fn periodic_summary(&mut self) -> Option<String> {
+        if self.last_summary.elapsed() < SUMMARY_INTERVAL || self.sources.is_empty() {
+            return None;
+        }
+        self.last_summary = Instant::now();
+        let summary = format!(
+            "{} (max queue depth {})",
+            self.summary(),
+            self.max_queue_depth
+        );
+        self.max_queue_depth = 0;
+        Some(summary)
+    }
+
+    /// Counters and histograms in Prometheus text exposition format
+    pub fn prometheus(&self, docs: usize, evicted_docs: usize, queue_depth: usize) -> String {
+        let mut out = String::new();
+        out.push_str("# TYPE scls_completion_requests_total counter\n");
+        out.push_str(&format!(
+            "scls_completion_requests_total {}\n",
+            self.completion_requests
+        ));
+
+        out.push_str("# TYPE scls_source_latency_seconds histogram\n");
+        for (source, s) in &self.sources {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(s.buckets) {
+                cumulative += count;
+                out.push_str(&format!(
+                    "scls_source_latency_seconds_bucket{{source=\"{source}\",le=\"{bound}\"}} {cumulative}\n"
+                ));
+            }
+            out.push_str(&format!(
+                "scls_source_latency_seconds_bucket{{source=\"{source}\",le=\"+Inf\"}} {}\n",
+                s.requests
+            ));
+            out.push_str(&format!(
+                "scls_source_latency_seconds_sum{{source=\"{source}\"}} {}\n",
+                s.total_ms / 1000.0
+            ));
+            out.push_str(&format!(
+                "scls_source_latency_seconds_count{{source=\"{source}\"}} {}\n",
+                s.requests
+            ));
+        }
+
+        out.push_str("# TYPE scls_source_items_total counter\n");
+        for (source, s) in &self.sources {
+            out.push_str(&format!(
+                "scls_source_items_total{{source=\"{source}\"}} {}\n",
+                s.items
+            ));
+        }
+
+        out.push_str("# TYPE scls_automata_cache_hits_total counter\n");
+        out.push_str(&format!(
+            "scls_automata_cache_hits_total {}\n",
+            self.automata_hits
+        ));
+        out.push_str("# TYPE scls_automata_cache_misses_total counter\n");
+        out.push_str(&format!(
+            "scls_automata_cache_misses_total {}\n",
+            self.automata_misses
+        ));
+
+        out.push_str("# TYPE scls_completion_cache_hits_total counter\n");
+        out.push_str(&format!(
+            "scls_completion_cache_hits_total {}\n",
+            self.completion_cache_hits
+        ));
+        out.push_str("# TYPE scls_completion_superseded_total counter\n");
+        out.push_str(&format!(
+            "scls_completion_superseded_total {}\n",
+            self.completion_superseded
+        ));
+
+        out.push_str("# TYPE scls_queue_depth gauge\n");
+        out.push_str(&format!("scls_queue_depth {queue_depth}\n"));
+
+        out.push_str("# TYPE scls_documents gauge\n");
+        out.push_str(&format!("scls_documents{{state=\"resident\"}} {docs}\n"));
+        out.push_str(&format!(
+            "scls_documents{{state=\"evicted\"}} {evicted_docs}\n"
+        ));
+        out
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Synonyms from Moby (`word,synonym,...`) or OpenThesaurus (`synonym;synonym;...`) text files
+#[derive(Default)]
+pub struct Thesaurus {
+    senses: Vec<Vec<String>>,
+    index: HashMap<String, Vec<usize>>,
+}
+
+impl Thesaurus {
+    pub fn from_path(path: &std::path::Path) -> Result<Self> {
+        tracing::info!("Try load thesaurus from: {path:?}");
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&content))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut thesaurus = Self::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let is_synset = line.contains(';');
+            let words = line
+                .split(if is_synset { ';' } else { ',' })
+                .map(strip_annotations)
+                .filter(|w| !w.is_empty())
+                .collect::<Vec<_>>();
+            if words.len() < 2 {
+                continue;
+            }
+
+            let idx = thesaurus.senses.len();
+            // moby lists synonyms of the first word only
+            let keys = if is_synset { &words[..] } else { &words[..1] };
+            for key in keys {
+                thesaurus
+                    .index
+                    .entry(key.to_lowercase())
+                    .or_default()
+                    .push(idx);
+            }
+            thesaurus.senses.push(words);
+        }
+        thesaurus
+    }
+
+    /// Senses of the word
+    pub fn lookup<'a>(&'a self, word: &str) -> impl Iterator<Item = &'a [String]> + 'a {
+        self.index
+            .get(&word.to_lowercase())
+            .into_iter()
+            .flatten()
+            .map(|idx| self.senses[*idx].as_slice())
+    }
+}
+
+// drop `(ugs.)` like notes
+fn strip_annotations(word: &str) -> String {
+    let mut result = String::new();
+    let mut depth = 0;
+    for ch in word.chars() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = std::cmp::max(depth - 1, 0),
+            _ if depth == 0 => result.push(ch),
+            _ => {}
+        }
+    }
+    result.trim().to_string()
+}
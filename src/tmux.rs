@@ -0,0 +1,52 @@
+use crate::split_words;
+use std::collections::HashSet;
+use std::time::Duration;
+
+// how often other panes are captured, on completion request
+pub const CAPTURE_INTERVAL: Duration = Duration::from_secs(10);
+// limit of indexed panes words
+pub const MAX_WORDS: usize = 5000;
+// shorter words are not worth completing
+const MIN_WORD_CHARS: usize = 3;
+
+/// Visible text of tmux panes except the editor pane (`TMUX_PANE`)
+pub fn capture_panes(timeout: Duration) -> anyhow::Result<String> {
+    let dir = std::env::temp_dir();
+    let own_pane = std::env::var("TMUX_PANE").unwrap_or_default();
+    let panes = crate::command::run(&dir, "tmux list-panes -a -F '#{pane_id}'", timeout)?;
+    let mut text = String::new();
+    for pane in panes.lines().map(str::trim) {
+        // pane ids are like `%12`, never run anything else
+        if pane == own_pane
+            || !pane.starts_with('%')
+            || !pane[1..].chars().all(|c| c.is_ascii_digit())
+        {
+            continue;
+        }
+        match crate::command::run(
+            &dir,
+            &format!("tmux capture-pane -p -J -t '{pane}'"),
+            timeout,
+        ) {
+            Ok(output) => text.push_str(&output),
+            Err(e) => tracing::debug!("On capture tmux pane {pane}: {e}"),
+        }
+    }
+    Ok(text)
+}
+
+/// Unique words of captured text, most recent (bottom) lines first
+pub fn words(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    text.lines()
+        .rev()
+        .flat_map(split_words)
+        .filter(|word| {
+            word.chars().count() >= MIN_WORD_CHARS
+                && !word.chars().all(char::is_numeric)
+                && seen.insert(*word)
+        })
+        .map(str::to_string)
+        .take(MAX_WORDS)
+        .collect()
+}
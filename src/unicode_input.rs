@@ -0,0 +1,63 @@
+use crate::snippets::config::UnicodeInputInfo;
+
+// categories of unicode input symbols, selectable per language
+pub const CATEGORIES: [&str; 6] = ["greek", "arrows", "math", "scripts", "emoji", "other"];
+
+/// Category of unicode input symbol by its first char
+pub fn category(body: &str) -> &'static str {
+    let Some(ch) = body.chars().next() else {
+        return "other";
+    };
+    match ch as u32 {
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => "greek",
+        0x2190..=0x21FF | 0x27F0..=0x27FF | 0x2900..=0x297F | 0x2B00..=0x2BFF => "arrows",
+        0x2200..=0x22FF | 0x27C0..=0x27EF | 0x2980..=0x2AFF | 0x1D400..=0x1D7FF => "math",
+        0x00B2 | 0x00B3 | 0x00B9 | 0x2070..=0x209F | 0x1D62..=0x1D6A => "scripts",
+        0x2600..=0x27BF | 0x1F000..=0x1FAFF => "emoji",
+        _ => "other",
+    }
+}
+
+/// Rank of query match by entry prefix, aliases and description words, lower is better.
+/// Each whitespace separated token should match, like `greater equal` for `≥`
+pub fn match_rank(query: &str, prefix: &str, info: Option<&UnicodeInputInfo>) -> Option<usize> {
+    let prefix = prefix.to_lowercase();
+    let aliases = info
+        .map(|i| {
+            i.aliases
+                .iter()
+                .map(|a| a.to_lowercase())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let words = info
+        .and_then(|i| i.description.as_deref())
+        .map(|d| {
+            d.to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut rank = 0;
+    for token in query.to_lowercase().split_whitespace() {
+        rank += if prefix == token {
+            0
+        } else if aliases.iter().any(|a| *a == token) {
+            1
+        } else if prefix.starts_with(token) {
+            2
+        } else if aliases.iter().any(|a| a.starts_with(token)) {
+            3
+        } else if words.iter().any(|w| w.starts_with(token)) {
+            4
+        } else if prefix.contains(token) {
+            5
+        } else {
+            return None;
+        };
+    }
+    Some(rank)
+}
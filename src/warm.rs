@@ -0,0 +1,61 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// limit of documents kept in warm-start cache, most recently used
+pub const MAX_WARM_DOCS: usize = 200;
+
+/// Words of document file from the last session
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WarmDocument {
+    pub path: PathBuf,
+    // file modification time in millis since epoch
+    pub mtime: u128,
+    pub language_id: String,
+    pub words: Vec<String>,
+}
+
+/// Last session documents words of workspace roots, persisted as JSON on shutdown
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WarmCache {
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
+    #[serde(default)]
+    pub docs: Vec<WarmDocument>,
+}
+
+/// File modification time in millis since epoch
+pub fn mtime(path: &Path) -> Option<u128> {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis())
+}
+
+impl WarmCache {
+    /// Load documents of workspace roots whose files haven't changed since they were saved
+    pub fn load(path: &Path, roots: &[PathBuf]) -> Result<Vec<WarmDocument>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let cache = serde_json::from_str::<Self>(&std::fs::read_to_string(path)?)?;
+        if cache.roots != roots {
+            return Ok(Vec::new());
+        }
+        Ok(cache
+            .docs
+            .into_iter()
+            .filter(|doc| mtime(&doc.path) == Some(doc.mtime))
+            .collect())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
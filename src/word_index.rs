@@ -0,0 +1,146 @@
+use crate::split_words;
+use ropey::Rope;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Words never indexed, listed ones and matched by patterns (compiled once)
+#[derive(Debug, Default, Clone)]
+pub struct WordsFilter {
+    words: Vec<String>,
+    patterns: Vec<String>,
+    set: Option<Arc<regex::RegexSet>>,
+}
+
+impl WordsFilter {
+    pub fn new(words: &[String], patterns: &[String]) -> Self {
+        // invalid patterns are skipped, reported by self-check
+        let set = (!patterns.is_empty()).then(|| {
+            let valid = patterns.iter().filter(|p| regex::Regex::new(p).is_ok());
+            Arc::new(regex::RegexSet::new(valid).unwrap_or_else(|_| regex::RegexSet::empty()))
+        });
+        Self {
+            words: words.to_vec(),
+            patterns: patterns.to_vec(),
+            set,
+        }
+    }
+
+    pub fn is_excluded(&self, word: &str) -> bool {
+        self.words.iter().any(|w| w == word)
+            || self.set.as_ref().is_some_and(|set| set.is_match(word))
+    }
+}
+
+impl PartialEq for WordsFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.words == other.words && self.patterns == other.patterns
+    }
+}
+
+/// Words of document with its occurrences count, updated incrementally on edits
+#[derive(Debug, Default)]
+pub struct WordIndex {
+    // (ascii lowercase word, word) ordered for case insensitive prefix lookup
+    words: BTreeMap<(String, String), usize>,
+    // excluded words are never counted
+    filter: WordsFilter,
+}
+
+impl WordIndex {
+    pub fn with_filter(filter: WordsFilter) -> Self {
+        Self {
+            words: BTreeMap::new(),
+            filter,
+        }
+    }
+
+    pub fn new(text: &Rope, filter: WordsFilter) -> Self {
+        let mut index = Self::with_filter(filter);
+        index.add_lines(text, 0, text.len_lines().saturating_sub(1));
+        index
+    }
+
+    /// Index of distinct words, like ones kept in warm cache
+    pub fn from_words(words: impl IntoIterator<Item = String>, filter: WordsFilter) -> Self {
+        let mut index = Self::with_filter(filter);
+        for word in words {
+            if !index.filter.is_excluded(&word) {
+                *index
+                    .words
+                    .entry((word.to_ascii_lowercase(), word))
+                    .or_default() += 1;
+            }
+        }
+        index
+    }
+
+    pub fn filter(&self) -> &WordsFilter {
+        &self.filter
+    }
+
+    /// Drop words excluded by new filter, words excluded before stay missing
+    pub fn refilter(&mut self, filter: WordsFilter) {
+        self.words.retain(|(_, word), _| !filter.is_excluded(word));
+        self.filter = filter;
+    }
+
+    /// Count words of lines `start..=end`
+    pub fn add_lines(&mut self, text: &Rope, start: usize, end: usize) {
+        for_each_word(text, start, end, |word| {
+            if self.filter.is_excluded(word) {
+                return;
+            }
+            *self
+                .words
+                .entry((word.to_ascii_lowercase(), word.to_string()))
+                .or_default() += 1;
+        });
+    }
+
+    /// Forget words of lines `start..=end`, called before the lines are edited
+    pub fn remove_lines(&mut self, text: &Rope, start: usize, end: usize) {
+        for_each_word(text, start, end, |word| {
+            let key = (word.to_ascii_lowercase(), word.to_string());
+            if let Some(count) = self.words.get_mut(&key) {
+                *count -= 1;
+                if *count == 0 {
+                    self.words.remove(&key);
+                }
+            }
+        });
+    }
+
+    /// Distinct words starting with prefix, ascii case insensitive
+    pub fn starting_with<'a>(&'a self, prefix: &str) -> impl Iterator<Item = &'a str> + 'a {
+        let lowercase_prefix = prefix.to_ascii_lowercase();
+        self.words
+            .range((lowercase_prefix.clone(), String::new())..)
+            .take_while(move |((key, _), _)| key.starts_with(&lowercase_prefix))
+            .map(|((_, word), _)| word.as_str())
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words
+            .contains_key(&(word.to_ascii_lowercase(), word.to_string()))
+    }
+
+    /// Distinct words, sorted case insensitive
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.words.keys().map(|(_, word)| word.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+fn for_each_word(text: &Rope, start: usize, end: usize, mut f: impl FnMut(&str)) {
+    for line in (start..=end).map_while(|i| text.get_line(i)) {
+        let line = std::borrow::Cow::<str>::from(line);
+        split_words(&line).for_each(&mut f);
+    }
+}
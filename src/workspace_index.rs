@@ -0,0 +1,92 @@
+use crate::word_index::{WordIndex, WordsFilter};
+use ropey::Rope;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// limits of files read per workspace root, larger files are skipped
+const MAX_FILES: usize = 10000;
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+// files with NUL byte in its head are taken as binary, like git does
+const BINARY_CHECK_BYTES: usize = 8000;
+// dirs never walked, even when not ignored by git
+const SKIPPED_DIRS: [&str; 2] = ["node_modules", "target"];
+
+/// Words of files under workspace roots, one index per root.
+/// Hidden files, files ignored by `.gitignore`/`.ignore` and matched by exclude globs are skipped
+pub fn index_roots(
+    roots: &[PathBuf],
+    exclude: &[String],
+    filter: &WordsFilter,
+) -> Vec<(PathBuf, WordIndex)> {
+    roots
+        .iter()
+        .map(|root| {
+            let mut index = WordIndex::with_filter(filter.clone());
+            for path in collect_files(root, exclude) {
+                let Some(content) = read_text(&path) else {
+                    continue;
+                };
+                let text = Rope::from_str(&content);
+                index.add_lines(&text, 0, text.len_lines().saturating_sub(1));
+            }
+            tracing::debug!("Indexed {} words of workspace {root:?}", index.len());
+            (root.clone(), index)
+        })
+        .collect()
+}
+
+/// Glob of `index_exclude` setting is valid
+pub fn check_exclude(glob: &str) -> Result<(), ignore::Error> {
+    ignore::overrides::OverrideBuilder::new("/")
+        .add(&format!("!{glob}"))
+        .map(|_| ())
+}
+
+fn collect_files(root: &Path, exclude: &[String]) -> Vec<PathBuf> {
+    let mut overrides = ignore::overrides::OverrideBuilder::new(root);
+    for glob in exclude {
+        // invalid globs are skipped, reported by self-check
+        if let Err(e) = overrides.add(&format!("!{glob}")) {
+            tracing::warn!("Invalid index exclude glob '{glob}': {e}");
+        }
+    }
+    let overrides = overrides.build().unwrap_or_else(|e| {
+        tracing::warn!("On build index exclude globs: {e}");
+        ignore::overrides::Override::empty()
+    });
+
+    ignore::WalkBuilder::new(root)
+        .require_git(false)
+        .overrides(overrides)
+        .filter_entry(|entry| {
+            !(entry.file_type().is_some_and(|t| t.is_dir())
+                && entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| SKIPPED_DIRS.contains(&name)))
+        })
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_type().is_some_and(|t| t.is_file())
+                && entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES)
+        })
+        .map(|entry| entry.into_path())
+        .take(MAX_FILES)
+        .collect()
+}
+
+/// Content of UTF-8 text file, binary files are read up to its head only
+fn read_text(path: &Path) -> Option<String> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut content = Vec::new();
+    file.by_ref()
+        .take(BINARY_CHECK_BYTES as u64)
+        .read_to_end(&mut content)
+        .ok()?;
+    if content.contains(&0) {
+        return None;
+    }
+    file.read_to_end(&mut content).ok()?;
+    String::from_utf8(content).ok()
+}
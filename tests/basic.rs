@@ -8,7 +8,8 @@ use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tower_lsp::{jsonrpc, lsp_types};
 
-pub struct AsyncIn(UnboundedReceiver<String>);
+// received messages with not yet read remainder
+pub struct AsyncIn(UnboundedReceiver<String>, Vec<u8>);
 pub struct AsyncOut(UnboundedSender<String>);
 
 fn encode_message(content_type: Option<&str>, message: &str) -> String {
@@ -31,14 +32,20 @@ impl AsyncRead for AsyncIn {
         buf: &mut ReadBuf<'_>,
     ) -> Poll<std::io::Result<()>> {
         let rx = self.get_mut();
-        match rx.0.poll_recv(cx) {
-            Poll::Ready(Some(v)) => {
-                tracing::debug!("read value: {:?}", v);
-                buf.put_slice(v.as_bytes());
-                Poll::Ready(Ok(()))
+        if rx.1.is_empty() {
+            match rx.0.poll_recv(cx) {
+                Poll::Ready(Some(v)) => {
+                    tracing::debug!("read value: {:?}", v);
+                    rx.1 = v.into_bytes();
+                }
+                _ => return Poll::Pending,
             }
-            _ => Poll::Pending,
         }
+        // reader buffer may be smaller than message
+        let n = rx.1.len().min(buf.remaining());
+        buf.put_slice(&rx.1[..n]);
+        rx.1.drain(..n);
+        Poll::Ready(Ok(()))
     }
 }
 
@@ -75,15 +82,39 @@ impl TestContext {
         snippets: Vec<snippets::Snippet>,
         unicode_input: HashMap<String, String>,
         home_dir: String,
+    ) -> anyhow::Result<Self> {
+        Self::with_options(
+            snippets,
+            unicode_input,
+            home_dir,
+            server::ServerOptions::default(),
+        )
+        .await
+    }
+
+    pub async fn with_options(
+        snippets: Vec<snippets::Snippet>,
+        unicode_input: HashMap<String, String>,
+        home_dir: String,
+        options: server::ServerOptions,
     ) -> anyhow::Result<Self> {
         let (request_tx, rx) = mpsc::unbounded_channel::<String>();
         let (tx, response_rx) = mpsc::unbounded_channel::<String>();
 
-        let async_in = AsyncIn(rx);
+        let async_in = AsyncIn(rx, Vec::new());
         let async_out = AsyncOut(tx);
 
         let server = tokio::spawn(async move {
-            server::start(async_in, async_out, snippets, unicode_input, home_dir).await
+            server::start(
+                async_in,
+                async_out,
+                snippets,
+                unicode_input,
+                Vec::new(),
+                home_dir,
+                options,
+            )
+            .await
         });
 
         Ok(Self {
@@ -117,7 +148,7 @@ impl TestContext {
                 .await
                 .ok_or_else(|| anyhow::anyhow!("empty response"))?;
             // decode response
-            let payload = response.split('\n').last().unwrap_or_default();
+            let payload = response.split('\n').next_back().unwrap_or_default();
 
             // skip log messages
             if payload.contains("window/logMessage") {
@@ -139,9 +170,10 @@ impl TestContext {
     }
 
     pub async fn initialize(&mut self) -> anyhow::Result<()> {
+        // snippets are expanded by client, like by most editors
         let request = jsonrpc::Request::build("initialize")
             .id(1)
-            .params(serde_json::json!({"capabilities":{}}))
+            .params(serde_json::json!({"capabilities":{"textDocument":{"completion":{"completionItem":{"snippetSupport":true}}}}}))
             .finish();
 
         let _ = self
@@ -152,13 +184,57 @@ impl TestContext {
     }
 }
 
+/// Completion items, request is sent again (with next id) while answered as incomplete
+async fn complete_loaded(
+    context: &mut TestContext,
+    request: &str,
+) -> anyhow::Result<Vec<lsp_types::CompletionItem>> {
+    let mut request = serde_json::from_str::<serde_json::Value>(request)?;
+    for id in 3..100 {
+        request["id"] = id.into();
+        context.send_all(&[&request.to_string()]).await?;
+        match context.recv::<lsp_types::CompletionResponse>().await? {
+            lsp_types::CompletionResponse::Array(items) => return Ok(items),
+            lsp_types::CompletionResponse::List(list) if !list.is_incomplete => {
+                return Ok(list.items)
+            }
+            lsp_types::CompletionResponse::List(_) => {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        }
+    }
+    anyhow::bail!("completion is still incomplete")
+}
+
+fn git(repo: &str, args: &[&str]) -> anyhow::Result<()> {
+    let output = std::process::Command::new("git")
+        .args(["-c", "user.name=scls", "-c", "user.email=scls@localhost"])
+        .args(args)
+        .current_dir(repo)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("git {args:?}: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+fn init_git_repo(repo: &str, subjects: &[&str]) -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(repo);
+    std::fs::create_dir_all(repo)?;
+    git(repo, &["init", "-q"])?;
+    for subject in subjects {
+        git(repo, &["commit", "-q", "--allow-empty", "-m", subject])?;
+    }
+    Ok(())
+}
+
 #[test_log::test(tokio::test)]
 async fn initialize() -> anyhow::Result<()> {
     let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
 
     let request = jsonrpc::Request::build("initialize")
         .id(1)
-        .params(serde_json::json!({"capabilities":{}}))
+        .params(serde_json::json!({"capabilities":{"textDocument":{"synchronization":{"didSave":true}}}}))
         .finish();
 
     let response = context
@@ -168,15 +244,20 @@ async fn initialize() -> anyhow::Result<()> {
     assert_eq!(
         response.capabilities.completion_provider,
         Some(lsp_types::CompletionOptions {
-            resolve_provider: Some(false),
+            resolve_provider: Some(true),
             trigger_characters: Some(vec![std::path::MAIN_SEPARATOR_STR.to_string()]),
             ..lsp_types::CompletionOptions::default()
         })
     );
     assert_eq!(
         response.capabilities.text_document_sync,
-        Some(lsp_types::TextDocumentSyncCapability::Kind(
-            lsp_types::TextDocumentSyncKind::INCREMENTAL,
+        Some(lsp_types::TextDocumentSyncCapability::Options(
+            lsp_types::TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(lsp_types::TextDocumentSyncKind::INCREMENTAL),
+                save: Some(lsp_types::TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            }
         ))
     );
 
@@ -216,12 +297,14 @@ async fn snippets() -> anyhow::Result<()> {
                 prefix: "ma".to_string(),
                 body: "def main(): pass".to_string(),
                 description: None,
+                ..Default::default()
             },
             snippets::Snippet {
                 scope: Some(vec!["c".to_string()]),
                 prefix: "ma".to_string(),
                 body: "malloc".to_string(),
                 description: None,
+                ..Default::default()
             },
         ],
         HashMap::new(),
@@ -253,20 +336,23 @@ async fn snippets() -> anyhow::Result<()> {
 }
 
 #[test_log::test(tokio::test)]
-async fn unicode_input() -> anyhow::Result<()> {
+async fn label_details() -> anyhow::Result<()> {
     let mut context = TestContext::new(
-        Vec::new(),
-        HashMap::from_iter([
-            ("alpha".to_string(), "α".to_string()),
-            ("betta".to_string(), "β".to_string()),
-        ]),
+        vec![snippets::Snippet {
+            scope: Some(vec!["python".to_string()]),
+            prefix: "cls".to_string(),
+            body: "\nclass ${1:Name}:\n    pass".to_string(),
+            description: None,
+            ..Default::default()
+        }],
+        HashMap::from_iter([("clubs".to_string(), "♣".to_string())]),
         String::new(),
     )
     .await?;
     context.initialize().await?;
     context.send_all(&[
-        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"α+bet","uri":"file:///tmp/main.py","version":0}}}"#,
-        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":5,"line":0},"textDocument":{"uri":"file:///tmp/main.py"}},"id":3}"#
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"clsx\ncl","uri":"file:///tmp/label-details.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/label-details.py"}},"id":3}"#
     ]).await?;
 
     let response = context.recv::<lsp_types::CompletionResponse>().await?;
@@ -275,73 +361,396 @@ async fn unicode_input() -> anyhow::Result<()> {
         anyhow::bail!("completion array expected")
     };
 
+    let mut details = items
+        .into_iter()
+        .map(|i| {
+            let details = i.label_details.unwrap_or_default();
+            (i.label, details.detail, details.description)
+        })
+        .collect::<Vec<_>>();
+    details.sort();
+    assert_eq!(
+        details,
+        vec![
+            (
+                "cls".to_string(),
+                Some(" class ${1:Name}:".to_string()),
+                Some("snippet".to_string())
+            ),
+            ("clsx".to_string(), None, None),
+            ("♣".to_string(), None, Some("σ unicode".to_string())),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn documentation_format() -> anyhow::Result<()> {
+    let snippets = || {
+        vec![snippets::Snippet {
+            scope: Some(vec!["python".to_string()]),
+            prefix: "ma".to_string(),
+            body: "def main(): pass".to_string(),
+            description: Some("Main function".to_string()),
+            ..Default::default()
+        }]
+    };
+    for (capabilities, expected) in [
+        (
+            serde_json::json!({}),
+            lsp_types::Documentation::String("def main(): pass".to_string()),
+        ),
+        (
+            serde_json::json!({"textDocument":{"completion":{"completionItem":{"documentationFormat":["markdown","plaintext"]}}}}),
+            lsp_types::Documentation::MarkupContent(lsp_types::MarkupContent {
+                kind: lsp_types::MarkupKind::Markdown,
+                value: "```python\ndef main(): pass\n```".to_string(),
+            }),
+        ),
+    ] {
+        let mut context = TestContext::new(snippets(), HashMap::new(), String::new()).await?;
+        let request = jsonrpc::Request::build("initialize")
+            .id(1)
+            .params(serde_json::json!({ "capabilities": capabilities }))
+            .finish();
+        context
+            .request::<lsp_types::InitializeResult>(&request)
+            .await?;
+        context.send_all(&[
+            r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"ma","uri":"file:///tmp/documentation.py","version":0}}}"#,
+            r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/documentation.py"}},"id":3}"#
+        ]).await?;
+
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        assert_eq!(
+            items
+                .into_iter()
+                .map(|i| (i.detail, i.documentation))
+                .collect::<Vec<_>>(),
+            vec![(Some("Main function".to_string()), Some(expected))]
+        );
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn snippets_plain_text() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        vec![snippets::Snippet {
+            scope: Some(vec!["rust".to_string()]),
+            prefix: "fn".to_string(),
+            body: "fn ${1:name}(${2|a,b|}) -> ${3:${4:u8}} {\n\t$0\n} // \\$1 $TM_FILENAME"
+                .to_string(),
+            description: None,
+            ..Default::default()
+        }],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    // client without snippet support
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{}}))
+        .finish();
+    context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"fn","uri":"file:///tmp/plain.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/plain.rs"}},"id":3}"#
+    ]).await?;
+
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
     assert_eq!(
         items
             .into_iter()
-            .filter_map(|i| match i.text_edit {
-                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
-                _ => None,
-            })
+            .map(|i| (i.insert_text, i.insert_text_format))
             .collect::<Vec<_>>(),
-        vec!["β"]
+        vec![(
+            Some("fn name(a) -> u8 {\n\t\n} // $1 ".to_string()),
+            Some(lsp_types::InsertTextFormat::PLAIN_TEXT)
+        )]
     );
 
     Ok(())
 }
 
 #[test_log::test(tokio::test)]
-async fn paths() -> anyhow::Result<()> {
-    std::fs::create_dir_all("/tmp/scls-test/sub-folder")?;
-
-    let mut context = TestContext::new(Vec::new(), HashMap::new(), "/tmp".to_string()).await?;
+async fn snippets_exact_prefix() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        vec![
+            snippets::Snippet {
+                scope: None,
+                prefix: "for".to_string(),
+                body: "for $1 in $2:".to_string(),
+                description: None,
+                ..Default::default()
+            },
+            snippets::Snippet {
+                scope: None,
+                prefix: "format".to_string(),
+                body: "format($1)".to_string(),
+                description: None,
+                ..Default::default()
+            },
+        ],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
     context.initialize().await?;
     context.send_all(&[
-        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"/tmp/scls-test/","uri":"file:///tmp/main.py","version":0}}}"#,
-        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":15,"line":0},"textDocument":{"uri":"file:///tmp/main.py"}},"id":3}"#
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"forest\nfor","uri":"file:///tmp/exact.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":3,"line":1},"textDocument":{"uri":"file:///tmp/exact.py"}},"id":3}"#
     ]).await?;
 
-    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    let preselected = items
+        .iter()
+        .map(|i| (i.label.as_str(), i.preselect))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        preselected,
+        vec![("forest", None), ("for", Some(true)), ("format", None)]
+    );
 
-    let lsp_types::CompletionResponse::Array(items) = response else {
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"snippets_exclusive_on_exact":true}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":3,"line":1},"textDocument":{"uri":"file:///tmp/exact.py"}},"id":4}"#
+    ]).await?;
+
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
         anyhow::bail!("completion array expected")
     };
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["for"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn snippets_when_condition() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        vec![
+            snippets::Snippet {
+                scope: None,
+                prefix: "derive".to_string(),
+                body: "derive($1)]".to_string(),
+                description: None,
+                when: Some(r"^\s*#\[$".to_string()),
+                ..Default::default()
+            },
+            snippets::Snippet {
+                scope: None,
+                prefix: "def".to_string(),
+                body: "def $1():".to_string(),
+                description: None,
+                when: Some(r"^\s*$".to_string()),
+                ..Default::default()
+            },
+        ],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r##"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"#[de\n  de\nx = de","uri":"file:///tmp/when.rs","version":0}}}"##,
+    ]).await?;
+
+    for (line, expected) in [(0, vec!["derive"]), (1, vec!["def"]), (2, vec![])] {
+        let request = jsonrpc::Request::build("textDocument/completion")
+            .id(line + 3)
+            .params(serde_json::json!({"position":{"character":if line == 2 { 6 } else { 4 },"line":line},"textDocument":{"uri":"file:///tmp/when.rs"}}))
+            .finish();
+        let lsp_types::CompletionResponse::Array(items) = context
+            .request::<lsp_types::CompletionResponse>(&request)
+            .await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        assert_eq!(
+            items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+            expected,
+            "line {line}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn snippets_variables() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        vec![snippets::Snippet {
+            scope: None,
+            prefix: "sign".to_string(),
+            body: "// ${AUTHOR} <${EMAIL}> ${TM_FILENAME}".to_string(),
+            description: None,
+            ..Default::default()
+        }],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"variables":{"author":"Jane $1","email":"jane@x.y"}}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"si","uri":"file:///tmp/variables.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/variables.py"}},"id":3}"#
+    ]).await?;
 
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
     assert_eq!(
         items
             .into_iter()
-            .filter_map(|i| match i.text_edit {
-                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
-                _ => None,
-            })
+            .filter_map(|i| i.insert_text)
             .collect::<Vec<_>>(),
-        vec!["/tmp/scls-test/sub-folder"]
+        vec!["// Jane \\$1 <jane@x.y> ${TM_FILENAME}"]
     );
 
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn snippets_file_templates() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        vec![
+            snippets::Snippet {
+                scope: Some(vec!["rust".to_string()]),
+                prefix: "main".to_string(),
+                body: "fn main() {\n    $0\n}".to_string(),
+                description: None,
+                file_template: true,
+                file_glob: Some("main.rs".to_string()),
+                ..Default::default()
+            },
+            snippets::Snippet {
+                scope: Some(vec!["rust".to_string()]),
+                prefix: "mod".to_string(),
+                body: "//! $0".to_string(),
+                description: None,
+                file_template: true,
+                file_glob: Some("lib.rs".to_string()),
+                ..Default::default()
+            },
+        ],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
     context.send_all(&[
-        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"/tmp/scls-test/su","uri":"file:///tmp/main2.py","version":0}}}"#,
-        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":17,"line":0},"textDocument":{"uri":"file:///tmp/main2.py"}},"id":3}"#
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"m","uri":"file:///tmp/src/main.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":1,"line":0},"textDocument":{"uri":"file:///tmp/src/main.rs"}},"id":3}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"fn foo() {}\nm","uri":"file:///tmp/src/other.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":1,"line":1},"textDocument":{"uri":"file:///tmp/src/other.rs"}},"id":4}"#,
     ]).await?;
 
-    let response = context.recv::<lsp_types::CompletionResponse>().await?;
-
-    let lsp_types::CompletionResponse::Array(items) = response else {
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
         anyhow::bail!("completion array expected")
     };
-
     assert_eq!(
         items
             .into_iter()
-            .filter_map(|i| match i.text_edit {
-                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
-                _ => None,
-            })
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::FILE))
+            .map(|i| i.label)
             .collect::<Vec<_>>(),
-        vec!["/tmp/scls-test/sub-folder"]
+        vec!["main"]
+    );
+
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    assert!(items.is_empty());
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn snippets_shebang_scope() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        vec![
+            snippets::Snippet {
+                scope: Some(vec!["bash".to_string()]),
+                prefix: "ifthen".to_string(),
+                body: "if $1; then\n    $0\nfi".to_string(),
+                description: None,
+                ..Default::default()
+            },
+            snippets::Snippet {
+                scope: Some(vec!["python".to_string()]),
+                prefix: "ifmain".to_string(),
+                body: "if __name__ == \"__main__\":\n    $0".to_string(),
+                description: None,
+                ..Default::default()
+            },
+        ],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r##"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"plaintext","text":"#!/usr/bin/env python3\nif","uri":"file:///tmp/script","version":0}}}"##,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/script"}},"id":3}"#,
+    ]).await?;
+
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["ifmain"]
     );
 
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn unicode_input() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        Vec::new(),
+        HashMap::from_iter([
+            ("alpha".to_string(), "α".to_string()),
+            ("betta".to_string(), "β".to_string()),
+        ]),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
     context.send_all(&[
-        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"~/scls-test/su","uri":"file:///tmp/main3.py","version":0}}}"#,
-        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":14,"line":0},"textDocument":{"uri":"file:///tmp/main3.py"}},"id":3}"#
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"α+bet","uri":"file:///tmp/main.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":5,"line":0},"textDocument":{"uri":"file:///tmp/main.py"}},"id":3}"#
     ]).await?;
 
     let response = context.recv::<lsp_types::CompletionResponse>().await?;
@@ -358,8 +767,3833 @@ async fn paths() -> anyhow::Result<()> {
                 _ => None,
             })
             .collect::<Vec<_>>(),
-        vec!["~/scls-test/sub-folder"]
+        vec!["β"]
     );
 
     Ok(())
 }
+
+#[test_log::test(tokio::test)]
+async fn unicode_input_categories() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        Vec::new(),
+        HashMap::from_iter([
+            ("alpha".to_string(), "α".to_string()),
+            ("arrow".to_string(), "→".to_string()),
+            ("apple".to_string(), "🍎".to_string()),
+        ]),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"unicode_input_categories":{"python":["greek","arrows"],"*":["emoji"]}}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"a","uri":"file:///tmp/main.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":1,"line":0},"textDocument":{"uri":"file:///tmp/main.py"}},"id":3}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"a","uri":"file:///tmp/main.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":1,"line":0},"textDocument":{"uri":"file:///tmp/main.md"}},"id":4}"#,
+    ]).await?;
+
+    for expected in [vec!["α", "→"], vec!["🍎"]] {
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        let mut labels = items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::TEXT))
+            .map(|i| i.label)
+            .collect::<Vec<_>>();
+        labels.sort();
+        assert_eq!(labels, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn unicode_input_variants() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-unicode-variants")?;
+    let path = std::path::PathBuf::from("/tmp/scls-test-unicode-variants/emoji.toml");
+    std::fs::write(
+        &path,
+        r#"
+fire = "🔥"
+thumbsup = ["👍", "👍🏻", "👍🏿", "👍‍"]
+heart = { default = "❤", green = "💚" }
+"#,
+    )?;
+
+    let mut unicode_input = snippets::config::load_unicode_input_from_file(&path)?
+        .bodies
+        .into_iter()
+        .collect::<Vec<_>>();
+    unicode_input.sort();
+    assert_eq!(
+        unicode_input,
+        [
+            ("fire", "🔥"),
+            ("heart", "❤"),
+            ("heart_green", "💚"),
+            ("thumbsup", "👍"),
+            ("thumbsup_3", "👍‍"),
+            ("thumbsup_dark", "👍🏿"),
+            ("thumbsup_light", "👍🏻"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn unicode_input_aliases() -> anyhow::Result<()> {
+    use simple_completion_language_server::unicode_input::match_rank;
+
+    std::fs::create_dir_all("/tmp/scls-test-unicode-aliases")?;
+    let path = std::path::PathBuf::from("/tmp/scls-test-unicode-aliases/math.toml");
+    std::fs::write(
+        &path,
+        r#"
+ge = { body = "≥", aliases = ["geq"], description = "greater-than or equal to" }
+le = { body = "≤", aliases = ["leq"], description = "less-than or equal to" }
+"#,
+    )?;
+    let unicode_input = snippets::config::load_unicode_input_from_file(&path)?;
+    let info = unicode_input.info.get("ge");
+    assert_eq!(
+        unicode_input.bodies.get("ge").map(String::as_str),
+        Some("≥")
+    );
+    assert_eq!(match_rank("geq", "ge", info), Some(1));
+    assert_eq!(match_rank("greater equal", "ge", info), Some(8));
+    assert_eq!(match_rank("greater less", "ge", info), None);
+
+    let load_sources = move || simple_completion_language_server::LoadedSources {
+        unicode_input: unicode_input.bodies.clone(),
+        unicode_input_info: unicode_input.info.clone(),
+        ..Default::default()
+    };
+    let mut context = TestContext::with_options(
+        Vec::new(),
+        HashMap::new(),
+        String::new(),
+        server::ServerOptions {
+            load_sources: Some(std::sync::Arc::new(load_sources)),
+            ..Default::default()
+        },
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"x leq\ngreat","uri":"file:///tmp/unicode-aliases.txt","version":0}}}"#,
+    ]).await?;
+
+    let mut labels = Vec::new();
+    for (id, (line, character)) in [(0, 5), (1, 5)].into_iter().enumerate() {
+        // sources are loaded in background
+        for _ in 0..50 {
+            let request = jsonrpc::Request::build("textDocument/completion")
+                .id(id as i64 + 3)
+                .params(serde_json::json!({
+                    "position":{"character":character,"line":line},
+                    "textDocument":{"uri":"file:///tmp/unicode-aliases.txt"}
+                }))
+                .finish();
+            let lsp_types::CompletionResponse::Array(items) = context
+                .request::<lsp_types::CompletionResponse>(&request)
+                .await?
+            else {
+                anyhow::bail!("completion array expected")
+            };
+            let mut found = items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+            if !found.is_empty() {
+                found.sort();
+                labels.push(found);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    }
+    assert_eq!(labels, vec![vec!["≤"], vec!["≥"]]);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn document_highlight() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"foo bar foo_baz Foo\nα foo","uri":"file:///tmp/highlight.txt","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/documentHighlight","params":{"position":{"character":1,"line":0},"textDocument":{"uri":"file:///tmp/highlight.txt"}},"id":3}"#,
+    ]).await?;
+
+    let highlights = context.recv::<Vec<lsp_types::DocumentHighlight>>().await?;
+    assert_eq!(
+        highlights
+            .into_iter()
+            .map(|h| (
+                h.range.start.line,
+                h.range.start.character,
+                h.range.end.character
+            ))
+            .collect::<Vec<_>>(),
+        // utf-16 columns
+        vec![(0, 0, 3), (1, 2, 5)]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn document_link() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-links/docs")?;
+    std::fs::write("/tmp/scls-test-links/docs/guide.md", "")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"See [guide](docs/guide.md), https://example.com/a?b=1.\nmissing/file.md /tmp/scls-test-links/docs","uri":"file:///tmp/scls-test-links/README.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/documentLink","params":{"textDocument":{"uri":"file:///tmp/scls-test-links/README.md"}},"id":3}"#,
+    ]).await?;
+
+    let links = context.recv::<Vec<lsp_types::DocumentLink>>().await?;
+    assert_eq!(
+        links
+            .into_iter()
+            .map(|l| (
+                l.range.start.line,
+                l.range.start.character,
+                l.range.end.character,
+                l.target.map(|t| t.to_string()).unwrap_or_default()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                0,
+                12,
+                25,
+                "file:///tmp/scls-test-links/docs/guide.md".to_string()
+            ),
+            (0, 28, 53, "https://example.com/a?b=1".to_string()),
+            (1, 16, 41, "file:///tmp/scls-test-links/docs".to_string()),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn unicode_input_by_name() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        Vec::new(),
+        HashMap::from_iter([
+            ("alpha".to_string(), "α".to_string()),
+            ("betta".to_string(), "β".to_string()),
+            ("Alef".to_string(), "ℵ".to_string()),
+        ]),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"an al","uri":"file:///tmp/unicode-name.txt","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/codeAction","params":{"textDocument":{"uri":"file:///tmp/unicode-name.txt"},"range":{"start":{"line":0,"character":4},"end":{"line":0,"character":4}},"context":{"diagnostics":[]}},"id":3}"#,
+    ]).await?;
+
+    let actions = context.recv::<lsp_types::CodeActionResponse>().await?;
+    assert_eq!(
+        actions
+            .into_iter()
+            .filter_map(|a| match a {
+                lsp_types::CodeActionOrCommand::CodeAction(a) => Some(a.title),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec!["Insert ℵ (Alef)", "Insert α (alpha)"]
+    );
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/executeCommand","params":{"command":"scls.insertUnicode","arguments":["bet","file:///tmp/unicode-name.txt",{"line":0,"character":2}]},"id":4}"#,
+    ]).await?;
+
+    // edit is applied by client
+    let request = loop {
+        let message = context
+            .response_rx
+            .recv()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("empty response"))?;
+        let payload = message.split('\n').next_back().unwrap_or_default();
+        if payload.contains("workspace/applyEdit") {
+            break serde_json::from_str::<serde_json::Value>(payload)?;
+        }
+    };
+    assert_eq!(
+        request["params"]["edit"]["changes"]["file:///tmp/unicode-name.txt"][0]["newText"],
+        "β"
+    );
+    let response =
+        serde_json::json!({"jsonrpc":"2.0","id":request["id"],"result":{"applied":true}});
+    context.request_tx.send(format!(
+        "Content-Length: {}\r\n\r\n{response}",
+        response.to_string().len()
+    ))?;
+
+    assert_eq!(context.recv::<String>().await?, "β");
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn runtime_commands() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-runtime-commands/sub-folder")?;
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{}}))
+        .finish();
+    let result = context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+    let commands = result
+        .capabilities
+        .execute_command_provider
+        .map(|p| p.commands)
+        .unwrap_or_default();
+    for command in [
+        "scls.toggleFeature",
+        "scls.reloadSnippets",
+        "scls.showStats",
+    ] {
+        assert!(commands.iter().any(|c| c == command), "{command}");
+    }
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"hello he\n/tmp/scls-test-runtime-commands/","uri":"file:///tmp/runtime-commands.txt","version":0}}}"#,
+    ]).await?;
+
+    // answer of request by id, messages shown to user are skipped
+    let mut execute = async |id: u32,
+                             command: serde_json::Value|
+           -> anyhow::Result<serde_json::Value> {
+        context.send_all(&[
+            &serde_json::json!({"jsonrpc":"2.0","method":"workspace/executeCommand","params":command,"id":id}).to_string(),
+            &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":8,"line":0}},"textDocument":{{"uri":"file:///tmp/runtime-commands.txt"}}}},"id":{}}}"#, id + 1),
+            &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":32,"line":1}},"textDocument":{{"uri":"file:///tmp/runtime-commands.txt"}}}},"id":{}}}"#, id + 2),
+        ]).await?;
+        let mut answers = HashMap::new();
+        while answers.len() < 3 {
+            let message = context
+                .response_rx
+                .recv()
+                .await
+                .ok_or_else(|| anyhow::anyhow!("empty response"))?;
+            for payload in message
+                .split("Content-Length: ")
+                .filter_map(|m| m.split_once("\r\n\r\n"))
+                .map(|(_, payload)| payload)
+            {
+                let value = serde_json::from_str::<serde_json::Value>(payload)?;
+                if let Some(id) = value["id"].as_u64() {
+                    answers.insert(id, value);
+                }
+            }
+        }
+        let labels = |id: u64| {
+            answers[&id]["result"]
+                .as_array()
+                .map(|items| items.iter().map(|i| i["label"].clone()).collect::<Vec<_>>())
+                .unwrap_or_default()
+        };
+        Ok(serde_json::json!({
+            "answer": answers[&(id as u64)],
+            "labels": labels(id as u64 + 1),
+            "paths": labels(id as u64 + 2),
+        }))
+    };
+
+    let toggle =
+        serde_json::json!({"command":"scls.toggleFeature","arguments":[{"feature":"words"}]});
+    let value = execute(2, toggle.clone()).await?;
+    assert_eq!(
+        value["answer"]["result"],
+        serde_json::json!({"feature": "words", "enabled": false})
+    );
+    assert_eq!(value["labels"], serde_json::json!([]));
+    assert_eq!(
+        value["paths"],
+        serde_json::json!(["/tmp/scls-test-runtime-commands/sub-folder"])
+    );
+
+    let value = execute(5, toggle).await?;
+    assert_eq!(value["answer"]["result"]["enabled"], true);
+    assert_eq!(value["labels"], serde_json::json!(["hello"]));
+
+    // other features are kept
+    let toggle =
+        serde_json::json!({"command":"scls.toggleFeature","arguments":[{"feature":"paths"}]});
+    let value = execute(8, toggle.clone()).await?;
+    assert_eq!(
+        value["answer"]["result"],
+        serde_json::json!({"feature": "paths", "enabled": false})
+    );
+    assert_eq!(value["labels"], serde_json::json!(["hello"]));
+    assert_eq!(value["paths"], serde_json::json!([]));
+
+    let value = execute(11, toggle).await?;
+    assert_eq!(value["answer"]["result"]["enabled"], true);
+    assert_eq!(
+        value["paths"],
+        serde_json::json!(["/tmp/scls-test-runtime-commands/sub-folder"])
+    );
+
+    let value = execute(
+        14,
+        serde_json::json!({"command":"scls.toggleFeature","arguments":[{"feature":"citations"}]}),
+    )
+    .await?;
+    assert_eq!(
+        value["answer"]["error"]["message"],
+        "Unknown feature: citations"
+    );
+
+    let value = execute(17, serde_json::json!({"command":"scls.showStats"})).await?;
+    assert!(value["answer"]["result"]
+        .as_str()
+        .is_some_and(|stats| stats.starts_with("10 completion requests")));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn workspace_symbol() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-symbols")?;
+    let snippets_file = std::path::PathBuf::from("/tmp/scls-test-symbols/rust.toml");
+    std::fs::write(
+        &snippets_file,
+        "[[snippets]]\nprefix = \"fnmain\"\nbody = \"fn main() {}\"\n",
+    )?;
+
+    let mut context = TestContext::new(
+        vec![snippets::Snippet {
+            scope: Some(vec!["rust".to_string()]),
+            prefix: "fnmain".to_string(),
+            body: "fn main() {}".to_string(),
+            file: Some(snippets_file),
+            ..Default::default()
+        }],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"bibtex","text":"@string{kn = \"Knuth\"}\n\n@book{knuth1984,\n  title = {The TeXbook}\n}","uri":"file:///tmp/scls-test-symbols/refs.bib","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"workspace/symbol","params":{"query":"kn"},"id":3}"#,
+        r#"{"jsonrpc":"2.0","method":"workspace/symbol","params":{"query":"main"},"id":4}"#,
+    ]).await?;
+
+    for expected in [
+        ("knuth1984", "file:///tmp/scls-test-symbols/refs.bib", 2),
+        ("fnmain", "file:///tmp/scls-test-symbols/rust.toml", 1),
+    ] {
+        let symbols = context.recv::<Vec<lsp_types::SymbolInformation>>().await?;
+        assert_eq!(
+            symbols
+                .into_iter()
+                .map(|s| (
+                    s.name,
+                    s.location.uri.to_string(),
+                    s.location.range.start.line
+                ))
+                .collect::<Vec<_>>(),
+            vec![(expected.0.to_string(), expected.1.to_string(), expected.2)]
+        );
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn word_context() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"word_context":true}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"fn main() {\n    let foobarbaz = 1;\n}","uri":"file:///tmp/scls-context/other.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"foobar_x\nfoob","uri":"file:///tmp/scls-context/main.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":4,"line":1},"textDocument":{"uri":"file:///tmp/scls-context/main.rs"}},"id":3}"#,
+    ]).await?;
+
+    let lsp_types::CompletionResponse::Array(mut items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    assert_eq!(
+        items.iter().map(|i| i.label.as_str()).collect::<Vec<_>>(),
+        vec!["foobar_x", "foobarbaz"]
+    );
+
+    let mut resolved = Vec::new();
+    for item in items {
+        let request = jsonrpc::Request::build("completionItem/resolve")
+            .id(4)
+            .params(serde_json::to_value(item)?)
+            .finish();
+        let item = context
+            .request::<lsp_types::CompletionItem>(&request)
+            .await?;
+        resolved.push((item.detail, item.documentation));
+    }
+    assert_eq!(
+        resolved,
+        vec![
+            (
+                Some("main.rs:1".to_string()),
+                Some(lsp_types::Documentation::String("foobar_x".to_string()))
+            ),
+            (
+                Some("other.rs:2".to_string()),
+                Some(lsp_types::Documentation::String(
+                    "let foobarbaz = 1;".to_string()
+                ))
+            ),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn words_exclude_patterns() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"words_exclude_patterns":["^[0-9]+$","^[0-9a-f]{32,}$"]}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"de00334455667788990aabbccff0034556 deadline 1234 12th\nde\n12","uri":"file:///tmp/exclude-patterns.txt","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/exclude-patterns.txt"}},"id":3}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":2},"textDocument":{"uri":"file:///tmp/exclude-patterns.txt"}},"id":4}"#,
+    ]).await?;
+
+    for expected in [vec!["deadline"], vec!["12th"]] {
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        assert_eq!(
+            items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    // opened documents are indexed again on patterns change
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"words_exclude_patterns":["^dead"]}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/exclude-patterns.txt"}},"id":5}"#,
+    ]).await?;
+
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["de00334455667788990aabbccff0034556"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn source_line_filters() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"source_line_filters":{"words":"^//"}}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"hello\nhe\n// he","uri":"file:///tmp/filters.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/filters.rs"}},"id":3}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":5,"line":2},"textDocument":{"uri":"file:///tmp/filters.rs"}},"id":4}"#,
+    ]).await?;
+
+    for expected in [Vec::<&str>::new(), vec!["hello"]] {
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        assert_eq!(
+            items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn workspace_folders_isolation() -> anyhow::Result<()> {
+    let root = "/tmp/scls-test-folders";
+    let _ = std::fs::remove_dir_all(root);
+    std::fs::create_dir_all(format!("{root}/a"))?;
+    std::fs::create_dir_all(format!("{root}/b"))?;
+    std::fs::write(format!("{root}/a/.scls-words"), "apple\n")?;
+    std::fs::write(format!("{root}/b/.scls-words"), "apricot\n")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{},"workspaceFolders":[
+            {"uri":"file:///tmp/scls-test-folders/a","name":"a"},
+            {"uri":"file:///tmp/scls-test-folders/b","name":"b"},
+        ]}))
+        .finish();
+    context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"plaintext","text":"apex","uri":"file:///tmp/scls-test-folders/b/other.txt","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"plaintext","text":"ap","uri":"file:///tmp/scls-test-folders/a/main.txt","version":0}}}"#,
+    ]).await?;
+
+    let completion = r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-folders/a/main.txt"}},"id":3}"#;
+    for (settings, expected) in [
+        (None, vec!["apple"]),
+        (
+            Some(
+                r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"isolate_workspace_folders":false}}}"#,
+            ),
+            vec!["apex", "apple", "apricot"],
+        ),
+    ] {
+        if let Some(settings) = settings {
+            context.send_all(&[settings]).await?;
+        }
+        context.send_all(&[completion]).await?;
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        let mut labels = items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+        labels.sort();
+        assert_eq!(labels, expected);
+    }
+
+    std::fs::remove_dir_all(root)?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn workspace_words() -> anyhow::Result<()> {
+    let root = "/tmp/scls-test-workspace-words";
+    let _ = std::fs::remove_dir_all(root);
+    std::fs::create_dir_all(format!("{root}/src"))?;
+    std::fs::create_dir_all(format!("{root}/node_modules"))?;
+    std::fs::write(format!("{root}/src/fish.txt"), "zebrafish zeppelin\n")?;
+    std::fs::write(format!("{root}/node_modules/dep.js"), "zealot\n")?;
+    std::fs::write(format!("{root}/blob.bin"), "zero\0zone")?;
+    // ignored by git, ignore file and exclude glob
+    std::fs::create_dir_all(format!("{root}/generated"))?;
+    std::fs::write(format!("{root}/.gitignore"), "generated/\n")?;
+    std::fs::write(format!("{root}/generated/out.txt"), "zenith\n")?;
+    std::fs::write(format!("{root}/.ignore"), "notes.txt\n")?;
+    std::fs::write(format!("{root}/notes.txt"), "zest\n")?;
+    std::fs::write(format!("{root}/build.log"), "zeal\n")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{},"rootUri":"file:///tmp/scls-test-workspace-words"}))
+        .finish();
+    context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+    // missing thesaurus and abbreviations files never stop workspace indexing
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_workspace_words":true,"index_exclude":["*.log"],"feature_thesaurus":true,"thesaurus_path":"/tmp/scls-test-workspace-words/missing.txt","feature_abbreviations":true,"abbreviations_path":"/tmp/scls-test-workspace-words/missing.toml"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"plaintext","text":"zebra\nze","uri":"file:///tmp/scls-test-workspace-words/main.txt","version":0}}}"#,
+    ]).await?;
+
+    // files are indexed in background
+    let mut labels = Vec::new();
+    for id in 3..50 {
+        context.send_all(&[
+            &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":2,"line":1}},"textDocument":{{"uri":"file:///tmp/scls-test-workspace-words/main.txt"}}}},"id":{id}}}"#),
+        ]).await?;
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        labels = items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+        if labels.len() > 1 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    labels.sort();
+    assert_eq!(labels, vec!["zebra", "zebrafish", "zeppelin"]);
+
+    std::fs::remove_dir_all(root)?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn watched_files() -> anyhow::Result<()> {
+    let root = "/tmp/scls-test-watched";
+    let _ = std::fs::remove_dir_all(root);
+    std::fs::create_dir_all(root)?;
+    std::fs::write(format!("{root}/.scls-words"), "zephyr\n")?;
+    std::fs::write(format!("{root}/refs.bib"), "@book{zeno,\n}\n")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{},"rootUri":"file:///tmp/scls-test-watched"}))
+        .finish();
+    context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+
+    let requests = [
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-watched/main.md"}},"id":3}"#,
+        r#"{"jsonrpc":"2.0","method":"workspace/symbol","params":{"query":"ze"},"id":4}"#,
+    ];
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"ze","uri":"file:///tmp/scls-test-watched/main.md","version":0}}}"#,
+    ]).await?;
+
+    for (words, keys) in [("zephyr", "zeno"), ("zenith", "zeta")] {
+        context.send_all(&requests).await?;
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        assert_eq!(
+            items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+            vec![words]
+        );
+        let symbols = context.recv::<Vec<lsp_types::SymbolInformation>>().await?;
+        assert_eq!(
+            symbols.into_iter().map(|s| s.name).collect::<Vec<_>>(),
+            vec![keys]
+        );
+
+        std::fs::write(format!("{root}/.scls-words"), "zenith\n")?;
+        std::fs::write(format!("{root}/refs.bib"), "@book{zeta,\n}\n")?;
+        context.send_all(&[
+            r#"{"jsonrpc":"2.0","method":"workspace/didChangeWatchedFiles","params":{"changes":[{"uri":"file:///tmp/scls-test-watched/.scls-words","type":2},{"uri":"file:///tmp/scls-test-watched/refs.bib","type":2}]}}"#,
+        ]).await?;
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn folder_settings() -> anyhow::Result<()> {
+    let root = "/tmp/scls-test-folders";
+    let _ = std::fs::remove_dir_all(root);
+    std::fs::create_dir_all(format!("{root}/a"))?;
+    std::fs::create_dir_all(format!("{root}/b"))?;
+    std::fs::write(format!("{root}/a/.scls.toml"), "feature_words = false\n")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{},"workspaceFolders":[
+            {"uri":"file:///tmp/scls-test-folders/a","name":"a"},
+            {"uri":"file:///tmp/scls-test-folders/b","name":"b"},
+        ]}))
+        .finish();
+    context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"hello he","uri":"file:///tmp/scls-test-folders/a/main.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"hello he","uri":"file:///tmp/scls-test-folders/b/main.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":8,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-folders/a/main.md"}},"id":3}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":8,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-folders/b/main.md"}},"id":4}"#,
+    ]).await?;
+
+    for expected in [Vec::<&str>::new(), vec!["hello"]] {
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        assert_eq!(
+            items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    // settings file is read again on save, without watched files
+    std::fs::write(format!("{root}/b/.scls.toml"), "feature_words = false\n")?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"toml","text":"feature_words = false\n","uri":"file:///tmp/scls-test-folders/b/.scls.toml","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didSave","params":{"textDocument":{"uri":"file:///tmp/scls-test-folders/b/.scls.toml"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":8,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-folders/b/main.md"}},"id":5}"#,
+    ]).await?;
+
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    assert!(items.is_empty());
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn partial_results() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        vec![snippets::Snippet {
+            scope: None,
+            prefix: "hello_snippet".to_string(),
+            body: "hello".to_string(),
+            ..Default::default()
+        }],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"hello_word he","uri":"file:///tmp/partial.txt","version":0}}}"#,
+    ]).await?;
+
+    // identical request is streamed again, not answered by cached remainder
+    for id in [3, 4] {
+        context.send_all(&[
+            &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":13,"line":0}},"textDocument":{{"uri":"file:///tmp/partial.txt"}},"partialResultToken":"p1"}},"id":{id}}}"#),
+        ]).await?;
+
+        let mut partials = Vec::new();
+        let mut response = None;
+        while response.is_none() {
+            let chunk = context
+                .response_rx
+                .recv()
+                .await
+                .ok_or_else(|| anyhow::anyhow!("empty response"))?;
+            for payload in chunk
+                .split("Content-Length: ")
+                .filter_map(|m| m.split_once("\r\n\r\n"))
+                .map(|(_, payload)| payload)
+            {
+                let message = serde_json::from_str::<serde_json::Value>(payload)?;
+                match message["method"].as_str() {
+                    Some("$/progress") => {
+                        assert_eq!(message["params"]["token"], "p1");
+                        partials.push(
+                            message["params"]["value"]
+                                .as_array()
+                                .into_iter()
+                                .flatten()
+                                .map(|i| i["label"].as_str().unwrap_or_default().to_string())
+                                .collect::<Vec<_>>(),
+                        );
+                    }
+                    Some(_) => continue,
+                    None => response = Some(message),
+                }
+            }
+        }
+        let response = response.unwrap_or_default();
+
+        // words then snippets, each source by its own chunk
+        assert_eq!(partials, vec![vec!["hello_word"], vec!["hello_snippet"]]);
+        assert_eq!(response["result"], serde_json::json!([]));
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn abbreviations() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-abbreviations")?;
+    std::fs::write(
+        "/tmp/scls-test-abbreviations/abbreviations.toml",
+        "btw = \"by the way\"\nteh = \"the\"\n",
+    )?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_abbreviations":true,"abbreviations_path":"/tmp/scls-test-abbreviations/abbreviations.toml"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"Btw\nBTW","uri":"file:///tmp/abbreviations.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"btw","uri":"file:///tmp/abbreviations.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":3,"line":0},"textDocument":{"uri":"file:///tmp/abbreviations.md"}},"id":3}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":3,"line":1},"textDocument":{"uri":"file:///tmp/abbreviations.md"}},"id":4}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":3,"line":0},"textDocument":{"uri":"file:///tmp/abbreviations.rs"}},"id":5}"#,
+    ]).await?;
+
+    for expected in [vec!["By the way"], vec!["BY THE WAY"], Vec::new()] {
+        let lsp_types::CompletionResponse::Array(items) =
+            context.recv::<lsp_types::CompletionResponse>().await?
+        else {
+            anyhow::bail!("completion array expected")
+        };
+        assert_eq!(
+            items
+                .into_iter()
+                .filter(|i| i
+                    .detail
+                    .as_deref()
+                    .is_some_and(|d| d.starts_with("Abbreviation")))
+                .map(|i| i.label)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn paths() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test/sub-folder")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), "/tmp".to_string()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"/tmp/scls-test/","uri":"file:///tmp/main.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":15,"line":0},"textDocument":{"uri":"file:///tmp/main.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec!["/tmp/scls-test/sub-folder"]
+    );
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"/tmp/scls-test/su","uri":"file:///tmp/main2.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":17,"line":0},"textDocument":{"uri":"file:///tmp/main2.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec!["/tmp/scls-test/sub-folder"]
+    );
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"~/scls-test/su","uri":"file:///tmp/main3.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":14,"line":0},"textDocument":{"uri":"file:///tmp/main3.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec!["~/scls-test/sub-folder"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn commit_characters() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-commit/folder")?;
+    std::fs::write("/tmp/scls-test-commit/file.txt", "")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), "/tmp".to_string()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"commit_characters":{"paths":["/"],"words":["."]}}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"fileinput\n/tmp/scls-test-commit/f","uri":"file:///tmp/commit.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":23,"line":1},"textDocument":{"uri":"file:///tmp/commit.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    // files are never continued by `/`
+    let mut items = items
+        .into_iter()
+        .map(|i| (i.label, i.commit_characters.unwrap_or_default()))
+        .collect::<Vec<_>>();
+    items.sort();
+    assert_eq!(
+        items,
+        vec![
+            ("/tmp/scls-test-commit/file.txt".to_string(), Vec::new()),
+            (
+                "/tmp/scls-test-commit/folder".to_string(),
+                vec!["/".to_string()]
+            ),
+            ("fileinput".to_string(), vec![".".to_string()]),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn datetime() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"da","uri":"file:///tmp/main.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/main.md"}},"id":2}"#
+    ]).await?;
+
+    // part of trigger word gives nothing
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    assert!(items
+        .iter()
+        .all(|i| i.filter_text.as_deref() != Some("date")));
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"contentChanges":[{"range":{"end":{"character":2,"line":0},"start":{"character":0,"line":0}},"text":"Date"}],"textDocument":{"uri":"file:///tmp/main.md","version":1}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":4,"line":0},"textDocument":{"uri":"file:///tmp/main.md"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(items.len(), 5);
+    assert!(items
+        .iter()
+        .all(|i| i.filter_text.as_deref() == Some("date")));
+    assert_eq!(
+        items[0].label,
+        chrono::Local::now().format("%Y-%m-%d").to_string()
+    );
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"contentChanges":[{"range":{"end":{"character":4,"line":0},"start":{"character":0,"line":0}},"text":"ts"}],"textDocument":{"uri":"file:///tmp/main.md","version":2}}}"#,
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"datetime_formats":["%s"]}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/main.md"}},"id":4}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].filter_text.as_deref(), Some("ts"));
+    let epoch = items[0].label.parse::<i64>()?;
+    assert!((chrono::Utc::now().timestamp() - epoch).abs() < 5);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn generators() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_generators":true}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"uu\nrand8","uri":"file:///tmp/main.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/main.md"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    let uuid = items
+        .iter()
+        .find(|i| i.filter_text.as_deref() == Some("uuid4"))
+        .map(|i| i.label.clone())
+        .unwrap_or_default();
+    assert_eq!(uuid.len(), 36);
+    assert_eq!(
+        uuid.split('-').map(str::len).collect::<Vec<_>>(),
+        vec![8, 4, 4, 4, 12]
+    );
+    assert_eq!(uuid.chars().nth(14), Some('4'));
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":5,"line":1},"textDocument":{"uri":"file:///tmp/main.md"}},"id":4}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    let random = items
+        .iter()
+        .find(|i| i.filter_text.as_deref() == Some("rand8"))
+        .map(|i| i.label.clone())
+        .unwrap_or_default();
+    assert_eq!(random.len(), 8);
+    assert!(random.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn case_styles() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_case_styles":true,"feature_words":false}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"yaml","text":"key: parseHTTP-request","uri":"file:///tmp/main.yaml","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":22,"line":0},"textDocument":{"uri":"file:///tmp/main.yaml"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    let items = items
+        .into_iter()
+        .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::TEXT))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        items
+            .iter()
+            .map(|i| (i.detail.as_deref().unwrap_or_default(), i.label.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("camelCase", "parseHttpRequest"),
+            ("PascalCase", "ParseHttpRequest"),
+            ("snake_case", "parse_http_request"),
+            ("kebab-case", "parse-http-request"),
+            ("SCREAMING_SNAKE_CASE", "PARSE_HTTP_REQUEST"),
+        ]
+    );
+    let Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) = &items[0].text_edit else {
+        anyhow::bail!("text edit expected")
+    };
+    assert_eq!(te.replace.start.character, 5);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn cli_flags() -> anyhow::Result<()> {
+    let dir = "/tmp/scls-test-cli-flags";
+    std::fs::create_dir_all(dir)?;
+    let tool = format!("{dir}/mytool");
+    std::fs::write(
+        &tool,
+        "#!/bin/sh\ncat <<EOF\nUsage: mytool [OPTION]...\n  -v, --verbose       explain what is being done\n      --version       output version information\n  -o, --output=FILE   write to FILE\nEOF\n",
+    )?;
+    std::process::Command::new("chmod")
+        .args(["+x", &tool])
+        .status()?;
+
+    // allowed commands are found by PATH only
+    let path = std::env::var("PATH").unwrap_or_default();
+    if !path.split(':').any(|p| p == dir) {
+        std::env::set_var("PATH", format!("{dir}:{path}"));
+    }
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_cli_flags":true,"cli_flags_commands":["mytool"],"require_workspace_trust":false}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"bash","text":"cd /tmp && mytool --ver\n./mytool --ver\n/tmp/scls-test-cli-flags/mytool --ver","uri":"file:///tmp/scls-test-cli-flags/main.sh","version":0}}}"#,
+    ]).await?;
+
+    // commands with path are never run
+    for (request, expected) in [
+        (
+            r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":23,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-cli-flags/main.sh"}},"id":3}"#,
+            vec![
+                ("--verbose", "explain what is being done"),
+                ("--version", "output version information"),
+            ],
+        ),
+        (
+            r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":14,"line":1},"textDocument":{"uri":"file:///tmp/scls-test-cli-flags/main.sh"}},"id":3}"#,
+            Vec::new(),
+        ),
+        (
+            r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":36,"line":2},"textDocument":{"uri":"file:///tmp/scls-test-cli-flags/main.sh"}},"id":3}"#,
+            Vec::new(),
+        ),
+    ] {
+        // help is run in background
+        let items = complete_loaded(&mut context, request).await?;
+
+        assert_eq!(
+            items
+                .iter()
+                .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::PROPERTY))
+                .map(|i| (i.label.as_str(), i.detail.as_deref().unwrap_or_default()))
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn comment_tags() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"variables":{"user":"jane"}}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"let x = 1; // to\nlet to","uri":"file:///tmp/main.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":16,"line":0},"textDocument":{"uri":"file:///tmp/main.rs"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    assert_eq!(
+        items
+            .iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::KEYWORD))
+            .map(|i| i.label.as_str())
+            .collect::<Vec<_>>(),
+        vec!["TODO", "TODO(jane):"]
+    );
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":6,"line":1},"textDocument":{"uri":"file:///tmp/main.rs"}},"id":4}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    assert!(!items
+        .iter()
+        .any(|i| i.kind == Some(lsp_types::CompletionItemKind::KEYWORD)));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn mentions() -> anyhow::Result<()> {
+    let dir = "/tmp/scls-test-mentions";
+    std::fs::create_dir_all(format!("{dir}/.git"))?;
+    std::fs::create_dir_all(format!("{dir}/.github"))?;
+    std::fs::write(
+        format!("{dir}/.github/CODEOWNERS"),
+        "# owners\n* @jane\n/docs/ @jane @org/docs-team # docs\n",
+    )?;
+    std::fs::write(
+        format!("{dir}/people.txt"),
+        "# team\n@jdoe Jane Doe\njohn John Smith\n",
+    )?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_mentions":true,"mention_source":{"people_path":"/tmp/scls-test-mentions/people.txt"}}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"Thanks @j","uri":"file:///tmp/scls-test-mentions/docs/main.md","version":0}}}"#,
+    ]).await?;
+
+    // files are read in background
+    let request = r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":9,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-mentions/docs/main.md"}},"id":3}"#;
+    let items = complete_loaded(&mut context, request).await?;
+    assert_eq!(
+        items
+            .iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| (i.label.as_str(), i.detail.as_deref().unwrap_or_default()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("@jdoe", "Jane Doe"),
+            ("@john", "John Smith"),
+            ("@jane", ""),
+        ]
+    );
+
+    // saved people file is read again
+    std::fs::write(format!("{dir}/people.txt"), "jim Jim Beam\n")?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didSave","params":{"textDocument":{"uri":"file:///tmp/scls-test-mentions/people.txt"}}}"#,
+    ]).await?;
+    let mut labels = Vec::new();
+    for _ in 0..50 {
+        labels = complete_loaded(&mut context, request)
+            .await?
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| i.label)
+            .collect::<Vec<_>>();
+        if labels.contains(&"@jim".to_string()) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(labels, vec!["@jim", "@jane"]);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn markdown_code_blocks() -> anyhow::Result<()> {
+    let dir = "/tmp/scls-test-code-blocks";
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(format!("{dir}/people.txt"), "jdoe Jane Doe\n")?;
+
+    let mut context = TestContext::new(
+        vec![snippets::Snippet {
+            scope: Some(vec!["python".to_string()]),
+            prefix: "ma".to_string(),
+            body: "def main(): pass".to_string(),
+            ..Default::default()
+        }],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_mentions":true,"mention_source":{"people_path":"/tmp/scls-test-code-blocks/people.txt","codeowners":false}}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"Thanks @j\n```Py\nx = @j\nma\n```\nafter @j ma","uri":"file:///tmp/scls-test-code-blocks/main.md","version":0}}}"#,
+    ]).await?;
+
+    // people file is read in background
+    let mut mentions = Vec::new();
+    for (line, character) in [(0, 9), (2, 6), (5, 8)] {
+        let items = complete_loaded(
+            &mut context,
+            &format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":{character},"line":{line}}},"textDocument":{{"uri":"file:///tmp/scls-test-code-blocks/main.md"}}}},"id":3}}"#,
+            ),
+        )
+        .await?;
+        mentions.push(items.iter().filter(|i| i.label == "@jdoe").count());
+    }
+    assert_eq!(mentions, vec![1, 0, 1]);
+
+    // snippets of code block language
+    let mut snippets = Vec::new();
+    for (id, (line, character)) in [(3, 2), (5, 11)].into_iter().enumerate() {
+        context
+            .send(&jsonrpc::Request::from_str(&format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":{character},"line":{line}}},"textDocument":{{"uri":"file:///tmp/scls-test-code-blocks/main.md"}}}},"id":{}}}"#,
+                id + 10
+            ))?)
+            .await?;
+        let response = context.recv::<lsp_types::CompletionResponse>().await?;
+        let lsp_types::CompletionResponse::Array(items) = response else {
+            anyhow::bail!("completion array expected")
+        };
+        snippets.push(
+            items
+                .iter()
+                .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::SNIPPET))
+                .count(),
+        );
+    }
+    assert_eq!(snippets, vec![1, 0]);
+
+    // closing fence removed, block is not closed now
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"contentChanges":[{"range":{"end":{"character":0,"line":5},"start":{"character":0,"line":4}},"text":""}],"textDocument":{"uri":"file:///tmp/scls-test-code-blocks/main.md","version":1}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":8,"line":4},"textDocument":{"uri":"file:///tmp/scls-test-code-blocks/main.md"}},"id":6}"#
+    ]).await?;
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    assert!(!items.iter().any(|i| i.label == "@jdoe"));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn acceptance_stats() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"rust","text":"alpha alphabet alps\nal","uri":"file:///tmp/scls-test-acceptance/main.rs","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/scls-test-acceptance/main.rs"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    let item = items
+        .iter()
+        .find(|i| i.label == "alps")
+        .ok_or_else(|| anyhow::anyhow!("word expected"))?;
+    assert_eq!(item.sort_text, None);
+    let command = item
+        .command
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("command expected"))?;
+    assert_eq!(command.command, "scls.completionAccepted");
+    assert_eq!(
+        command.arguments,
+        Some(vec![
+            "/tmp/scls-test-acceptance".into(),
+            "rust".into(),
+            "alps".into()
+        ])
+    );
+
+    context.send_all(&[
+        &serde_json::json!({"jsonrpc":"2.0","method":"workspace/executeCommand","params":command,"id":4}).to_string(),
+    ]).await?;
+    context.recv::<Option<serde_json::Value>>().await?;
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/scls-test-acceptance/main.rs"}},"id":5}"#
+    ]).await?;
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+    let mut items = items
+        .into_iter()
+        .map(|i| (i.sort_text.unwrap_or(i.label.clone()), i.label))
+        .collect::<Vec<_>>();
+    items.sort();
+    assert_eq!(items[0].1, "alps");
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn completion_cache() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_generators":true}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"uu","uri":"file:///tmp/main.md","version":0}}}"#,
+    ]).await?;
+
+    let mut uuids = Vec::new();
+    for (id, change) in [
+        (3, None),
+        (4, None),
+        (
+            5,
+            Some(
+                r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/main.md","version":1},"contentChanges":[{"text":"uu"}]}}"#,
+            ),
+        ),
+    ] {
+        if let Some(change) = change {
+            context.send_all(&[change]).await?;
+        }
+        context.send_all(&[
+            &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":2,"line":0}},"textDocument":{{"uri":"file:///tmp/main.md"}}}},"id":{id}}}"#),
+        ]).await?;
+        let response = context.recv::<lsp_types::CompletionResponse>().await?;
+        let lsp_types::CompletionResponse::Array(items) = response else {
+            anyhow::bail!("completion array expected")
+        };
+        uuids.push(
+            items
+                .into_iter()
+                .find(|i| i.filter_text.as_deref() == Some("uuid4"))
+                .map(|i| i.label)
+                .ok_or_else(|| anyhow::anyhow!("uuid expected"))?,
+        );
+    }
+
+    // repeated request is served from cache, new document version is recomputed
+    assert_eq!(uuids[0], uuids[1]);
+    assert_ne!(uuids[1], uuids[2]);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn colors() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"css","text":"a { color: #ff0000; }\nb { color: #f","uri":"file:///tmp/main.css","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":13,"line":1},"textDocument":{"uri":"file:///tmp/main.css"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::COLOR))
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec!["#ff0000", "#b22222", "#fffaf0", "#228b22", "#ff00ff"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn git_refs() -> anyhow::Result<()> {
+    let repo = "/tmp/scls-test-git-refs";
+    init_git_repo(repo, &["init"])?;
+    git(repo, &["branch", "feature/completion"])?;
+    git(repo, &["tag", "v0.1.0"])?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"git-commit","text":"Merge branch 'fea","uri":"file:///tmp/scls-test-git-refs/.git/COMMIT_EDITMSG","version":0}}}"#,
+    ]).await?;
+
+    let request = r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":17,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-git-refs/.git/COMMIT_EDITMSG"}},"id":2}"#;
+    // git is run in background, client is asked to complete again
+    context.send_all(&[request]).await?;
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+    let lsp_types::CompletionResponse::List(list) = response else {
+        anyhow::bail!("incomplete completion list expected")
+    };
+    assert!(list.is_incomplete);
+
+    let items = complete_loaded(&mut context, request).await?;
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| i.label)
+            .collect::<Vec<_>>(),
+        vec!["feature/completion"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn git_commit() -> anyhow::Result<()> {
+    let repo = "/tmp/scls-test-git-commit";
+    init_git_repo(
+        repo,
+        &[
+            "feat(parser): add",
+            "fix(parser): fix",
+            "docs(readme): update",
+        ],
+    )?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"git-commit","text":"fi\n","uri":"file:///tmp/scls-test-git-commit/.git/COMMIT_EDITMSG","version":0}}}"#,
+    ]).await?;
+
+    let items = complete_loaded(
+        &mut context,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-git-commit/.git/COMMIT_EDITMSG"}},"id":3}"#,
+    ).await?;
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::KEYWORD))
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec!["fix: ", "fix(parser): ", "fix(readme): "]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn git_trailers() -> anyhow::Result<()> {
+    let repo = "/tmp/scls-test-git-trailers";
+    init_git_repo(repo, &["init"])?;
+    git(
+        repo,
+        &[
+            "commit",
+            "-q",
+            "--allow-empty",
+            "--author",
+            "Jane Doe <jane@localhost>",
+            "-m",
+            "second",
+        ],
+    )?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"git-commit","text":"fix: typo\n\nCo-authored-by: ja","uri":"file:///tmp/scls-test-git-trailers/.git/COMMIT_EDITMSG","version":0}}}"#,
+    ]).await?;
+
+    let items = complete_loaded(
+        &mut context,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":18,"line":2},"textDocument":{"uri":"file:///tmp/scls-test-git-trailers/.git/COMMIT_EDITMSG"}},"id":3}"#,
+    ).await?;
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec![" Jane Doe <jane@localhost>"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn issues() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"issue_source":{"command":"echo '[{\"number\":12,\"title\":\"Fix parser\"},{\"number\":21,\"title\":\"Add docs\"}]'","languages":["markdown"]},"require_workspace_trust":false}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"closes #1","uri":"file:///tmp/issues.md","version":0}}}"#,
+    ]).await?;
+
+    // command is run in background
+    let items = complete_loaded(
+        &mut context,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":9,"line":0},"textDocument":{"uri":"file:///tmp/issues.md"}},"id":3}"#,
+    ).await?;
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| (i.label, i.detail.unwrap_or_default()))
+            .collect::<Vec<_>>(),
+        vec![("#12".to_string(), "Fix parser".to_string())]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn background_sources() -> anyhow::Result<()> {
+    let load_sources = || simple_completion_language_server::LoadedSources {
+        snippets: vec![snippets::Snippet {
+            prefix: "hello".to_string(),
+            body: "hello world".to_string(),
+            ..Default::default()
+        }],
+        problems: vec!["broken snippets".to_string()],
+        ..Default::default()
+    };
+    let mut context = TestContext::with_options(
+        Vec::new(),
+        HashMap::new(),
+        String::new(),
+        server::ServerOptions {
+            load_sources: Some(std::sync::Arc::new(load_sources)),
+            ..Default::default()
+        },
+    )
+    .await?;
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{"window":{"workDoneProgress":true}}}))
+        .finish();
+    let _ = context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"hel","uri":"file:///tmp/background.txt","version":0}}}"#,
+    ]).await?;
+
+    let completion = |id: u32| {
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":3,"line":0}},"textDocument":{{"uri":"file:///tmp/background.txt"}}}},"id":{id}}}"#
+        )
+    };
+    let has_snippet = |value: &serde_json::Value| {
+        value["result"]
+            .as_array()
+            .is_some_and(|items| items.iter().any(|i| i["label"] == "hello"))
+    };
+
+    // loading waits for progress token, server answers with empty sources meanwhile
+    let mut messages = Vec::new();
+    let mut loaded = false;
+    let mut problems = false;
+    while !(loaded && problems) {
+        let chunk = context
+            .response_rx
+            .recv()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("empty response"))?;
+        for payload in chunk
+            .split("Content-Length: ")
+            .filter_map(|m| m.split_once("\r\n\r\n"))
+            .map(|(_, payload)| payload)
+        {
+            let value = serde_json::from_str::<serde_json::Value>(payload)?;
+            match value["method"].as_str() {
+                Some("window/workDoneProgress/create") => {
+                    context.send_all(&[&completion(2)]).await?;
+                    let answer =
+                        serde_json::json!({"jsonrpc":"2.0","id":value["id"],"result":null});
+                    context
+                        .request_tx
+                        .send(encode_message(None, &answer.to_string()))?;
+                }
+                Some("$/progress") => messages.push(value["params"]["value"].clone()),
+                Some("window/showMessage") => {
+                    problems = value["params"]["message"]
+                        .as_str()
+                        .is_some_and(|m| m.contains("broken snippets"));
+                }
+                Some(_) => continue,
+                None if value["id"] == 2 => assert!(!has_snippet(&value)),
+                None => {}
+            }
+        }
+        loaded = messages.iter().any(|m| m["kind"] == "end");
+    }
+    assert_eq!(messages[0]["kind"], "begin");
+    assert_eq!(
+        messages[1]["message"],
+        "1 snippets, 0 unicode inputs, 0 words"
+    );
+
+    let lsp_types::CompletionResponse::Array(items) = context
+        .request::<lsp_types::CompletionResponse>(&jsonrpc::Request::from_str(&completion(3))?)
+        .await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    assert!(items.iter().any(|i| i.label == "hello"));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn reload_sources() -> anyhow::Result<()> {
+    let root = "/tmp/scls-test-reload-sources";
+    let _ = std::fs::remove_dir_all(root);
+    std::fs::create_dir_all(root)?;
+    std::fs::write(format!("{root}/snippets.txt"), "hello")?;
+
+    let load_sources = move || simple_completion_language_server::LoadedSources {
+        snippets: std::fs::read_to_string(format!("{root}/snippets.txt"))
+            .unwrap_or_default()
+            .lines()
+            .map(|prefix| snippets::Snippet {
+                prefix: prefix.to_string(),
+                body: format!("{prefix} world"),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let mut context = TestContext::with_options(
+        Vec::new(),
+        HashMap::new(),
+        String::new(),
+        server::ServerOptions {
+            load_sources: Some(std::sync::Arc::new(load_sources)),
+            sources_paths: vec![std::path::PathBuf::from(root)],
+            ..Default::default()
+        },
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"he","uri":"file:///tmp/reload.txt","version":0}}}"#,
+    ]).await?;
+
+    let mut id = 2;
+    for expected in ["hello", "help"] {
+        // sources are loaded in background
+        let mut labels = Vec::new();
+        for _ in 0..50 {
+            context.send_all(&[
+                &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":2,"line":0}},"textDocument":{{"uri":"file:///tmp/reload.txt"}}}},"id":{id}}}"#),
+            ]).await?;
+            id += 1;
+            let lsp_types::CompletionResponse::Array(items) =
+                context.recv::<lsp_types::CompletionResponse>().await?
+            else {
+                anyhow::bail!("completion array expected")
+            };
+            labels = items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+            if labels.iter().any(|l| l == expected) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(labels, vec![expected]);
+
+        std::fs::write(format!("{root}/snippets.txt"), "help")?;
+        context.send_all(&[
+            r#"{"jsonrpc":"2.0","method":"workspace/didChangeWatchedFiles","params":{"changes":[{"uri":"file:///tmp/scls-test-reload-sources/snippets.txt","type":2}]}}"#,
+        ]).await?;
+    }
+
+    std::fs::remove_dir_all(root)?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn watch_config() -> anyhow::Result<()> {
+    let root = "/tmp/scls-test-watch-config";
+    let _ = std::fs::remove_dir_all(root);
+    std::fs::create_dir_all(root)?;
+    std::fs::write(format!("{root}/snippets.txt"), "hello")?;
+
+    let load_sources = move || simple_completion_language_server::LoadedSources {
+        snippets: std::fs::read_to_string(format!("{root}/snippets.txt"))
+            .unwrap_or_default()
+            .lines()
+            .map(|prefix| snippets::Snippet {
+                prefix: prefix.to_string(),
+                body: format!("{prefix} world"),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let mut context = TestContext::with_options(
+        Vec::new(),
+        HashMap::new(),
+        String::new(),
+        server::ServerOptions {
+            load_sources: Some(std::sync::Arc::new(load_sources)),
+            sources_paths: vec![std::path::PathBuf::from(root)],
+            ..Default::default()
+        },
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"watch_config":true}}}"#,
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"he","uri":"file:///tmp/watch-config.txt","version":0}}}"#,
+    ]).await?;
+
+    // client reports nothing, file is changed on disk only
+    let mut id = 2;
+    for expected in ["hello", "help"] {
+        let mut labels = Vec::new();
+        for _ in 0..100 {
+            context.send_all(&[
+                &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":2,"line":0}},"textDocument":{{"uri":"file:///tmp/watch-config.txt"}}}},"id":{id}}}"#),
+            ]).await?;
+            id += 1;
+            let lsp_types::CompletionResponse::Array(items) =
+                context.recv::<lsp_types::CompletionResponse>().await?
+            else {
+                anyhow::bail!("completion array expected")
+            };
+            labels = items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+            if labels.iter().any(|l| l == expected) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(labels, vec![expected]);
+
+        std::fs::write(format!("{root}/snippets.txt"), "help")?;
+    }
+
+    std::fs::remove_dir_all(root)?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn workspace_trust() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-trust")?;
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"command_sources":[{"trigger":"!","command":"printf 'mail/work\\n'"},{"trigger":"!","command":"echo mail/blocked"}],"command_allowlist":["printf"]}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"!mail","uri":"file:///tmp/scls-test-trust/commands.txt","version":0}}}"#,
+    ]).await?;
+
+    let completion = |id: u32| {
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":5,"line":0}},"textDocument":{{"uri":"file:///tmp/scls-test-trust/commands.txt"}}}},"id":{id}}}"#
+        )
+    };
+    let labels = |items: Vec<lsp_types::CompletionItem>| {
+        items
+            .into_iter()
+            .filter(|i| i.label.starts_with("mail/"))
+            .map(|i| i.label)
+            .collect::<Vec<_>>()
+    };
+
+    // first command of workspace asks user, nothing runs meanwhile
+    context.send_all(&[&completion(3)]).await?;
+    let mut prompt = None;
+    let mut items = None;
+    while prompt.is_none() || items.is_none() {
+        let messages = context
+            .response_rx
+            .recv()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("empty response"))?;
+        // several messages could be written at once
+        for payload in messages
+            .split("Content-Length: ")
+            .filter_map(|m| m.split_once("\r\n\r\n"))
+            .map(|(_, payload)| payload)
+        {
+            let value = serde_json::from_str::<serde_json::Value>(payload)?;
+            match value["method"].as_str() {
+                Some("window/showMessageRequest") => {
+                    assert!(value["params"]["message"]
+                        .as_str()
+                        .is_some_and(|m| m.contains("/tmp/scls-test-trust")));
+                    prompt = Some(value["id"].clone());
+                }
+                Some(_) => continue,
+                None => {
+                    items = Some(serde_json::from_value::<Vec<lsp_types::CompletionItem>>(
+                        value["result"].clone(),
+                    )?)
+                }
+            }
+        }
+    }
+    assert!(labels(items.unwrap_or_default()).is_empty());
+
+    let answer = serde_json::json!({"jsonrpc":"2.0","id":prompt,"result":{"title":"Allow"}});
+    context
+        .request_tx
+        .send(encode_message(None, &answer.to_string()))?;
+
+    // answer is handled by backend in background, commands out of allowlist never run
+    let mut trusted = Vec::new();
+    for id in 4..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        context.send_all(&[&completion(id)]).await?;
+        trusted = labels(complete_loaded(&mut context, &completion(id)).await?);
+        if !trusted.is_empty() {
+            break;
+        }
+    }
+    assert_eq!(trusted, vec!["mail/work"]);
+
+    Ok(())
+}
+
+#[test]
+fn command_policy() -> anyhow::Result<()> {
+    use simple_completion_language_server::command;
+
+    assert_eq!(command::program("gh issue list --json number"), "gh");
+    assert_eq!(command::program("./bin/mytool --help 2>&1"), "mytool");
+    assert!(command::is_allowed("gh issue list", None));
+    assert!(command::is_allowed(
+        "gh issue list",
+        Some(&["gh".to_string()])
+    ));
+    assert!(!command::is_allowed(
+        "curl example.com",
+        Some(&["gh".to_string()])
+    ));
+
+    // verbose command is stopped and its output is truncated
+    let output = command::run(
+        &std::env::temp_dir(),
+        "yes",
+        std::time::Duration::from_secs(5),
+    )?;
+    assert_eq!(output.len() as u64, command::MAX_OUTPUT_BYTES);
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn command_sources() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"command_sources":[{"trigger":"!","command":"printf 'mail/work\\nmail/home\\nbank\\n'"},{"trigger":"!","command":"sleep 5","timeout":50}],"require_workspace_trust":false}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"text","text":"!mail","uri":"file:///tmp/commands.txt","version":0}}}"#,
+    ]).await?;
+
+    // commands are run in background, timed out one gives nothing
+    let items = complete_loaded(
+        &mut context,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":5,"line":0},"textDocument":{"uri":"file:///tmp/commands.txt"}},"id":3}"#,
+    ).await?;
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec!["mail/work", "mail/home"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn gitmoji() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"git-commit","text":":spark","uri":"file:///tmp/gitmoji/COMMIT_EDITMSG","version":0}}}"#,
+    ]).await?;
+
+    // git refs are looked up in background
+    let items = complete_loaded(
+        &mut context,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":6,"line":0},"textDocument":{"uri":"file:///tmp/gitmoji/COMMIT_EDITMSG"}},"id":3}"#,
+    ).await?;
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.filter_text.as_deref() == Some(":sparkles:"))
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec!["✨"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn word_list() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-words")?;
+    std::fs::write(
+        "/tmp/scls-test-words/.scls-words",
+        "Kubernetes\tcontainer orchestration\nkubectl\nhelix\n",
+    )?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{},"rootUri":"file:///tmp/scls-test-words"}))
+        .finish();
+    context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"ku","uri":"file:///tmp/scls-test-words/main.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-words/main.md"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .map(|i| (i.label, i.detail))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "Kubernetes".to_string(),
+                Some("container orchestration".to_string())
+            ),
+            ("kubectl".to_string(), None)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn thesaurus() -> anyhow::Result<()> {
+    std::fs::write(
+        "/tmp/scls-test-thesaurus.txt",
+        "happy,glad,cheerful\n# comment\nglad;joyful (poetic);happy\n",
+    )?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"feature_thesaurus":true,"thesaurus_path":"/tmp/scls-test-thesaurus.txt"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"I am happy","uri":"file:///tmp/thesaurus.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":10,"line":0},"textDocument":{"uri":"file:///tmp/thesaurus.md"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["glad", "cheerful", "joyful"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn markdown_links() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"See [docs][he] and [site](https://example.com/site)\n\n[helix]: https://helix-editor.com\n[home]: https://example.com/home\n[docs]: https://ex","uri":"file:///tmp/links.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":13,"line":0},"textDocument":{"uri":"file:///tmp/links.md"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| i.label)
+            .collect::<Vec<_>>(),
+        vec!["helix"]
+    );
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":18,"line":4},"textDocument":{"uri":"file:///tmp/links.md"}},"id":4}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| i.label)
+            .collect::<Vec<_>>(),
+        vec!["https://example.com/site", "https://example.com/home"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn frontmatter_tags() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all("/tmp/scls-test-notes");
+    std::fs::create_dir_all("/tmp/scls-test-notes/daily")?;
+    std::fs::write(
+        "/tmp/scls-test-notes/a.md",
+        "---\ntitle: a\ntags: [rust, research]\n---\n",
+    )?;
+    std::fs::write(
+        "/tmp/scls-test-notes/daily/b.md",
+        "---\ntags:\n  - rust\n  - reading\n---\n",
+    )?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{},"rootUri":"file:///tmp/scls-test-notes"}))
+        .finish();
+    context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"---\ntags:\n  - r\n---\n","uri":"file:///tmp/scls-test-notes/c.md","version":0}}}"#,
+    ]).await?;
+
+    // notes are walked in background
+    let mut tags = Vec::new();
+    for id in 3..50 {
+        context.send_all(&[
+            &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":5,"line":2}},"textDocument":{{"uri":"file:///tmp/scls-test-notes/c.md"}}}},"id":{id}}}"#),
+        ]).await?;
+        let response = context.recv::<lsp_types::CompletionResponse>().await?;
+        let lsp_types::CompletionResponse::Array(items) = response else {
+            anyhow::bail!("completion array expected")
+        };
+        tags = items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::VALUE))
+            .map(|i| i.label)
+            .collect::<Vec<_>>();
+        if !tags.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    assert_eq!(tags, vec!["rust", "reading", "research"]);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn labels() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"latex","text":"\\section{Intro}\\label{sec:intro}\n\\begin{equation}\\label{eq:energy}\\end{equation}","uri":"file:///tmp/labels/intro.tex","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"latex","text":"See \\ref{sec:","uri":"file:///tmp/labels/main.tex","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":13,"line":0},"textDocument":{"uri":"file:///tmp/labels/main.tex"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| (i.label, i.detail.unwrap_or_default()))
+            .collect::<Vec<_>>(),
+        vec![("sec:intro".to_string(), "intro.tex".to_string())]
+    );
+
+    // labels are parsed again on incremental change
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/labels/intro.tex","version":1},"contentChanges":[{"range":{"start":{"line":0,"character":26},"end":{"line":0,"character":31}},"text":"overview"}]}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":13,"line":0},"textDocument":{"uri":"file:///tmp/labels/main.tex"}},"id":4}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| i.label)
+            .collect::<Vec<_>>(),
+        vec!["sec:overview"]
+    );
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"typst","text":"= Intro <intro>\nSee @in","uri":"file:///tmp/labels/main.typ","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":7,"line":1},"textDocument":{"uri":"file:///tmp/labels/main.typ"}},"id":5}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::REFERENCE))
+            .map(|i| i.label)
+            .collect::<Vec<_>>(),
+        vec!["intro"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn bibtex() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"bibtex","text":"@inpro\n@article{key,\n  tit\n  month = ja\n}","uri":"file:///tmp/refs.bib","version":0}}}"#,
+    ]).await?;
+
+    for (line, character, expected) in [
+        (0, 6, vec!["inproceedings"]),
+        (2, 5, vec!["title = "]),
+        (3, 12, vec!["jan"]),
+    ] {
+        context
+            .send(
+                &jsonrpc::Request::build("textDocument/completion")
+                    .id(3)
+                    .params(serde_json::json!({
+                        "position":{"character":character,"line":line},
+                        "textDocument":{"uri":"file:///tmp/refs.bib"}
+                    }))
+                    .finish(),
+            )
+            .await?;
+
+        let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+        let lsp_types::CompletionResponse::Array(items) = response else {
+            anyhow::bail!("completion array expected")
+        };
+
+        assert_eq!(
+            items
+                .into_iter()
+                .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::KEYWORD))
+                .filter_map(|i| match i.text_edit {
+                    Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => Some(te.new_text),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bibtex_entries() {
+    use simple_completion_language_server::bibtex;
+
+    let text = r#"@string{tug = "TeX Users Group"}
+@String(jcs = {Journal of } # "Computer" # { Science})
+
+@proceedings{tug84,
+  title = {Proceedings of } # tug,
+  year = 1984,
+}
+@xdata{knuth, author = {Donald E. Knuth}}
+@inproceedings{knuth1984,
+  title = {Literate Programming},
+  crossref = {tug84},
+  xdata = {knuth},
+}
+@article{short, title = "Short", journal = jcs, month = jan}
+@comment{@article{hidden, title = {Hidden}}}
+"#;
+    let entries = bibtex::entries(text);
+    assert_eq!(
+        entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.line))
+            .collect::<Vec<_>>(),
+        vec![("tug84", 3), ("knuth", 7), ("knuth1984", 8), ("short", 13)]
+    );
+    let field = |key: &str, name: &str| {
+        entries
+            .iter()
+            .find(|e| e.key == key)
+            .and_then(|e| e.fields.get(name).cloned())
+    };
+    assert_eq!(
+        field("knuth1984", "booktitle").as_deref(),
+        Some("Proceedings of TeX Users Group")
+    );
+    assert_eq!(
+        field("knuth1984", "title").as_deref(),
+        Some("Literate Programming")
+    );
+    assert_eq!(field("short", "month").as_deref(), Some("January"));
+    assert_eq!(
+        entries[2].summary().as_deref(),
+        Some("Literate Programming. In: Proceedings of TeX Users Group. Donald E. Knuth, 1984")
+    );
+    assert_eq!(
+        entries[3].summary().as_deref(),
+        Some("Short. In: Journal of Computer Science")
+    );
+}
+
+#[test]
+fn cli_complete() -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let mut child =
+        std::process::Command::new(env!("CARGO_BIN_EXE_simple-completion-language-server"))
+            .args([
+                "complete",
+                "--file",
+                "/tmp/scls-test-cli.md",
+                "--line",
+                "2",
+                "--col",
+                "3",
+                "--stdin",
+            ])
+            .env("SNIPPETS_PATH", "/tmp/scls-test-cli-none")
+            .env("EXTERNAL_SNIPPETS_CONFIG", "/tmp/scls-test-cli-none")
+            .env("UNICODE_INPUT_PATH", "/tmp/scls-test-cli-none")
+            .env("WORDS_PATH", "/tmp/scls-test-cli-none")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("stdin expected"))?
+        .write_all(b"hello\nhe")?;
+
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "hello\n");
+
+    Ok(())
+}
+
+#[test]
+fn cli_snippet_add() -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let snippets_path = "/tmp/scls-test-snippet-add";
+    let _ = std::fs::remove_dir_all(snippets_path);
+
+    let add = || -> anyhow::Result<bool> {
+        let mut child =
+            std::process::Command::new(env!("CARGO_BIN_EXE_simple-completion-language-server"))
+                .args([
+                    "snippet",
+                    "add",
+                    "--prefix",
+                    "ld",
+                    "--scope",
+                    "python",
+                    "--description",
+                    "log \"debug\"",
+                    "--body",
+                    "-",
+                ])
+                .env("SNIPPETS_PATH", snippets_path)
+                .stdin(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::null())
+                .spawn()?;
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("stdin expected"))?
+            .write_all(b"log.debug(\"$1\")\n\t'$0'\n")?;
+        Ok(child.wait()?.success())
+    };
+
+    assert!(add()?);
+    // duplicate prefix
+    assert!(!add()?);
+
+    let snippets =
+        snippets::config::load_snippets_from_path(&std::path::PathBuf::from(snippets_path), &None)?;
+    assert_eq!(snippets.len(), 1);
+    assert_eq!(snippets[0].prefix, "ld");
+    assert_eq!(snippets[0].body, "log.debug(\"$1\")\n\t'$0'");
+    assert_eq!(snippets[0].description.as_deref(), Some("log \"debug\""));
+    assert_eq!(snippets[0].scope, Some(vec!["python".to_string()]));
+
+    Ok(())
+}
+
+#[test]
+fn yaml_snippets() -> anyhow::Result<()> {
+    let snippets_path = std::path::PathBuf::from("/tmp/scls-test-yaml-snippets");
+    let _ = std::fs::remove_dir_all(&snippets_path);
+    std::fs::create_dir_all(&snippets_path)?;
+    std::fs::write(
+        snippets_path.join("python.yaml"),
+        "snippets:\n  - prefix: ld\n    body: log.debug(\"$1\")\n---\nprefix: li\nbody: log.info(\"$1\")\ndescription: log info\n",
+    )?;
+    std::fs::write(
+        snippets_path.join("snippets.yml"),
+        "snippets:\n  - prefix: todo\n    body: 'TODO: $1'\n",
+    )?;
+
+    let mut snippets = snippets::config::load_snippets_from_path(&snippets_path, &None)?
+        .into_iter()
+        .map(|s| (s.prefix, s.body, s.scope))
+        .collect::<Vec<_>>();
+    snippets.sort();
+    assert_eq!(
+        snippets,
+        vec![
+            (
+                "ld".to_string(),
+                "log.debug(\"$1\")".to_string(),
+                Some(vec!["python".to_string()])
+            ),
+            (
+                "li".to_string(),
+                "log.info(\"$1\")".to_string(),
+                Some(vec!["python".to_string()])
+            ),
+            ("todo".to_string(), "TODO: $1".to_string(), None),
+        ]
+    );
+
+    std::fs::remove_dir_all(&snippets_path)?;
+    Ok(())
+}
+
+#[test]
+fn validate_snippets_findings() -> anyhow::Result<()> {
+    use simple_completion_language_server::snippets::validate::{validate_snippets, FindingKind};
+    use simple_completion_language_server::StartOptions;
+
+    let snippets_path = std::path::PathBuf::from("/tmp/scls-test-validate-snippets");
+    let _ = std::fs::remove_dir_all(&snippets_path);
+    std::fs::create_dir_all(&snippets_path)?;
+    std::fs::write(
+        snippets_path.join("snippets.toml"),
+        "[[snippets]]\nprefix = \"fn\"\nscope = [\"rust\", \"pyhton\"]\nbody = \"fn\"\n",
+    )?;
+    std::fs::write(
+        snippets_path.join("rust.toml"),
+        "[[snippets]]\nprefix = \"fn\"\nbody = \"fn\"\n",
+    )?;
+    std::fs::write(snippets_path.join("zig.toml"), "[[snippets]]\nprefix = 1\n")?;
+    std::fs::write(
+        snippets_path.join("zz.yaml"),
+        "snippets:\n  - prefix: [ld\n",
+    )?;
+
+    let findings = validate_snippets(&StartOptions {
+        home_dir: "/tmp".to_string(),
+        snippets_paths: vec![snippets_path],
+        external_snippets_config_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        unicode_input_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        words_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        snippets_usage_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        acceptance_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        warm_cache_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+    })?;
+
+    let kinds = findings.iter().map(|f| &f.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            &FindingKind::DuplicatePrefix,
+            &FindingKind::UnknownScope,
+            &FindingKind::ParseError,
+            &FindingKind::ParseError
+        ]
+    );
+    assert!(findings[1].message.contains("did you mean 'python'"));
+    assert_eq!((findings[2].line, findings[2].column), (Some(2), Some(10)));
+    assert_eq!(findings[3].line, Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn snippets_search_paths() -> anyhow::Result<()> {
+    use simple_completion_language_server::snippets::config::load_snippets;
+    use simple_completion_language_server::snippets::validate::validate_snippets;
+    use simple_completion_language_server::StartOptions;
+
+    let base_path = std::path::PathBuf::from("/tmp/scls-test-snippets-paths");
+    let _ = std::fs::remove_dir_all(&base_path);
+    let (data, team) = (base_path.join("data"), base_path.join("team"));
+    std::fs::create_dir_all(&data)?;
+    std::fs::create_dir_all(&team)?;
+    std::fs::write(
+        data.join("rust.toml"),
+        "[[snippets]]\nprefix = \"fn\"\nbody = \"fn data\"\n[[snippets]]\nprefix = \"ok\"\nbody = \"Ok(())\"\n",
+    )?;
+    std::fs::write(
+        team.join("rust.toml"),
+        "[[snippets]]\nprefix = \"fn\"\nbody = \"fn team\"\n",
+    )?;
+    std::fs::write(
+        team.join("python.toml"),
+        "[[snippets]]\nprefix = \"fn\"\nbody = \"def\"\n",
+    )?;
+
+    let start_options = StartOptions {
+        home_dir: "/tmp".to_string(),
+        snippets_paths: vec![data, base_path.join("missed"), team],
+        external_snippets_config_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        unicode_input_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        words_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        snippets_usage_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        acceptance_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        warm_cache_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+    };
+
+    let mut snippets = load_snippets(&start_options)?
+        .into_iter()
+        .map(|s| (s.prefix, s.body))
+        .collect::<Vec<_>>();
+    snippets.sort();
+    assert_eq!(
+        snippets,
+        vec![
+            ("fn".to_string(), "def".to_string()),
+            ("fn".to_string(), "fn team".to_string()),
+            ("ok".to_string(), "Ok(())".to_string()),
+        ]
+    );
+    // overrides of later paths are not reported as duplicates
+    assert!(validate_snippets(&start_options)?.is_empty());
+
+    std::fs::remove_dir_all(&base_path)?;
+    Ok(())
+}
+
+#[test]
+fn external_wordlists() -> anyhow::Result<()> {
+    use simple_completion_language_server::snippets::config::{load_external_words, load_snippets};
+    use simple_completion_language_server::StartOptions;
+    use std::io::Write;
+
+    let base_path = std::path::PathBuf::from("/tmp/scls-test-external-words");
+    let _ = std::fs::remove_dir_all(&base_path);
+    let lists_path = base_path.join("external-snippets/example.com/words.git/lists");
+    std::fs::create_dir_all(&lists_path)?;
+    std::fs::write(
+        base_path.join("external-snippets.toml"),
+        "[[sources]]\ngit = \"https://example.com/words.git\"\n[[sources.paths]]\npath = \"lists\"\n",
+    )?;
+    std::fs::write(
+        lists_path.join("countries.txt"),
+        "Norway\n# comment\nPeru\n",
+    )?;
+    let mut encoder = flate2::write::GzEncoder::new(
+        std::fs::File::create(lists_path.join("medical.txt.gz"))?,
+        flate2::Compression::default(),
+    );
+    encoder.write_all(b"tachycardia\tfast heart rate\n")?;
+    encoder.finish()?;
+    std::fs::write(
+        lists_path.join("markdown.toml"),
+        "[[snippets]]\nprefix = \"h1\"\nbody = \"# $1\"\n",
+    )?;
+
+    let start_options = StartOptions {
+        home_dir: "/tmp".to_string(),
+        snippets_paths: vec![std::path::PathBuf::from("/tmp/scls-not-exists")],
+        external_snippets_config_path: base_path.join("external-snippets.toml"),
+        unicode_input_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        words_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        snippets_usage_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        acceptance_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        warm_cache_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+    };
+
+    let words = load_external_words(&start_options)?;
+    assert_eq!(
+        words
+            .iter()
+            .map(|w| (w.word.as_str(), w.description.as_deref()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Norway", None),
+            ("Peru", None),
+            ("tachycardia", Some("fast heart rate")),
+        ]
+    );
+    // word lists are not loaded as snippets
+    let snippets = load_snippets(&start_options)?;
+    assert_eq!(
+        snippets
+            .iter()
+            .map(|s| s.prefix.as_str())
+            .collect::<Vec<_>>(),
+        vec!["h1"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tmux_words() {
+    use simple_completion_language_server::tmux::words;
+
+    let captured = "$ cargo test\nerror[E0432]: unresolved import `tokio::net`\n  --> src/metrics.rs:4:5\n$ cargo test";
+    assert_eq!(
+        words(captured),
+        vec![
+            "cargo",
+            "test",
+            "src",
+            "metrics",
+            "error",
+            "E0432",
+            "unresolved",
+            "import",
+            "tokio",
+            "net"
+        ]
+    );
+}
+
+#[test]
+fn startup_self_check() -> anyhow::Result<()> {
+    use simple_completion_language_server::selfcheck::check_start_options;
+    use simple_completion_language_server::StartOptions;
+
+    let config_dir = std::path::PathBuf::from("/tmp/scls-test-self-check");
+    let _ = std::fs::remove_dir_all(&config_dir);
+    std::fs::create_dir_all(config_dir.join("snippets"))?;
+    std::fs::create_dir_all(config_dir.join("unicode-input"))?;
+    std::fs::write(
+        config_dir.join("snippets/rust.toml"),
+        "[[snippets]]\nprefix = 1\n",
+    )?;
+    std::fs::write(config_dir.join("unicode-input/base.toml"), "alpha = \n")?;
+    std::fs::write(
+        config_dir.join("external-snippets.toml"),
+        "[[sources]]\nname = \"missing\"\ngit = \"https://example.com/missing.git\"\npaths = []\n",
+    )?;
+
+    let problems = check_start_options(&StartOptions {
+        home_dir: "/tmp".to_string(),
+        snippets_paths: vec![config_dir.join("snippets")],
+        external_snippets_config_path: config_dir.join("external-snippets.toml"),
+        unicode_input_path: config_dir.join("unicode-input"),
+        words_path: config_dir.join("words"),
+        snippets_usage_path: config_dir.join("snippets-usage.json"),
+        acceptance_path: config_dir.join("acceptance.json"),
+        warm_cache_path: config_dir.join("warm-cache.json"),
+    });
+
+    assert!(problems[0].starts_with("Failed to parse snippets"));
+    assert!(problems[1].starts_with("Failed to parse unicode input"));
+    assert!(problems
+        .last()
+        .is_some_and(|p| p.starts_with("External snippets 'missing' are not fetched")));
+
+    Ok(())
+}
+
+#[test]
+fn snippets_includes() {
+    use simple_completion_language_server::snippets::config::resolve_includes;
+
+    let snippet = |prefix: &str, scope: Option<&str>, body: &str| snippets::Snippet {
+        scope: scope.map(|s| vec![s.to_string()]),
+        prefix: prefix.to_string(),
+        body: body.to_string(),
+        description: None,
+        ..Default::default()
+    };
+    let mut snippets = vec![
+        snippet("license", None, "MIT"),
+        snippet("license", Some("python"), "# MIT"),
+        snippet("header", Some("python"), "{{> license}}\n# {{> author }}"),
+        snippet("author", None, "by {{>missing}}"),
+        snippet("loop", None, "{{> loop}}"),
+    ];
+    resolve_includes(&mut snippets);
+
+    assert_eq!(snippets[2].body, "# MIT\n# by {{>missing}}");
+    assert_eq!(snippets[4].body, "{{> loop}}");
+}
+
+#[test]
+fn snippets_usage() -> anyhow::Result<()> {
+    use simple_completion_language_server::snippets::usage::SnippetUsage;
+
+    let path = std::path::PathBuf::from("/tmp/scls-test-usage/snippets-usage.json");
+    let _ = std::fs::remove_dir_all("/tmp/scls-test-usage");
+
+    let mut usage = SnippetUsage::load(&path)?;
+    usage.record("fn");
+    usage.record("for");
+    usage.record("for");
+    usage.save()?;
+
+    let usage = SnippetUsage::load(&path)?;
+    assert_eq!(usage.top(), vec![("for", 2), ("fn", 1)]);
+    assert!(usage.sort_text("for") < usage.sort_text("fn"));
+    assert!(usage.sort_text("fn").is_some_and(|s| s.as_str() < "fn"));
+    assert_eq!(usage.sort_text("if"), None);
+
+    Ok(())
+}
+
+#[test]
+fn cli_fetch_external_snippets_offline() -> anyhow::Result<()> {
+    let config_dir = std::path::PathBuf::from("/tmp/scls-test-fetch-offline");
+    let _ = std::fs::remove_dir_all(&config_dir);
+    std::fs::create_dir_all(config_dir.join("external-snippets/example.com/cloned/.git"))?;
+    std::fs::write(
+        config_dir.join("external-snippets/example.com/cloned/.git/HEAD"),
+        "ref: refs/heads/main\n",
+    )?;
+    let config_path = config_dir.join("external-snippets.toml");
+    let write_config = |repos: &[&str]| {
+        std::fs::write(
+            &config_path,
+            repos
+                .iter()
+                .map(|r| format!("[[sources]]\ngit = \"https://example.com/{r}\"\npaths = []\n"))
+                .collect::<String>(),
+        )
+    };
+    let fetch = |args: &[&str]| -> anyhow::Result<bool> {
+        Ok(
+            std::process::Command::new(env!("CARGO_BIN_EXE_simple-completion-language-server"))
+                .arg("fetch-external-snippets")
+                .args(args)
+                .env("EXTERNAL_SNIPPETS_CONFIG", &config_path)
+                .stderr(std::process::Stdio::null())
+                .status()?
+                .success(),
+        )
+    };
+
+    write_config(&["cloned", "missing"])?;
+    assert!(fetch(&["--dry-run"])?);
+    assert!(!config_dir
+        .join("external-snippets/example.com/missing")
+        .exists());
+    assert!(!fetch(&["--offline"])?);
+
+    write_config(&["cloned"])?;
+    assert!(fetch(&["--offline"])?);
+
+    Ok(())
+}
+
+#[test]
+fn search_snippets() -> anyhow::Result<()> {
+    use simple_completion_language_server::snippets::search::search_snippets;
+    use simple_completion_language_server::StartOptions;
+
+    let snippets_path = std::path::PathBuf::from("/tmp/scls-test-search-snippets");
+    let _ = std::fs::remove_dir_all(&snippets_path);
+    std::fs::create_dir_all(&snippets_path)?;
+    std::fs::write(
+        snippets_path.join("rust.toml"),
+        r#"
+[[snippets]]
+prefix = "fn"
+body = "fn ${1:name}() {}"
+description = "function"
+
+[[snippets]]
+prefix = "dbg"
+body = "dbg!($1)"
+
+[[snippets]]
+prefix = "fmt_display"
+body = "impl fmt::Display for $1 {}"
+"#,
+    )?;
+
+    let matches = search_snippets(
+        &StartOptions {
+            home_dir: "/tmp".to_string(),
+            snippets_paths: vec![snippets_path.clone()],
+            external_snippets_config_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+            unicode_input_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+            words_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+            snippets_usage_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+            acceptance_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+            warm_cache_path: std::path::PathBuf::from("/tmp/scls-not-exists"),
+        },
+        "fmtdisp",
+    )?;
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].snippet.prefix, "fmt_display");
+    assert_eq!(
+        matches[0].source,
+        snippets_path.join("rust.toml").display().to_string()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cli_init() -> anyhow::Result<()> {
+    let config_dir = std::path::PathBuf::from("/tmp/scls-test-init");
+    let _ = std::fs::remove_dir_all(&config_dir);
+
+    let run = |cmd: &str| -> anyhow::Result<bool> {
+        Ok(
+            std::process::Command::new(env!("CARGO_BIN_EXE_simple-completion-language-server"))
+                .arg(cmd)
+                .env("SNIPPETS_PATH", config_dir.join("snippets"))
+                .env("UNICODE_INPUT_PATH", config_dir.join("unicode-input"))
+                .env(
+                    "EXTERNAL_SNIPPETS_CONFIG",
+                    config_dir.join("external-snippets.toml"),
+                )
+                .stderr(std::process::Stdio::null())
+                .status()?
+                .success(),
+        )
+    };
+
+    assert!(run("init")?);
+    assert!(config_dir.join("snippets/snippets.toml").exists());
+    assert!(config_dir.join("unicode-input/base.toml").exists());
+    assert!(config_dir.join("external-snippets.toml").exists());
+
+    // keep user changes
+    std::fs::write(config_dir.join("unicode-input/base.toml"), "a = \"b\"\n")?;
+    assert!(run("init")?);
+    assert_eq!(
+        std::fs::read_to_string(config_dir.join("unicode-input/base.toml"))?,
+        "a = \"b\"\n"
+    );
+
+    // starter files are valid
+    assert!(run("validate-snippets")?);
+    assert!(run("validate-unicode-input")?);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn words_exclude() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"words_exclude":["hello"]}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"hello help\nhe","uri":"file:///tmp/words_exclude.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/words_exclude.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["help"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn analyze_words() -> anyhow::Result<()> {
+    let path = std::path::PathBuf::from("/tmp/scls-test-analyze");
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(path.join("target"))?;
+    std::fs::write(path.join("a.rs"), "let value = 1;\nlet other = value;")?;
+    std::fs::write(path.join("b.rs"), "let value = 0x1f2e3d4c5b6a;")?;
+    std::fs::write(path.join("target/c.rs"), "let let let let")?;
+
+    let analysis = simple_completion_language_server::analyze::analyze(&path)?;
+
+    assert_eq!(analysis.files, 2);
+    assert_eq!(
+        analysis
+            .top(2)
+            .map(|(w, s)| (w.as_str(), s.count, s.files))
+            .collect::<Vec<_>>(),
+        vec![("let", 3, 2), ("value", 3, 2)]
+    );
+    assert_eq!(
+        analysis.suggested_exclude(10).collect::<Vec<_>>(),
+        vec!["let", "0x1f2e3d4c5b6a"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn snippet_index() {
+    use simple_completion_language_server::snippets::index::SnippetIndex;
+
+    let snippet = |prefix: &str, scope: Option<Vec<&str>>| snippets::Snippet {
+        scope: scope.map(|s| s.into_iter().map(String::from).collect()),
+        prefix: prefix.to_string(),
+        body: prefix.to_string(),
+        description: None,
+        ..Default::default()
+    };
+    let snippets = vec![
+        snippet("mb", None),
+        snippet("main", Some(vec!["python", "rust"])),
+        snippet("ma", Some(vec![])),
+        snippet("malloc", Some(vec!["c"])),
+        snippet("m", None),
+    ];
+    let index = SnippetIndex::new(&snippets);
+
+    assert_eq!(index.lookup("ma", "rust"), vec![1, 2]);
+    assert_eq!(index.lookup("ma", "c"), vec![2, 3]);
+    assert_eq!(index.lookup("m", "go"), vec![0, 2, 4]);
+    assert_eq!(index.lookup("x", "rust"), Vec::<usize>::new());
+}
+
+#[test]
+fn snippets_unique_scope() -> anyhow::Result<()> {
+    let path = std::path::PathBuf::from("/tmp/scls-test-unique-scope/python.toml");
+    std::fs::create_dir_all("/tmp/scls-test-unique-scope")?;
+    std::fs::write(
+        &path,
+        "[[snippets]]\nprefix = \"ld\"\nscope = [\"python\", \"django\"]\nbody = \"log.debug\"\n",
+    )?;
+
+    let snippets = snippets::config::load_snippets_from_file(&path, &None)?;
+
+    assert_eq!(
+        snippets[0].scope,
+        Some(vec!["python".to_string(), "django".to_string()])
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn resident_documents() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"max_resident_documents":1}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"hello helix","uri":"file:///tmp/scls-test-resident-a.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"he","uri":"file:///tmp/scls-test-resident-b.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":0},"textDocument":{"uri":"file:///tmp/scls-test-resident-b.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    let mut labels = items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+    labels.sort();
+    assert_eq!(labels, vec!["helix", "hello"]);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn evicted_documents() -> anyhow::Result<()> {
+    std::fs::write("/tmp/scls-test-evicted-a.py", "hello helix\n")?;
+    let _ = std::fs::remove_file("/tmp/scls-test-evicted-c.py");
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"workspace/didChangeConfiguration","params":{"settings":{"max_resident_documents":1}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"hello helix\n","uri":"file:///tmp/scls-test-evicted-a.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"","uri":"file:///tmp/scls-test-evicted-b.py","version":0}}}"#,
+        // evicted document is read from disk, change is applied after it
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/scls-test-evicted-a.py","version":1},"contentChanges":[{"range":{"start":{"line":1,"character":0},"end":{"line":1,"character":0}},"text":"he"}]}}"#,
+        // changed document isn't evicted, its text exists in client only
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"hero\n","uri":"file:///tmp/scls-test-evicted-c.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/scls-test-evicted-c.py","version":1},"contentChanges":[{"range":{"start":{"line":1,"character":0},"end":{"line":1,"character":0}},"text":"he"}]}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"","uri":"file:///tmp/scls-test-evicted-d.py","version":0}}}"#,
+    ]).await?;
+
+    let mut id = 3;
+    // words of all documents, typed prefix is found in both
+    for uri in [
+        "file:///tmp/scls-test-evicted-c.py",
+        "file:///tmp/scls-test-evicted-a.py",
+    ] {
+        let mut labels = Vec::new();
+        for _ in 0..50 {
+            context.send_all(&[
+                &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":2,"line":1}},"textDocument":{{"uri":"{uri}"}}}},"id":{id}}}"#),
+            ]).await?;
+            id += 1;
+            // incomplete while evicted document is read
+            if let lsp_types::CompletionResponse::Array(items) =
+                context.recv::<lsp_types::CompletionResponse>().await?
+            {
+                labels = items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+                labels.sort();
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(labels, vec!["helix", "hello", "hero"]);
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn save_doc_from_disk() -> anyhow::Result<()> {
+    std::fs::write("/tmp/scls-test-save.py", "hello\nhe")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"\nhe","uri":"file:///tmp/scls-test-save.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didSave","params":{"textDocument":{"uri":"file:///tmp/scls-test-save.py"}}}"#,
+    ]).await?;
+
+    // content is read in background
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/scls-test-save.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["hello"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn out_of_order_change() -> anyhow::Result<()> {
+    std::fs::write("/tmp/scls-test-resync.py", "hello\nhe")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"\nhe","uri":"file:///tmp/scls-test-resync.py","version":2}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/scls-test-resync.py","version":1},"contentChanges":[{"range":{"start":{"line":0,"character":0},"end":{"line":0,"character":0}},"text":"help"}]}}"#,
+    ]).await?;
+
+    // content is re-read in background
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/scls-test-resync.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["hello"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn rapid_changes() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"h\nhe","uri":"file:///tmp/rapid.py","version":0}}}"#,
+    ]).await?;
+
+    // more changes than backend queue capacity, type and erase char
+    for version in 1..=100 {
+        let change = if version % 2 == 1 {
+            r#"{"range":{"start":{"line":0,"character":1},"end":{"line":0,"character":1}},"text":"x"}"#
+        } else {
+            r#"{"range":{"start":{"line":0,"character":1},"end":{"line":0,"character":2}},"text":""}"#
+        };
+        context.send_all(&[&format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didChange","params":{{"textDocument":{{"uri":"file:///tmp/rapid.py","version":{version}}},"contentChanges":[{change}]}}}}"#
+        )]).await?;
+    }
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/rapid.py","version":101},"contentChanges":[{"range":{"start":{"line":0,"character":1},"end":{"line":0,"character":1}},"text":"ello"}]}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/rapid.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["hello"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn superseded_completions() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"hello\nh","uri":"file:///tmp/superseded.py","version":0}}}"#,
+    ]).await?;
+    // type `hell` requesting completion after each char
+    for (i, ch) in "ell".chars().enumerate() {
+        let (column, version, id) = (i + 1, i + 1, i + 3);
+        context.send_all(&[
+            &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/completion","params":{{"position":{{"character":{column},"line":1}},"textDocument":{{"uri":"file:///tmp/superseded.py"}}}},"id":{id}}}"#),
+            &format!(r#"{{"jsonrpc":"2.0","method":"textDocument/didChange","params":{{"textDocument":{{"uri":"file:///tmp/superseded.py","version":{version}}},"contentChanges":[{{"range":{{"start":{{"line":1,"character":{column}}},"end":{{"line":1,"character":{column}}}}},"text":"{ch}"}}]}}}}"#),
+        ]).await?;
+    }
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":4,"line":1},"textDocument":{"uri":"file:///tmp/superseded.py"}},"id":6}"#,
+    ]).await?;
+
+    // outdated requests are answered by empty incomplete list
+    let mut responses = Vec::new();
+    for _ in 3..7 {
+        responses.push(context.recv::<lsp_types::CompletionResponse>().await?);
+    }
+    for (i, response) in responses.into_iter().enumerate() {
+        match response {
+            lsp_types::CompletionResponse::Array(items) => {
+                assert_eq!(
+                    items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+                    vec!["hello"]
+                );
+            }
+            lsp_types::CompletionResponse::List(list) if i < 3 => {
+                assert!(list.is_incomplete && list.items.is_empty());
+            }
+            response => anyhow::bail!("last completion is answered, got {response:?}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn incremental_word_index() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"hello help\nheap\nhelium\nhe","uri":"file:///tmp/index.py","version":0}}}"#,
+        // join first two lines dropping `help` and `heap`, then rename `helium`
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/index.py","version":1},"contentChanges":[{"range":{"start":{"line":0,"character":5},"end":{"line":1,"character":4}},"text":" hero"}]}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/index.py","version":2},"contentChanges":[{"range":{"start":{"line":1,"character":0},"end":{"line":1,"character":6}},"text":"helix\nhelm"}]}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":3},"textDocument":{"uri":"file:///tmp/index.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    let mut labels = items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+    labels.sort();
+    assert_eq!(labels, vec!["helix", "hello", "helm", "hero"]);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn utf16_positions() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        Vec::new(),
+        HashMap::from_iter([("alpha".to_string(), "α".to_string())]),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"🔥 alp","uri":"file:///tmp/utf16.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":6,"line":0},"textDocument":{"uri":"file:///tmp/utf16.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items
+            .into_iter()
+            .filter_map(|i| match i.text_edit {
+                Some(lsp_types::CompletionTextEdit::InsertAndReplace(te)) => {
+                    Some((
+                        te.new_text,
+                        te.replace.start.character,
+                        te.replace.end.character,
+                    ))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec![("α".to_string(), 3, 6)]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn word_with_combining_marks() {
+    use simple_completion_language_server::char_is_word;
+
+    // "e" + combining acute accent
+    assert!("cafe\u{301}".chars().all(char_is_word));
+    assert!(!char_is_word(' '));
+}
+
+#[test]
+fn words_on_grapheme_boundaries() {
+    use simple_completion_language_server::split_words;
+
+    // flag is a pair of regional indicators, heart is followed by variation selector,
+    // decomposed Hangul is a sequence of conjoining jamo
+    assert_eq!(
+        split_words("flag\u{1F1FA}\u{1F1E6} love\u{2764}\u{FE0F}ly \u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}!")
+            .collect::<Vec<_>>(),
+        vec![
+            "flag",
+            "love",
+            "ly",
+            "\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}"
+        ]
+    );
+}
+
+#[test_log::test(tokio::test)]
+async fn decomposed_hangul_word() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"\u1112\u1161\u11ab\u1100\u1173\u11af\ud83c\uddf0\ud83c\uddf7\n\u1112\u1161","uri":"file:///tmp/hangul.md","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/hangul.md"}},"id":3}"#
+    ]).await?;
+
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    assert_eq!(
+        items
+            .into_iter()
+            .filter(|i| i.kind == Some(lsp_types::CompletionItemKind::TEXT))
+            .map(|i| i.label)
+            .collect::<Vec<_>>(),
+        vec!["\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn full_content_change() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"","uri":"file:///tmp/full_change.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/full_change.py","version":1},"contentChanges":[{"text":"hello\nhe"}]}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/full_change.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["hello"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn full_sync() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{},"initializationOptions":{"full_sync":true}}))
+        .finish();
+    let response = context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+    let Some(lsp_types::TextDocumentSyncCapability::Options(sync)) =
+        response.capabilities.text_document_sync
+    else {
+        anyhow::bail!("text document sync options expected")
+    };
+    assert_eq!(sync.change, Some(lsp_types::TextDocumentSyncKind::FULL));
+
+    // changes before full content are never applied
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"","uri":"file:///tmp/full_sync.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///tmp/full_sync.py","version":1},"contentChanges":[{"range":{"start":{"line":10,"character":0},"end":{"line":10,"character":0}},"text":"x"},{"text":"hello\nhe"}]}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/full_sync.py"}},"id":3}"#
+    ]).await?;
+
+    let lsp_types::CompletionResponse::Array(items) =
+        context.recv::<lsp_types::CompletionResponse>().await?
+    else {
+        anyhow::bail!("completion array expected")
+    };
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["hello"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn full_sync_detection() -> anyhow::Result<()> {
+    for (params, expected) in [
+        (
+            serde_json::json!({"capabilities":{}}),
+            lsp_types::TextDocumentSyncKind::FULL,
+        ),
+        (
+            serde_json::json!({"capabilities":{"textDocument":{"synchronization":{"didSave":true}}},"clientInfo":{"name":"acme-lsp"}}),
+            lsp_types::TextDocumentSyncKind::FULL,
+        ),
+        (
+            serde_json::json!({"capabilities":{"textDocument":{"synchronization":{"didSave":true}}}}),
+            lsp_types::TextDocumentSyncKind::INCREMENTAL,
+        ),
+        // detection is overridden by settings
+        (
+            serde_json::json!({"capabilities":{},"initializationOptions":{"full_sync":false}}),
+            lsp_types::TextDocumentSyncKind::INCREMENTAL,
+        ),
+    ] {
+        let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+        let request = jsonrpc::Request::build("initialize")
+            .id(1)
+            .params(params)
+            .finish();
+        let response = context
+            .request::<lsp_types::InitializeResult>(&request)
+            .await?;
+        let Some(lsp_types::TextDocumentSyncCapability::Options(sync)) =
+            response.capabilities.text_document_sync
+        else {
+            anyhow::bail!("text document sync options expected")
+        };
+        assert_eq!(sync.change, Some(expected));
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn untitled_document() -> anyhow::Result<()> {
+    let mut context = TestContext::new(
+        vec![snippets::Snippet {
+            scope: None,
+            prefix: "hey".to_string(),
+            body: "hey there".to_string(),
+            description: None,
+            ..Default::default()
+        }],
+        HashMap::new(),
+        String::new(),
+    )
+    .await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"markdown","text":"hello\nhe","uri":"untitled:Untitled-1","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didSave","params":{"textDocument":{"uri":"untitled:Untitled-1"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"untitled:Untitled-1"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["hello", "hey"]
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn percent_encoded_uri() -> anyhow::Result<()> {
+    std::fs::write("/tmp/scls test é.py", "hello\nhe")?;
+
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"\nhe","uri":"file:///tmp/scls%20test%20%C3%A9.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didSave","params":{"textDocument":{"uri":"file:///tmp/scls%20test%20%C3%A9.py"}}}"#,
+    ]).await?;
+
+    // content is read in background
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/scls%20test%20%C3%A9.py"}},"id":3}"#
+    ]).await?;
+
+    let response = context.recv::<lsp_types::CompletionResponse>().await?;
+
+    let lsp_types::CompletionResponse::Array(items) = response else {
+        anyhow::bail!("completion array expected")
+    };
+
+    assert_eq!(
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>(),
+        vec!["hello"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn json_log_format() -> anyhow::Result<()> {
+    std::fs::create_dir_all("/tmp/scls-test-json-log")?;
+    std::fs::write("/tmp/scls-test-json-log/base.toml", "alpha = \"α\"\n")?;
+
+    let output =
+        std::process::Command::new(env!("CARGO_BIN_EXE_simple-completion-language-server"))
+            .arg("validate-unicode-input")
+            .env("UNICODE_INPUT_PATH", "/tmp/scls-test-json-log")
+            .env("LOG_FORMAT", "json")
+            .env("RUST_LOG", "info")
+            .output()?;
+    assert!(output.status.success());
+
+    let lines = String::from_utf8(output.stderr)?
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+    let last = lines
+        .last()
+        .ok_or_else(|| anyhow::anyhow!("log expected"))?;
+    assert_eq!(last["level"], "INFO");
+    assert_eq!(last["fields"]["message"], "Successful. Total: 1");
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn source_stats() -> anyhow::Result<()> {
+    let (_tx, mut state) = simple_completion_language_server::BackendState::new(
+        "/tmp".to_string(),
+        Vec::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .await;
+    state.open_doc(serde_json::from_str(
+        r#"{"textDocument":{"languageId":"python","text":"hello\nhe","uri":"file:///tmp/stats.py","version":0}}"#,
+    )?);
+    let items = state.completion_items(&serde_json::from_str(
+        r#"{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/stats.py"}}"#,
+    )?)?;
+    assert_eq!(items.len(), 1);
+
+    let stats = state.stats();
+    let words = stats
+        .sources
+        .get("words")
+        .ok_or_else(|| anyhow::anyhow!("words stats expected"))?;
+    assert_eq!((words.requests, words.items), (1, 1));
+    assert!(stats.summary().contains("words: avg"));
+
+    Ok(())
+}
+
+#[test]
+fn adaptive_limit() {
+    use simple_completion_language_server::stats::tune_limit;
+    use std::time::Duration;
+
+    let target = Duration::from_millis(50);
+    assert_eq!(tune_limit(20, 20, Duration::from_millis(80), target), 10);
+    assert_eq!(tune_limit(10, 20, Duration::from_millis(80), target), 5);
+    assert_eq!(tune_limit(5, 20, Duration::from_millis(80), target), 5);
+    assert_eq!(tune_limit(5, 20, Duration::from_millis(40), target), 5);
+    assert_eq!(tune_limit(5, 20, Duration::from_millis(10), target), 10);
+    assert_eq!(tune_limit(10, 20, Duration::from_millis(10), target), 20);
+    assert_eq!(tune_limit(20, 20, Duration::from_millis(10), target), 20);
+    assert_eq!(tune_limit(3, 3, Duration::from_millis(80), target), 3);
+}
+
+#[test]
+fn prometheus_metrics() {
+    use simple_completion_language_server::stats::CompletionStats;
+
+    let mut stats = CompletionStats::default();
+    stats.completion_requests = 2;
+    stats.automata_hits = 1;
+    stats.completion_cache_hits = 3;
+    stats.completion_superseded = 4;
+    stats.record("words", std::time::Duration::from_millis(3), 4);
+    stats.record("words", std::time::Duration::from_millis(70), 1);
+
+    let metrics = stats.prometheus(3, 1, 2);
+    assert!(metrics.contains("scls_completion_requests_total 2\n"));
+    assert!(
+        metrics.contains("scls_source_latency_seconds_bucket{source=\"words\",le=\"0.001\"} 0\n")
+    );
+    assert!(
+        metrics.contains("scls_source_latency_seconds_bucket{source=\"words\",le=\"0.005\"} 1\n")
+    );
+    assert!(metrics.contains("scls_source_latency_seconds_bucket{source=\"words\",le=\"0.1\"} 2\n"));
+    assert!(metrics.contains("scls_source_latency_seconds_count{source=\"words\"} 2\n"));
+    assert!(metrics.contains("scls_source_items_total{source=\"words\"} 5\n"));
+    assert!(metrics.contains("scls_automata_cache_hits_total 1\n"));
+    assert!(metrics.contains("scls_completion_cache_hits_total 3\n"));
+    assert!(metrics.contains("scls_completion_superseded_total 4\n"));
+    assert!(metrics.contains("scls_queue_depth 2\n"));
+    assert!(metrics.contains("scls_documents{state=\"resident\"} 3\n"));
+    assert!(metrics.contains("scls_documents{state=\"evicted\"} 1\n"));
+}
+
+#[test]
+fn log_size_rotation() -> anyhow::Result<()> {
+    use simple_completion_language_server::logging::{expand_log_path, SizeRotatingWriter};
+    use std::io::Write;
+
+    let dir = std::path::PathBuf::from("/tmp/scls-test-log-rotation");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    let mut writer = SizeRotatingWriter::new(dir.join("scls.log"), 10, 2)?;
+    for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+        writer.write_all(line.as_bytes())?;
+    }
+    writer.flush()?;
+
+    assert_eq!(std::fs::read_to_string(dir.join("scls.log"))?, "fourth\n");
+    assert_eq!(std::fs::read_to_string(dir.join("scls.log.1"))?, "third\n");
+    assert_eq!(std::fs::read_to_string(dir.join("scls.log.2"))?, "second\n");
+    assert!(!dir.join("scls.log.3").exists());
+
+    assert_eq!(
+        expand_log_path("/tmp/scls-{pid}.log"),
+        format!("/tmp/scls-{}.log", std::process::id())
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn client_log_level() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+
+    let request = jsonrpc::Request::build("initialize")
+        .id(1)
+        .params(serde_json::json!({"capabilities":{},"initializationOptions":{"client_log_level":"off"}}))
+        .finish();
+    context
+        .request::<lsp_types::InitializeResult>(&request)
+        .await?;
+    context.send_all(&[
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"languageId":"python","text":"hello\nhe","uri":"file:///tmp/log_level.py","version":0}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/completion","params":{"position":{"character":2,"line":1},"textDocument":{"uri":"file:///tmp/log_level.py"}},"id":3}"#
+    ]).await?;
+
+    // read until completion response
+    let mut messages = Vec::new();
+    while !messages.iter().any(|m: &String| m.contains("\"id\":3")) {
+        messages.push(
+            context
+                .response_rx
+                .recv()
+                .await
+                .ok_or_else(|| anyhow::anyhow!("empty response"))?,
+        );
+    }
+
+    assert!(!messages.iter().any(|m| m.contains("window/logMessage")));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn ping() -> anyhow::Result<()> {
+    let mut context = TestContext::new(Vec::new(), HashMap::new(), String::new()).await?;
+    context.initialize().await?;
+
+    let request = jsonrpc::Request::build("scls/ping").id(2).finish();
+    let response = context.request::<serde_json::Value>(&request).await?;
+
+    assert_eq!(response["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(response["queue_depth"], 0);
+    assert!(response["uptime_secs"].is_u64());
+    let features = response["features"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("features expected"))?;
+    assert!(features.contains(&serde_json::json!("words")));
+    assert!(!features.contains(&serde_json::json!("thesaurus")));
+
+    Ok(())
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn profile_spans() {
+    use simple_completion_language_server::profile::{ProfileLayer, PROFILER};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let subscriber = tracing_subscriber::registry().with(ProfileLayer);
+    tracing::subscriber::with_default(subscriber, || {
+        assert!(PROFILER.start());
+        assert!(!PROFILER.start());
+        let _span = tracing::info_span!("source", source = "words").entered();
+    });
+
+    let trace = PROFILER.stop().unwrap_or_default();
+    let events = trace["traceEvents"].as_array().cloned().unwrap_or_default();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["name"], "source");
+    assert_eq!(events[0]["ph"], "B");
+    assert_eq!(events[0]["args"]["source"], "words");
+    assert_eq!(events[1]["ph"], "E");
+}
+
+#[cfg(feature = "otlp")]
+#[tokio::test]
+async fn otlp_export() -> anyhow::Result<()> {
+    use simple_completion_language_server::otlp::OtlpLayer;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let endpoint = format!("http://{}", listener.local_addr()?);
+
+    let subscriber = tracing_subscriber::registry().with(OtlpLayer::new(endpoint));
+    tracing::subscriber::with_default(subscriber, || {
+        let _parent = tracing::info_span!("completion").entered();
+        let _span = tracing::info_span!("source", source = "words").entered();
+    });
+
+    let (mut stream, _) = listener.accept().await?;
+    let mut request = Vec::new();
+    let mut buf = [0; 4096];
+    while !String::from_utf8_lossy(&request).ends_with("}]}]}]}") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    stream
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+        .await?;
+
+    let request = String::from_utf8(request)?;
+    assert!(request.starts_with("POST /v1/traces HTTP/1.1"));
+    let body = request
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body)
+        .unwrap_or_default();
+    let body = serde_json::from_str::<serde_json::Value>(body)?;
+    let spans = &body["resourceSpans"][0]["scopeSpans"][0]["spans"];
+    assert_eq!(spans[0]["name"], "source");
+    assert_eq!(spans[0]["attributes"][0]["value"]["stringValue"], "words");
+    assert_eq!(spans[0]["traceId"], spans[1]["traceId"]);
+    assert_eq!(spans[0]["parentSpanId"], spans[1]["spanId"]);
+
+    Ok(())
+}
+
+#[test]
+fn warm_cache() -> anyhow::Result<()> {
+    use simple_completion_language_server::warm::{mtime, WarmCache, WarmDocument};
+
+    let dir = std::env::temp_dir().join("scls-test-warm-cache");
+    std::fs::create_dir_all(&dir)?;
+    let unchanged = dir.join("unchanged.rs");
+    let changed = dir.join("changed.rs");
+    std::fs::write(&unchanged, "alpha beta")?;
+    std::fs::write(&changed, "gamma")?;
+
+    let cache = WarmCache {
+        roots: vec![dir.clone()],
+        docs: vec![
+            WarmDocument {
+                mtime: mtime(&unchanged).unwrap(),
+                path: unchanged.clone(),
+                language_id: "rust".to_string(),
+                words: vec!["alpha".to_string(), "beta".to_string()],
+            },
+            WarmDocument {
+                mtime: mtime(&changed).unwrap() - 1,
+                path: changed,
+                language_id: "rust".to_string(),
+                words: vec!["gamma".to_string()],
+            },
+        ],
+    };
+    let path = dir.join("warm-cache.json");
+    cache.save(&path)?;
+
+    let docs = WarmCache::load(&path, std::slice::from_ref(&dir))?;
+    assert_eq!(
+        docs.iter().map(|d| &d.path).collect::<Vec<_>>(),
+        vec![&unchanged]
+    );
+    assert_eq!(docs[0].words, vec!["alpha", "beta"]);
+
+    // other workspace sessions are not preloaded
+    assert!(WarmCache::load(&path, &[std::path::PathBuf::from("/")])?.is_empty());
+    assert!(WarmCache::load(&path, &[])?.is_empty());
+    assert!(WarmCache::load(&dir.join("not-exists.json"), std::slice::from_ref(&dir))?.is_empty());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}